@@ -0,0 +1,5 @@
+-- Coordinates for map-based search, filled in by the offline geocoder.
+ALTER TABLE house_listings
+    ADD COLUMN hlat DOUBLE NULL,
+    ADD COLUMN hlng DOUBLE NULL,
+    ADD INDEX idx_house_listings_coords (hlat, hlng);
@@ -0,0 +1,11 @@
+-- Free-text description shown on the detail page and indexed for search.
+ALTER TABLE house_listings
+    ADD COLUMN hdesc TEXT NOT NULL DEFAULT ('');
+
+-- Word-segmented text of each house. The ngram parser keeps two-character
+-- Chinese words searchable regardless of innodb_ft_min_token_size.
+CREATE TABLE house_search_index (
+    hno INT UNSIGNED NOT NULL PRIMARY KEY,
+    content TEXT NOT NULL,
+    FULLTEXT INDEX ft_house_search_content (content) WITH PARSER ngram
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4;
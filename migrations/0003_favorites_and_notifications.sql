@@ -0,0 +1,18 @@
+CREATE TABLE favorites (
+    uno INT UNSIGNED NOT NULL,
+    hno INT UNSIGNED NOT NULL,
+    fdate DATETIME NOT NULL,
+    PRIMARY KEY (uno, hno),
+    INDEX idx_favorites_hno (hno)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4;
+
+CREATE TABLE notifications (
+    nno INT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    uno INT UNSIGNED NOT NULL,
+    hno INT UNSIGNED NOT NULL,
+    ntype INT NOT NULL,
+    ncontent VARCHAR(255) NOT NULL,
+    ndate DATETIME NOT NULL,
+    nread BOOLEAN NOT NULL DEFAULT FALSE,
+    INDEX idx_notifications_uno (uno, nread)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4;
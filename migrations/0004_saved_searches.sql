@@ -0,0 +1,16 @@
+CREATE TABLE saved_searches (
+    sno INT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    uno INT UNSIGNED NOT NULL,
+    sname VARCHAR(64) NOT NULL,
+    squery VARCHAR(1024) NOT NULL,
+    sfrequency INT NOT NULL,
+    sdate DATETIME NOT NULL,
+    INDEX idx_saved_searches_uno (uno)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4;
+
+CREATE TABLE saved_search_matches (
+    sno INT UNSIGNED NOT NULL,
+    hno INT UNSIGNED NOT NULL,
+    mdate DATETIME NOT NULL,
+    PRIMARY KEY (sno, hno)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4;
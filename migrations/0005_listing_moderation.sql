@@ -0,0 +1,4 @@
+-- hunlisted gains Draft (2), PendingReview (3) and Rejected (4).
+ALTER TABLE house_listings
+    ADD COLUMN hreason VARCHAR(255) NULL,
+    ADD INDEX idx_house_listings_hunlisted (hunlisted);
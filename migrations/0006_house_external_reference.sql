@@ -0,0 +1,3 @@
+ALTER TABLE house_listings
+    ADD COLUMN hextref VARCHAR(64) NULL,
+    ADD UNIQUE INDEX uniq_house_listings_extref (hlandlore, hextref);
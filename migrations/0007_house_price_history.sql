@@ -0,0 +1,11 @@
+CREATE TABLE house_price_history (
+    pno INT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    hno INT UNSIGNED NOT NULL,
+    pprice INT UNSIGNED NOT NULL,
+    pdate DATETIME NOT NULL,
+    INDEX idx_house_price_history_hno (hno, pdate)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4;
+
+-- Current prices are the first known point of every existing house.
+INSERT INTO house_price_history (hno, pprice, pdate)
+SELECT hno, hprice, NOW() FROM house_listings;
@@ -0,0 +1,30 @@
+CREATE TABLE house_revisions (
+    rno INT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    hno INT UNSIGNED NOT NULL,
+    reditor INT UNSIGNED NOT NULL,
+    rdate DATETIME NOT NULL,
+    rfields VARCHAR(255) NOT NULL,
+    rsnapshot TEXT NOT NULL,
+    INDEX idx_house_revisions_hno (hno, rno)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4;
+
+-- Current state of every existing house is its first revision.
+INSERT INTO house_revisions (hno, reditor, rdate, rfields, rsnapshot)
+SELECT hno, hlandlore, NOW(), '', JSON_OBJECT(
+    'hno', hno,
+    'hdistrict', hdistrict,
+    'haddr', haddr,
+    'hlo', hlo,
+    'hflr', hflr,
+    'harea', harea,
+    'hprice', hprice,
+    'hlandlore', hlandlore,
+    'hsuite', hsuite,
+    'hunlisted', ELT(hunlisted + 1, 'Listed', 'Unlisted', 'Draft', 'PendingReview',
+                     'Rejected', 'Archived', 'Deleted'),
+    'hlat', hlat,
+    'hlng', hlng,
+    'hdesc', hdesc,
+    'hreason', hreason,
+    'hextref', hextref
+) FROM house_listings;
@@ -0,0 +1,12 @@
+CREATE TABLE house_flags (
+    fno INT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    hno INT UNSIGNED NOT NULL,
+    fkind INT NOT NULL,
+    fother INT UNSIGNED NULL,
+    fdetail VARCHAR(255) NOT NULL,
+    fdate DATETIME NOT NULL,
+    fresolved BOOLEAN NOT NULL DEFAULT FALSE,
+    INDEX idx_house_flags_hno (hno, fresolved)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4;
+
+CREATE INDEX idx_house_listings_dedup ON house_listings (hdistrict, hflr, harea);
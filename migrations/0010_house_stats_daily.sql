@@ -0,0 +1,9 @@
+CREATE TABLE house_stats_daily (
+    hno INT UNSIGNED NOT NULL,
+    sdate DATE NOT NULL,
+    simpressions INT UNSIGNED NOT NULL DEFAULT 0,
+    sviews INT UNSIGNED NOT NULL DEFAULT 0,
+    sfavorites INT UNSIGNED NOT NULL DEFAULT 0,
+    srequests INT UNSIGNED NOT NULL DEFAULT 0,
+    PRIMARY KEY (hno, sdate)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4;
@@ -0,0 +1,10 @@
+-- notifications.ntype gains ListingExpiring (5) and ListingExpired (6).
+ALTER TABLE house_listings
+    ADD COLUMN hlisted_at DATETIME NULL,
+    ADD COLUMN hrefreshed_at DATETIME NULL,
+    ADD INDEX idx_house_listings_refreshed (hunlisted, hrefreshed_at);
+
+-- Houses already public start their lifetime now rather than expiring at once.
+UPDATE house_listings
+    SET hlisted_at = NOW(), hrefreshed_at = NOW()
+    WHERE hunlisted = 0;
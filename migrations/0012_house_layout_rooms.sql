@@ -0,0 +1,20 @@
+ALTER TABLE house_listings
+    ADD COLUMN hbedrooms INT UNSIGNED NULL,
+    ADD COLUMN hliving_rooms INT UNSIGNED NULL,
+    ADD COLUMN hbathrooms INT UNSIGNED NULL,
+    ADD INDEX idx_house_listings_rooms (hbedrooms, hliving_rooms, hbathrooms);
+
+-- Backfill layouts written with digits, e.g. "3室2厅1卫" or "2房1厅". Layouts
+-- in Chinese numerals are parsed when the house is next saved.
+UPDATE house_listings SET
+    hbedrooms = CAST(REGEXP_SUBSTR(REGEXP_SUBSTR(hlo, '[0-9]+[室房居]'), '[0-9]+') AS UNSIGNED),
+    hliving_rooms = CAST(REGEXP_SUBSTR(REGEXP_SUBSTR(hlo, '[0-9]+厅'), '[0-9]+') AS UNSIGNED),
+    hbathrooms = CAST(REGEXP_SUBSTR(REGEXP_SUBSTR(hlo, '[0-9]+卫'), '[0-9]+') AS UNSIGNED);
+
+-- Render the displayed layout from the parsed counts.
+UPDATE house_listings SET
+    hlo = CONCAT_WS('',
+        CONCAT(hbedrooms, '室'),
+        CONCAT(hliving_rooms, '厅'),
+        CONCAT(hbathrooms, '卫'))
+    WHERE hbedrooms IS NOT NULL OR hliving_rooms IS NOT NULL OR hbathrooms IS NOT NULL;
@@ -0,0 +1,15 @@
+-- Regions listings are filed under: city (0) > district (1) > neighborhood (2).
+CREATE TABLE regions (
+    rno INT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    rparent INT UNSIGNED NULL,
+    rname VARCHAR(64) NOT NULL,
+    rlevel INT NOT NULL,
+    UNIQUE INDEX uniq_regions_name (rparent, rname)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4;
+
+ALTER TABLE house_listings
+    ADD COLUMN hregion INT UNSIGNED NULL,
+    ADD INDEX idx_house_listings_region (hregion);
+
+-- Filled from the gazetteer bundled with the geocoder when the application
+-- starts, which then files existing listings under their region.
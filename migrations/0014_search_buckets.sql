@@ -0,0 +1,28 @@
+-- Buckets offered by the search page; bkind 0 = floor, 1 = area, 2 = price.
+-- bindex is the 1-based value of `c`, `m` and `p` in search URLs.
+CREATE TABLE search_buckets (
+    bkind INT UNSIGNED NOT NULL,
+    bindex INT UNSIGNED NOT NULL,
+    blower INT UNSIGNED NOT NULL,
+    bupper INT UNSIGNED NULL,
+    blabel VARCHAR(32) NOT NULL,
+    PRIMARY KEY (bkind, bindex)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4;
+
+INSERT INTO search_buckets (bkind, bindex, blower, bupper, blabel) VALUES
+    (0, 1, 0, 1, '底层'),
+    (0, 2, 1, 10, '低楼层'),
+    (0, 3, 10, 20, '中楼层'),
+    (0, 4, 20, 30, '高楼层'),
+    (0, 5, 30, NULL, '顶层'),
+    (1, 1, 0, 50, '50m²以下'),
+    (1, 2, 50, 70, '50-70m²'),
+    (1, 3, 70, 90, '70-90m²'),
+    (1, 4, 90, 110, '90-110m²'),
+    (1, 5, 110, 150, '110-150m²'),
+    (2, 1, 0, 2000, '2000元以下'),
+    (2, 2, 2000, 4000, '2000-4000元'),
+    (2, 3, 4000, 6000, '4000-6000元'),
+    (2, 4, 6000, 10000, '6000-10000元'),
+    (2, 5, 10000, 15000, '10000-15000元'),
+    (2, 6, 15000, NULL, '15000元以上');
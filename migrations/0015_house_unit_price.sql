@@ -0,0 +1,8 @@
+-- Monthly rent per m², kept by the application whenever hprice or harea is
+-- saved. NULL for houses without an area.
+ALTER TABLE house_listings
+    ADD COLUMN hunit_price DOUBLE NULL,
+    ADD INDEX idx_house_listings_unit_price (hunit_price),
+    ADD INDEX idx_house_listings_district_unit_price (hdistrict, hunit_price);
+
+UPDATE house_listings SET hunit_price = ROUND(hprice / harea, 2) WHERE harea > 0;
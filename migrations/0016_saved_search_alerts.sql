@@ -0,0 +1,12 @@
+-- Saved searches already alerted of a house, so that later edits of the
+-- house do not alert them again.
+CREATE TABLE saved_search_alerts (
+    sno INT UNSIGNED NOT NULL,
+    hno INT UNSIGNED NOT NULL,
+    adate DATETIME NOT NULL,
+    PRIMARY KEY (sno, hno),
+    INDEX idx_saved_search_alerts_hno (hno)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4;
+
+INSERT IGNORE INTO saved_search_alerts (sno, hno, adate)
+SELECT sno, hno, mdate FROM saved_search_matches;
@@ -0,0 +1,6 @@
+-- Point up to which each periodic job has run, so that a restart neither
+-- misses nor repeats what happened in between.
+CREATE TABLE job_runs (
+    jname VARCHAR(64) NOT NULL PRIMARY KEY,
+    jlast DATETIME NOT NULL
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4;
@@ -0,0 +1,5 @@
+-- Revisions made by the scheduler have no editor; they were recorded under
+-- user 0 before.
+ALTER TABLE house_revisions MODIFY reditor INT UNSIGNED NULL;
+
+UPDATE house_revisions SET reditor = NULL WHERE reditor = 0;
@@ -0,0 +1,33 @@
+-- Backfill layouts written in Chinese numerals, e.g. "两室一厅" or "十二房",
+-- which 0012 left without room counts. The numerals are rewritten as digits
+-- first: single digits, then 十 with and without tens or ones around it.
+UPDATE house_listings h
+JOIN (
+    SELECT hno,
+        REGEXP_REPLACE(REGEXP_REPLACE(REGEXP_REPLACE(REGEXP_REPLACE(
+            REPLACE(REPLACE(REPLACE(REPLACE(REPLACE(REPLACE(REPLACE(REPLACE(
+                REPLACE(REPLACE(REPLACE(hlo,
+                    '零', '0'), '一', '1'), '二', '2'), '两', '2'), '三', '3'),
+                    '四', '4'), '五', '5'), '六', '6'), '七', '7'), '八', '8'), '九', '9'),
+            '([0-9])十([0-9])', '$1$2'),
+            '([0-9])十', '$10'),
+            '十([0-9])', '1$1'),
+            '十', '10') AS lo
+    FROM house_listings
+    WHERE hbedrooms IS NULL AND hliving_rooms IS NULL AND hbathrooms IS NULL
+        AND hlo REGEXP '[零一二两三四五六七八九十][室房居厅卫]'
+) n ON n.hno = h.hno
+SET
+    h.hbedrooms = CAST(REGEXP_SUBSTR(REGEXP_SUBSTR(n.lo, '[0-9]+[室房居]'), '[0-9]+') AS UNSIGNED),
+    h.hliving_rooms = CAST(REGEXP_SUBSTR(REGEXP_SUBSTR(n.lo, '[0-9]+厅'), '[0-9]+') AS UNSIGNED),
+    h.hbathrooms = CAST(REGEXP_SUBSTR(REGEXP_SUBSTR(n.lo, '[0-9]+卫'), '[0-9]+') AS UNSIGNED),
+    -- Render the room counts as digits, keeping the text after the last one.
+    h.hlo = CONCAT(
+        CONCAT_WS('',
+            CONCAT(CAST(
+                REGEXP_SUBSTR(REGEXP_SUBSTR(n.lo, '[0-9]+[室房居]'), '[0-9]+') AS UNSIGNED), '室'),
+            CONCAT(CAST(
+                REGEXP_SUBSTR(REGEXP_SUBSTR(n.lo, '[0-9]+厅'), '[0-9]+') AS UNSIGNED), '厅'),
+            CONCAT(CAST(
+                REGEXP_SUBSTR(REGEXP_SUBSTR(n.lo, '[0-9]+卫'), '[0-9]+') AS UNSIGNED), '卫')),
+        REGEXP_SUBSTR(h.hlo, '[^室房居厅卫]*$'));
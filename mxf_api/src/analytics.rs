@@ -0,0 +1,46 @@
+use rocket::serde::json::Json;
+use rocket::{Route, State};
+use sea_orm_rocket::Connection;
+
+use super::{Claims, MXFDb};
+
+use mxf_entity::errors::JieguoResponse;
+use mxf_entity::{DistrictPriceTrend, MXFError, SearchCacheStats};
+use mxf_service::{HouseService, PriceHistoryService};
+
+const DEFAULT_TREND_MONTHS: u32 = 12;
+const MAX_TREND_MONTHS: u32 = 120;
+
+#[get("/analytics/price_trends?<months>")]
+async fn price_trends(
+    months: Option<u32>,
+    conn: Connection<'_, MXFDb>,
+    price_history_service: &State<PriceHistoryService>,
+) -> Result<Json<Vec<DistrictPriceTrend>>, Json<JieguoResponse>> {
+    let trends = price_history_service
+        .get_district_trends(
+            conn.into_inner(),
+            months.unwrap_or(DEFAULT_TREND_MONTHS).clamp(1, MAX_TREND_MONTHS),
+        )
+        .await
+        .map_err(|e| e.to_json())?;
+
+    Ok(Json(trends))
+}
+
+/// Hit and miss counts of the search result cache, for admins.
+#[get("/analytics/search_cache")]
+async fn search_cache_stats(
+    user: Claims,
+    house_service: &State<HouseService>,
+) -> Result<Json<SearchCacheStats>, Json<JieguoResponse>> {
+    if !user.is_admin() {
+        return Err(MXFError::NotAdmin.to_json());
+    }
+
+    Ok(Json(house_service.search_cache().stats()))
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![price_trends, search_cache_stats]
+}
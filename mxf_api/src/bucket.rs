@@ -0,0 +1,35 @@
+use rocket::serde::json::Json;
+use rocket::{Route, State};
+use sea_orm_rocket::Connection;
+
+use super::{Claims, MXFDb};
+
+use mxf_entity::errors::JieguoResponse;
+use mxf_entity::{BucketsInput, MXFError};
+use mxf_service::{BucketService, HouseService};
+
+#[post("/buckets", format = "json", data = "<buckets_data>")]
+async fn save_buckets(
+    user: Claims,
+    conn: Connection<'_, MXFDb>,
+    bucket_service: &State<BucketService>,
+    house_service: &State<HouseService>,
+    buckets_data: Json<BucketsInput>,
+) -> Result<Json<JieguoResponse>, Json<JieguoResponse>> {
+    if !user.is_admin() {
+        return Err(MXFError::NotAdmin.to_json());
+    }
+
+    bucket_service
+        .save_buckets(conn.into_inner(), buckets_data.into_inner())
+        .await
+        .map_err(|e| e.to_json())?;
+    // Bucket numbers in cached searches now mean other ranges
+    house_service.search_cache().invalidate();
+
+    Ok(JieguoResponse::success_json())
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![save_buckets]
+}
@@ -2,6 +2,7 @@ use chrono::{Duration, Utc};
 use jsonwebtoken::errors::ErrorKind;
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use lazy_static::lazy_static;
+use mxf_entity::user::UserType;
 use mxf_entity::{MXFError, UserModel};
 use rocket::http::Status;
 use rocket::request::{FromRequest, Outcome};
@@ -57,6 +58,15 @@ impl Claims {
         }
     }
 
+    pub(crate) fn is_admin(&self) -> bool {
+        self.user.utype == UserType::Admin
+    }
+
+    /// Admins and employees
+    pub(crate) fn is_staff(&self) -> bool {
+        self.user.utype == UserType::Admin || self.user.utype == UserType::Employee
+    }
+
     /// Create a `Claims` from a 'Bearer <token>' value
     fn from_authorization(value: &str) -> Result<Self, AuthenticationError> {
         let token = value.trim();
@@ -0,0 +1,73 @@
+use rocket::http::ContentType;
+use rocket::response::stream::ByteStream;
+use rocket::serde::json::Json;
+use rocket::{Route, State};
+use sea_orm_rocket::Connection;
+
+use super::{Claims, MXFDb};
+
+use mxf_entity::errors::JieguoResponse;
+use mxf_entity::{ExportFormat, HouseFilter, MXFError};
+use mxf_service::{BucketService, ExportService, HouseService};
+
+fn content_type(format: ExportFormat) -> ContentType {
+    match format {
+        ExportFormat::Csv => ContentType::CSV,
+        ExportFormat::Jsonl => ContentType::new("application", "x-ndjson"),
+        ExportFormat::Xlsx => ContentType::new(
+            "application",
+            "vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        ),
+    }
+}
+
+/// Every house matching the filter, regardless of `page`. Only listed houses
+/// unless staff ask for `all`.
+#[get("/export?<format>&<all>&<house_filter..>")]
+async fn export_search(
+    user: Option<Claims>,
+    conn: Connection<'_, MXFDb>,
+    format: ExportFormat,
+    all: Option<bool>,
+    bucket_service: &State<BucketService>,
+    house_filter: HouseFilter<'_>,
+) -> Result<(ContentType, ByteStream![Vec<u8>]), Json<JieguoResponse>> {
+    let all = all.unwrap_or(false);
+    if all && !user.map(|u| u.is_staff()).unwrap_or(false) {
+        return Err(MXFError::NotStaff.to_json());
+    }
+    let buckets = bucket_service.get_buckets();
+    let house_filter = house_filter.with_buckets(&buckets).map_err(|e| e.to_json())?;
+
+    let select = HouseService::search_select(house_filter, !all);
+    let db = conn.into_inner().clone();
+    Ok((
+        content_type(format),
+        ByteStream(ExportService::export(db, select, format, false)),
+    ))
+}
+
+/// A landlord's portfolio. Admins may export anyone's, including deleted houses.
+#[get("/export/portfolio?<format>&<uno>")]
+async fn export_portfolio(
+    user: Claims,
+    conn: Connection<'_, MXFDb>,
+    format: ExportFormat,
+    uno: Option<u32>,
+) -> Result<(ContentType, ByteStream![Vec<u8>]), Json<JieguoResponse>> {
+    let uno = uno.unwrap_or(user.user.uno);
+    if uno != user.user.uno && !user.is_admin() {
+        return Err(MXFError::NotAdmin.to_json());
+    }
+
+    let select = HouseService::portfolio_select(uno, user.is_admin());
+    let db = conn.into_inner().clone();
+    Ok((
+        content_type(format),
+        ByteStream(ExportService::export(db, select, format, true)),
+    ))
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![export_search, export_portfolio]
+}
@@ -5,8 +5,14 @@ use rocket::serde::json::Json;
 use super::{Claims, MXFDb};
 
 use mxf_entity::errors::JieguoResponse;
-use mxf_service::HouseService;
-use mxf_entity::HouseListingModel;
+use mxf_service::{
+    BucketService, CalendarService, ExpiryService, HouseService, RecommendationService,
+    RentEstimateService,
+};
+use mxf_entity::{
+    AvailabilityCalendar, Cursor, CursorPage, HnoData, HouseFilter, HouseListingModel, ListingInput,
+    MXFError, RentEstimate, ReviewData, RnoData,
+};
 
 
 #[post("/new", data = "<house_data>")]
@@ -14,12 +20,16 @@ async fn new_house(
     user: Claims,
     conn: Connection<'_, MXFDb>,
     house_service: &State<HouseService>,
-    house_data: Json<HouseListingModel>,
+    house_data: Json<ListingInput>,
 ) -> Result<Json<JieguoResponse>, Json<JieguoResponse>> {
     let db = conn.into_inner();
+    let house = house_service
+        .validate_listing(db, house_data.into_inner())
+        .await
+        .map_err(|e| e.to_json())?;
 
     let hno = house_service
-        .new_house(db, house_data.into_inner(), user.user.uno)
+        .new_house(db, house, user.user.uno)
         .await
         .map_err(|e| e.to_json())?;
     println!("New: {}", hno);
@@ -27,6 +37,7 @@ async fn new_house(
     Ok(Json(JieguoResponse {
         jieguo: true,
         reason: Some(hno.to_string()),
+        errors: Vec::new(),
     }))
 }
 
@@ -35,21 +46,301 @@ async fn modify_house(
     user: Claims,
     conn: Connection<'_, MXFDb>,
     house_service: &State<HouseService>,
-    house_data: Json<HouseListingModel>
+    house_data: Json<ListingInput>,
 ) -> Result<Json<JieguoResponse>, Json<JieguoResponse>> {
     let db = conn.into_inner();
+    let house = house_service
+        .validate_listing(db, house_data.into_inner())
+        .await
+        .map_err(|e| e.to_json())?;
+
+    let hno = house_service
+        .modify_house(db, house, user.user.uno)
+        .await
+        .map_err(|e| e.to_json())?;
+
+    Ok(Json(JieguoResponse {
+        jieguo: true,
+        reason: Some(hno.to_string()),
+        errors: Vec::new(),
+    }))
+}
+
+const DEFAULT_PAGE_LIMIT: u64 = 20;
+const MAX_PAGE_LIMIT: u64 = 100;
+
+/// Search results `limit` at a time; pass `next_cursor` back as `cursor`
+/// with the same filter for the next ones.
+#[get("/houses?<cursor>&<limit>&<house_filter..>")]
+async fn houses(
+    cursor: Option<&str>,
+    limit: Option<u64>,
+    conn: Connection<'_, MXFDb>,
+    house_service: &State<HouseService>,
+    bucket_service: &State<BucketService>,
+    house_filter: HouseFilter<'_>,
+) -> Result<Json<CursorPage<HouseListingModel>>, Json<JieguoResponse>> {
+    let buckets = bucket_service.get_buckets();
+    let house_filter = house_filter.with_buckets(&buckets).map_err(|e| e.to_json())?;
+    let cursor = cursor.map(Cursor::parse).transpose().map_err(|e| e.to_json())?;
+    let page = house_service
+        .get_houses_after(
+            conn.into_inner(),
+            house_filter,
+            cursor,
+            limit.unwrap_or(DEFAULT_PAGE_LIMIT).clamp(1, MAX_PAGE_LIMIT),
+            true,
+        )
+        .await
+        .map_err(|e| e.to_json())?;
+
+    Ok(Json(page))
+}
+
+#[get("/similar?<hno>&<n>")]
+async fn similar_houses(
+    hno: u32,
+    n: Option<usize>,
+    user: Option<Claims>,
+    conn: Connection<'_, MXFDb>,
+    house_service: &State<HouseService>,
+    recommendation_service: &State<RecommendationService>,
+) -> Result<Json<Vec<HouseListingModel>>, Json<JieguoResponse>> {
+    let db = conn.into_inner();
+    let staff = user.as_ref().is_some_and(|u| u.is_staff());
+    let house = house_service
+        .get_visible_house(db, hno, user.map(|u| u.user.uno), staff)
+        .await
+        .map_err(|e| e.to_json())?;
+    let n = n.unwrap_or(RecommendationService::DEFAULT_RECOMMENDATIONS);
+    let similar = recommendation_service
+        .get_similar_houses(db, &house, n)
+        .await
+        .map_err(|e| e.to_json())?;
+
+    Ok(Json(similar))
+}
+
+/// Suggested rent for the house described by a listing form, which needs no
+/// address, description or price, with the comparables it is based on.
+#[post("/estimate", data = "<house_data>")]
+async fn estimate_rent(
+    _user: Claims,
+    conn: Connection<'_, MXFDb>,
+    house_service: &State<HouseService>,
+    rent_estimate_service: &State<RentEstimateService>,
+    house_data: Json<ListingInput>,
+) -> Result<Json<RentEstimate>, Json<JieguoResponse>> {
+    let db = conn.into_inner();
+    let house = house_service
+        .validate_estimate(db, house_data.into_inner())
+        .await
+        .map_err(|e| e.to_json())?;
+    let estimate = rent_estimate_service
+        .estimate(db, &house)
+        .await
+        .map_err(|e| e.to_json())?;
+
+    Ok(Json(estimate))
+}
+
+#[get("/calendar?<hno>")]
+async fn calendar(
+    hno: u32,
+    user: Option<Claims>,
+    conn: Connection<'_, MXFDb>,
+    house_service: &State<HouseService>,
+    calendar_service: &State<CalendarService>,
+) -> Result<Json<AvailabilityCalendar>, Json<JieguoResponse>> {
+    let db = conn.into_inner();
+    let staff = user.as_ref().is_some_and(|u| u.is_staff());
+    house_service
+        .get_visible_house(db, hno, user.map(|u| u.user.uno), staff)
+        .await
+        .map_err(|e| e.to_json())?;
+    let calendar = calendar_service
+        .get_calendar(db, hno)
+        .await
+        .map_err(|e| e.to_json())?;
+
+    Ok(Json(calendar))
+}
+
+#[post("/reindex")]
+async fn reindex(
+    user: Claims,
+    conn: Connection<'_, MXFDb>,
+    house_service: &State<HouseService>,
+) -> Result<Json<JieguoResponse>, Json<JieguoResponse>> {
+    if !user.is_admin() {
+        return Err(MXFError::NotAdmin.to_json());
+    }
+
+    let indexed = house_service
+        .rebuild_search_index(conn.into_inner())
+        .await
+        .map_err(|e| e.to_json())?;
+
+    Ok(Json(JieguoResponse {
+        jieguo: true,
+        reason: Some(indexed.to_string()),
+        errors: Vec::new(),
+    }))
+}
+
+#[post("/review", data = "<review_data>")]
+async fn review(
+    user: Claims,
+    conn: Connection<'_, MXFDb>,
+    house_service: &State<HouseService>,
+    review_data: Json<ReviewData>,
+) -> Result<Json<JieguoResponse>, Json<JieguoResponse>> {
+    if !user.is_staff() {
+        return Err(MXFError::NotStaff.to_json());
+    }
+    let review_data = review_data.into_inner();
+
+    house_service
+        .review_house(
+            conn.into_inner(),
+            review_data.hno,
+            review_data.approve,
+            review_data.reason,
+            user.user.uno,
+        )
+        .await
+        .map_err(|e| e.to_json())?;
+
+    Ok(JieguoResponse::success_json())
+}
+
+#[post("/archive", data = "<house_data>")]
+async fn archive_house(
+    user: Claims,
+    conn: Connection<'_, MXFDb>,
+    house_service: &State<HouseService>,
+    house_data: Json<HnoData>,
+) -> Result<Json<JieguoResponse>, Json<JieguoResponse>> {
+    house_service
+        .archive_house(conn.into_inner(), house_data.hno, user.user.uno, false)
+        .await
+        .map_err(|e| e.to_json())?;
+
+    Ok(JieguoResponse::success_json())
+}
+
+#[post("/delete", data = "<house_data>")]
+async fn delete_house(
+    user: Claims,
+    conn: Connection<'_, MXFDb>,
+    house_service: &State<HouseService>,
+    house_data: Json<HnoData>,
+) -> Result<Json<JieguoResponse>, Json<JieguoResponse>> {
+    house_service
+        .archive_house(conn.into_inner(), house_data.hno, user.user.uno, true)
+        .await
+        .map_err(|e| e.to_json())?;
+
+    Ok(JieguoResponse::success_json())
+}
+
+#[post("/restore", data = "<house_data>")]
+async fn restore_house(
+    user: Claims,
+    conn: Connection<'_, MXFDb>,
+    house_service: &State<HouseService>,
+    house_data: Json<HnoData>,
+) -> Result<Json<JieguoResponse>, Json<JieguoResponse>> {
+    if !user.is_admin() {
+        return Err(MXFError::NotAdmin.to_json());
+    }
+
+    house_service
+        .restore_house(conn.into_inner(), house_data.hno, user.user.uno)
+        .await
+        .map_err(|e| e.to_json())?;
+
+    Ok(JieguoResponse::success_json())
+}
+
+/// Landlord's "still available" confirmation; the new status is the reason.
+#[post("/refresh", data = "<house_data>")]
+async fn refresh_house(
+    user: Claims,
+    conn: Connection<'_, MXFDb>,
+    house_service: &State<HouseService>,
+    expiry_service: &State<ExpiryService>,
+    house_data: Json<HnoData>,
+) -> Result<Json<JieguoResponse>, Json<JieguoResponse>> {
+    let status = expiry_service
+        .refresh_house(conn.into_inner(), house_service, house_data.hno, user.user.uno)
+        .await
+        .map_err(|e| e.to_json())?;
+
+    Ok(Json(JieguoResponse {
+        jieguo: true,
+        reason: Some(format!("{:?}", status)),
+        errors: Vec::new(),
+    }))
+}
+
+#[post("/revert", data = "<revision_data>")]
+async fn revert_house(
+    user: Claims,
+    conn: Connection<'_, MXFDb>,
+    house_service: &State<HouseService>,
+    revision_data: Json<RnoData>,
+) -> Result<Json<JieguoResponse>, Json<JieguoResponse>> {
+    if !user.is_admin() {
+        return Err(MXFError::NotAdmin.to_json());
+    }
 
     let hno = house_service
-        .modify_house(db, house_data.into_inner(), user.user.uno)
+        .revert_house(conn.into_inner(), revision_data.rno, user.user.uno)
         .await
         .map_err(|e| e.to_json())?;
 
     Ok(Json(JieguoResponse {
         jieguo: true,
         reason: Some(hno.to_string()),
+        errors: Vec::new(),
     }))
 }
 
+#[post("/purge", data = "<house_data>")]
+async fn purge_house(
+    user: Claims,
+    conn: Connection<'_, MXFDb>,
+    house_service: &State<HouseService>,
+    house_data: Json<HnoData>,
+) -> Result<Json<JieguoResponse>, Json<JieguoResponse>> {
+    if !user.is_admin() {
+        return Err(MXFError::NotAdmin.to_json());
+    }
+
+    house_service
+        .purge_house(conn.into_inner(), house_data.hno)
+        .await
+        .map_err(|e| e.to_json())?;
+
+    Ok(JieguoResponse::success_json())
+}
+
 pub fn routes() -> Vec<Route> {
-    routes![new_house, modify_house]
+    routes![
+        new_house,
+        modify_house,
+        houses,
+        similar_houses,
+        estimate_rent,
+        calendar,
+        reindex,
+        review,
+        archive_house,
+        delete_house,
+        restore_house,
+        refresh_house,
+        revert_house,
+        purge_house,
+    ]
 }
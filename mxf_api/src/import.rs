@@ -0,0 +1,66 @@
+use rocket::data::{Data, ToByteUnit};
+use rocket::serde::json::Json;
+use rocket::{Route, State};
+use sea_orm_rocket::Connection;
+
+use super::{Claims, MXFDb};
+
+use mxf_entity::errors::JieguoResponse;
+use mxf_entity::{ImportJob, ImportOptions, MXFError};
+use mxf_service::{HouseService, ImportService};
+
+/// Upload limit for one import file.
+const MAX_IMPORT_MIB: u32 = 10;
+
+/// Validates the uploaded file and starts importing it in the background.
+/// If ok, returns the job id to poll at `/import/<id>`.
+#[post("/import?<options..>", data = "<file>")]
+async fn import(
+    user: Claims,
+    conn: Connection<'_, MXFDb>,
+    house_service: &State<HouseService>,
+    import_service: &State<ImportService>,
+    options: ImportOptions,
+    file: Data<'_>,
+) -> Result<Json<JieguoResponse>, Json<JieguoResponse>> {
+    let bytes = file
+        .open(MAX_IMPORT_MIB.mebibytes())
+        .into_bytes()
+        .await
+        .map_err(|e| MXFError::InvalidImport(e.to_string()).to_json())?;
+    if !bytes.is_complete() {
+        return Err(MXFError::InvalidImport(format!("file exceeds {} MiB", MAX_IMPORT_MIB)).to_json());
+    }
+    let rows = ImportService::parse(options.format, &bytes).map_err(|e| e.to_json())?;
+    let id = import_service.create_job(user.user.uno, options.dry_run, options.upsert, rows.len());
+    println!("Import job {} by {}: {} rows", id, user.user.uno, rows.len());
+
+    let db = conn.into_inner().clone();
+    let house_service = house_service.inner().clone();
+    let import_service = import_service.inner().clone();
+    rocket::tokio::spawn(async move {
+        import_service.run(&db, &house_service, id, rows).await;
+    });
+
+    Ok(Json(JieguoResponse {
+        jieguo: true,
+        reason: Some(id.to_string()),
+        errors: Vec::new(),
+    }))
+}
+
+#[get("/import/<id>")]
+async fn import_progress(
+    user: Claims,
+    import_service: &State<ImportService>,
+    id: u32,
+) -> Result<Json<ImportJob>, Json<JieguoResponse>> {
+    import_service
+        .get_job(id, user.user.uno)
+        .map(Json)
+        .map_err(|e| e.to_json())
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![import, import_progress]
+}
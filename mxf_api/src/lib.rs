@@ -7,16 +7,30 @@ use sea_orm_rocket::Database;
 use shuttle_secrets::SecretStore;
 use std::env;
 
-pub(self) mod claims;
-pub(self) mod database;
+mod claims;
+mod database;
+mod scheduler;
+mod visitor;
+pub mod analytics;
+pub mod bucket;
+pub mod export;
+pub mod import;
+pub mod map;
 pub mod order;
 pub mod pages;
+pub mod saved_search;
 pub mod session;
 pub mod house_listing;
 
-pub(self) use claims::Claims;
+use claims::Claims;
+use visitor::Visitor;
 use database::MXFDb;
-use mxf_service::{HouseService, OrderService, UserService};
+use mxf_service::{
+    AnalyticsService, BucketService, CalendarService, ExpiryPolicy, ExpiryService, FacetService,
+    FavoriteService, FraudService, HouseService, ImportService, NotificationService,
+    OrderService, PriceHistoryService, RecommendationService, RentEstimateService,
+    RevisionService, SavedSearchService, UserService,
+};
 
 
 pub async fn main(secret_store: SecretStore) -> rocket::Rocket<rocket::Build> {
@@ -28,7 +42,7 @@ pub async fn main(secret_store: SecretStore) -> rocket::Rocket<rocket::Build> {
         .merge((
             "databases.mxf",
             sea_orm_rocket::Config {
-                url: url,
+                url,
                 min_connections: Some(4),
                 max_connections: 1024,
                 connect_timeout: 3,
@@ -36,16 +50,43 @@ pub async fn main(secret_store: SecretStore) -> rocket::Rocket<rocket::Build> {
                 sqlx_logging: true,
             },
         ));
+    let expiry_policy = match figment.extract_inner::<ExpiryPolicy>("expiry") {
+        Ok(policy) => policy,
+        Err(e) if e.missing() => ExpiryPolicy::default(),
+        Err(e) => panic!("invalid expiry settings: {}", e),
+    };
 
     rocket::custom(figment)
         .attach(MXFDb::init())
         .manage(HouseService::init())
         .manage(UserService::init())
         .manage(OrderService::init())
+        .manage(FavoriteService::init())
+        .manage(NotificationService::init())
+        .manage(SavedSearchService::init())
+        .manage(ImportService::init())
+        .manage(PriceHistoryService::init())
+        .manage(RevisionService::init())
+        .manage(FraudService::init())
+        .manage(RecommendationService::init())
+        .manage(RentEstimateService::init())
+        .manage(AnalyticsService::init())
+        .manage(CalendarService::init())
+        .manage(ExpiryService::init(expiry_policy))
+        .manage(FacetService::init())
+        .manage(BucketService::init())
         .mount("/", FileServer::from(relative!("../static")))
         .mount("/", pages::routes())
         .mount("/", session::routes())
         .mount("/", order::routes())
         .mount("/", house_listing::routes())
+        .mount("/", map::routes())
+        .mount("/", saved_search::routes())
+        .mount("/", import::routes())
+        .mount("/", export::routes())
+        .mount("/", analytics::routes())
+        .mount("/", bucket::routes())
         .attach(Template::fairing())
+        .attach(scheduler::fairing())
+        .attach(scheduler::shutdown_fairing())
 }
@@ -0,0 +1,60 @@
+use rocket::serde::json::Json;
+use rocket::{Route, State};
+use sea_orm_rocket::Connection;
+
+use super::{Claims, MXFDb};
+
+use mxf_entity::errors::JieguoResponse;
+use mxf_entity::{HouseFilter, MXFError, MapMarker};
+use mxf_service::{BucketService, HouseService};
+
+const DEFAULT_MAP_ZOOM: u8 = 12u8;
+
+#[get("/map/markers?<zoom>&<house_filter..>")]
+async fn markers(
+    zoom: Option<u8>,
+    conn: Connection<'_, MXFDb>,
+    house_service: &State<HouseService>,
+    bucket_service: &State<BucketService>,
+    house_filter: HouseFilter<'_>,
+) -> Result<Json<Vec<MapMarker>>, Json<JieguoResponse>> {
+    let buckets = bucket_service.get_buckets();
+    let house_filter = house_filter.with_buckets(&buckets).map_err(|e| e.to_json())?;
+    let markers = house_service
+        .get_map_markers(
+            conn.into_inner(),
+            house_filter,
+            zoom.unwrap_or(DEFAULT_MAP_ZOOM),
+            true,
+        )
+        .await
+        .map_err(|e| e.to_json())?;
+
+    Ok(Json(markers))
+}
+
+#[post("/map/geocode")]
+async fn geocode(
+    user: Claims,
+    conn: Connection<'_, MXFDb>,
+    house_service: &State<HouseService>,
+) -> Result<Json<JieguoResponse>, Json<JieguoResponse>> {
+    if !user.is_admin() {
+        return Err(MXFError::NotAdmin.to_json());
+    }
+
+    let updated = house_service
+        .geocode_missing(conn.into_inner())
+        .await
+        .map_err(|e| e.to_json())?;
+
+    Ok(Json(JieguoResponse {
+        jieguo: true,
+        reason: Some(updated.to_string()),
+        errors: Vec::new(),
+    }))
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![markers, geocode]
+}
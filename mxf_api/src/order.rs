@@ -3,10 +3,15 @@ use rocket::{Route, State};
 use sea_orm_rocket::Connection;
 
 use mxf_entity::errors::JieguoResponse;
-use mxf_entity::HnoData;
-use mxf_service::{HouseService, OrderService};
+use mxf_entity::{Cursor, CursorPage, HnoData, ListStatus, MXFError, OrderModel, StatsEvent};
+use mxf_service::{
+    AnalyticsService, FavoriteService, HouseService, NotificationService, OrderService,
+};
 
-use super::{Claims, MXFDb};
+use super::{Claims, MXFDb, Visitor};
+
+const DEFAULT_PAGE_LIMIT: u64 = 20;
+const MAX_PAGE_LIMIT: u64 = 100;
 
 #[post("/lease", data = "<lease_data>")]
 async fn lease(
@@ -14,6 +19,8 @@ async fn lease(
     conn: Connection<'_, MXFDb>,
     order_service: &State<OrderService>,
     house_service: &State<HouseService>,
+    analytics_service: &State<AnalyticsService>,
+    visitor: Visitor,
     lease_data: Json<HnoData>,
 ) -> Result<Json<JieguoResponse>, Json<JieguoResponse>> {
     let hno = lease_data.hno;
@@ -24,21 +31,26 @@ async fn lease(
         .await
         .map_err(|e| e.to_json())?;
 
-    let hlandlore = house_service
+    let house = house_service
         .get_house_by_hno(db, hno)
         .await
-        .map_err(|e| e.to_json())?
-        .hlandlore;
+        .map_err(|e| e.to_json())?;
+    if house.hunlisted != ListStatus::Listed {
+        return Err(MXFError::HouseUnavailable(hno).to_json());
+    }
+    let hlandlore = house.hlandlore;
 
     println!("Lease: ono = {}, hlandlore = {}", ono, hlandlore);
     order_service
         .place_order_by_ono(db, ono, hno, hlandlore, user.user.uno)
         .await
         .map_err(|e| e.to_json())?;
+    visitor.record(analytics_service, &[hno], StatsEvent::Request);
 
     Ok(Json(JieguoResponse {
         jieguo: true,
         reason: None,
+        errors: Vec::new(),
     }))
 }
 
@@ -57,9 +69,116 @@ async fn confirm(
     Ok(Json(JieguoResponse {
         jieguo: true,
         reason: None,
+        errors: Vec::new(),
     }))
 }
 
+#[post("/favorite", data = "<favorite_data>")]
+async fn favorite(
+    user: Claims,
+    conn: Connection<'_, MXFDb>,
+    favorite_service: &State<FavoriteService>,
+    analytics_service: &State<AnalyticsService>,
+    visitor: Visitor,
+    favorite_data: Json<HnoData>,
+) -> Result<Json<JieguoResponse>, Json<JieguoResponse>> {
+    favorite_service
+        .add_favorite(conn.into_inner(), user.user.uno, favorite_data.hno, user.is_staff())
+        .await
+        .map_err(|e| e.to_json())?;
+    visitor.record(analytics_service, &[favorite_data.hno], StatsEvent::Favorite);
+
+    Ok(JieguoResponse::success_json())
+}
+
+#[post("/unfavorite", data = "<favorite_data>")]
+async fn unfavorite(
+    user: Claims,
+    conn: Connection<'_, MXFDb>,
+    favorite_service: &State<FavoriteService>,
+    favorite_data: Json<HnoData>,
+) -> Result<Json<JieguoResponse>, Json<JieguoResponse>> {
+    favorite_service
+        .remove_favorite(conn.into_inner(), user.user.uno, favorite_data.hno)
+        .await
+        .map_err(|e| e.to_json())?;
+
+    Ok(JieguoResponse::success_json())
+}
+
+#[post("/notifications/read")]
+async fn read_notifications(
+    user: Claims,
+    conn: Connection<'_, MXFDb>,
+    notification_service: &State<NotificationService>,
+) -> Result<Json<JieguoResponse>, Json<JieguoResponse>> {
+    notification_service
+        .mark_all_read(conn.into_inner(), user.user.uno)
+        .await
+        .map_err(|e| e.to_json())?;
+
+    Ok(JieguoResponse::success_json())
+}
+
+/// The user's own requests and leases, latest order of each, oldest first.
+/// `before` pages back from a `prev_cursor`.
+#[get("/orders/mine?<cursor>&<before>&<limit>")]
+async fn orders_mine(
+    cursor: Option<&str>,
+    before: Option<&str>,
+    limit: Option<u64>,
+    user: Claims,
+    conn: Connection<'_, MXFDb>,
+    order_service: &State<OrderService>,
+) -> Result<Json<CursorPage<OrderModel>>, Json<JieguoResponse>> {
+    let cursor = before.or(cursor).map(Cursor::parse).transpose().map_err(|e| e.to_json())?;
+    let page = order_service
+        .get_orders_by_htenant_after(
+            conn.into_inner(),
+            user.user.uno,
+            cursor,
+            before.is_some(),
+            limit.unwrap_or(DEFAULT_PAGE_LIMIT).clamp(1, MAX_PAGE_LIMIT),
+        )
+        .await
+        .map_err(|e| e.to_json())?;
+
+    Ok(Json(page))
+}
+
+/// Orders on the user's houses, like `/orders/mine`.
+#[get("/orders/received?<cursor>&<before>&<limit>")]
+async fn orders_received(
+    cursor: Option<&str>,
+    before: Option<&str>,
+    limit: Option<u64>,
+    user: Claims,
+    conn: Connection<'_, MXFDb>,
+    order_service: &State<OrderService>,
+) -> Result<Json<CursorPage<OrderModel>>, Json<JieguoResponse>> {
+    let cursor = before.or(cursor).map(Cursor::parse).transpose().map_err(|e| e.to_json())?;
+    let page = order_service
+        .get_orders_by_hlandlore_after(
+            conn.into_inner(),
+            user.user.uno,
+            cursor,
+            before.is_some(),
+            limit.unwrap_or(DEFAULT_PAGE_LIMIT).clamp(1, MAX_PAGE_LIMIT),
+        )
+        .await
+        .map_err(|e| e.to_json())?;
+
+    Ok(Json(page))
+}
+
 pub fn routes() -> Vec<Route> {
-    routes![lease, confirm]
+    routes![
+        lease,
+        confirm,
+        favorite,
+        unfavorite,
+        read_notifications,
+        orders_mine,
+        orders_received,
+    ]
 }
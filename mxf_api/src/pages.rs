@@ -1,16 +1,50 @@
 use chrono;
-use rocket::request::FlashMessage;
+use rocket::outcome::try_outcome;
+use rocket::request::{FlashMessage, FromRequest, Outcome, Request};
 use rocket::response::{Flash, Redirect};
 use rocket::{Route, State};
 use rocket_dyn_templates::{context, Template};
 use sea_orm_rocket::Connection;
 
-use super::{Claims, MXFDb};
+use super::{Claims, MXFDb, Visitor};
 use mxf_entity::user::UserType;
-use mxf_entity::{HouseFilter, ListStatus, MXFError};
-use mxf_service::{HouseService, OrderService};
+use mxf_entity::{parse_query, Cursor, HouseFilter, ListStatus, MXFError, StatsEvent};
+use mxf_service::{
+    AnalyticsService, BucketService, CalendarService, FacetService, FavoriteService, FraudService,
+    HouseService, NotificationService, OrderService, PriceHistoryService, RecommendationService,
+    RevisionService, SavedSearchService,
+};
 
 const DEFAULT_POSTS_PER_PAGE: u8 = 10u8;
+const DEFAULT_ORDERS_PER_PAGE: u64 = 20;
+const DEFAULT_STATS_DAYS: u32 = 14;
+
+/// What the search and detail pages both need to show and count listings.
+struct ListingContext<'r> {
+    house_service: &'r HouseService,
+    price_history_service: &'r PriceHistoryService,
+    analytics_service: &'r AnalyticsService,
+    visitor: Visitor,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ListingContext<'r> {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let house_service = try_outcome!(request.guard::<&State<HouseService>>().await);
+        let price_history_service =
+            try_outcome!(request.guard::<&State<PriceHistoryService>>().await);
+        let analytics_service = try_outcome!(request.guard::<&State<AnalyticsService>>().await);
+        let visitor = request.guard::<Visitor>().await.map_error(|(status, _)| (status, ()));
+        Outcome::Success(ListingContext {
+            house_service: house_service.inner(),
+            price_history_service: price_history_service.inner(),
+            analytics_service: analytics_service.inner(),
+            visitor: try_outcome!(visitor),
+        })
+    }
+}
 
 #[get("/")]
 async fn index(flash: Option<FlashMessage<'_>>) -> Template {
@@ -31,9 +65,17 @@ async fn index_alias() -> Redirect {
 #[get("/zufang?<house_filter..>")]
 async fn zufang(
     conn: Connection<'_, MXFDb>,
-    house_service: &State<HouseService>,
+    listing: ListingContext<'_>,
+    facet_service: &State<FacetService>,
+    bucket_service: &State<BucketService>,
+    flash: Option<FlashMessage<'_>>,
     house_filter: HouseFilter<'_>,
 ) -> Result<Template, Flash<Redirect>> {
+    let ListingContext { house_service, price_history_service, analytics_service, visitor } =
+        listing;
+    let buckets = bucket_service.get_buckets();
+    let house_filter =
+        house_filter.with_buckets(&buckets).map_err(|e| e.to_redirect("/zufang"))?;
     let db = conn.into_inner();
     println!("{}", db.ping().await.is_ok());
 
@@ -41,41 +83,148 @@ async fn zufang(
         .get_houses_in_page(db, house_filter, DEFAULT_POSTS_PER_PAGE, true)
         .await
         .map_err(|e| e.to_redirect("/zufang"))?;
+    let hnos: Vec<u32> = houses.iter().map(|h| h.hno).collect();
+    visitor.record(analytics_service, &hnos, StatsEvent::Impression);
+    let dropped = price_history_service
+        .get_price_drops(db, &houses)
+        .await
+        .map_err(|e| e.to_redirect("/zufang"))?;
+    let unit_price_medians = price_history_service
+        .get_unit_price_medians(db, house_service.region_service(), &houses)
+        .await
+        .map_err(|e| e.to_redirect("/zufang"))?;
+    let region_path = match house_filter.region() {
+        Some(rno) => house_service
+            .region_service()
+            .get_path(db, rno)
+            .await
+            .map_err(|e| e.to_redirect("/zufang"))?,
+        None => Vec::new(),
+    };
+    let region_path_queries: Vec<String> = region_path
+        .iter()
+        .map(|r| house_filter.with_region(Some(r.rno)).to_query_string())
+        .collect();
+    let region_options = house_service
+        .region_service()
+        .get_region_options(db, house_filter)
+        .await
+        .map_err(|e| e.to_redirect("/zufang"))?;
+    let facets = facet_service
+        .get_facet_counts(db, house_service.search_cache(), house_filter)
+        .await
+        .map_err(|e| e.to_redirect("/zufang"))?;
+    // Infinite scroll carries on from the last house shown by cursor
+    let next_cursor = match houses.last() {
+        Some(last) if house_filter.page < num_pages => Some(
+            house_service
+                .cursor_of(db, house_filter, last)
+                .await
+                .map_err(|e| e.to_redirect("/zufang"))?
+                .to_string(),
+        ),
+        _ => None,
+    };
 
     Ok(Template::render(
         "zufang",
         context! {
             title: "租房",
-            flash: ("success", house_filter.to_string()),
-            preload: house_filter,
+            flash: flash
+                .map(FlashMessage::into_inner)
+                .unwrap_or_else(|| ("success".to_string(), house_filter.to_string())),
+            preload: house_filter.with_facets(&facets),
+            query_text: house_filter.to_query_text(),
             items: houses,
+            dropped: dropped,
+            unit_price_medians: unit_price_medians,
+            sorts: HouseFilter::SORTS,
             max_page: num_pages,
+            next_cursor: next_cursor,
+            region_path: region_path,
+            region_path_queries: region_path_queries,
+            region_options: region_options,
+            all_regions_query: house_filter.with_region(None).to_query_string(),
         },
     ))
 }
 
+/// Runs a text query by sending it on to `/zufang` as the equivalent filter.
+#[get("/search?<text>")]
+async fn search(
+    text: &str,
+    bucket_service: &State<BucketService>,
+) -> Result<Redirect, Flash<Redirect>> {
+    let fields = parse_query(text).map_err(|e| e.to_redirect("/zufang"))?;
+    let buckets = bucket_service.get_buckets();
+    let house_filter =
+        HouseFilter::from_fields(&fields, &buckets).map_err(|e| e.to_redirect("/zufang"))?;
+    Ok(Redirect::to(format!("/zufang?{}", house_filter.to_query_string())))
+}
+
 #[get("/detail?<hno>")]
 async fn detail(
     hno: Option<u32>,
     user: Option<Claims>,
     conn: Connection<'_, MXFDb>,
-    house_service: &State<HouseService>,
+    listing: ListingContext<'_>,
     order_service: &State<OrderService>,
+    favorite_service: &State<FavoriteService>,
+    recommendation_service: &State<RecommendationService>,
 ) -> Result<Template, Flash<Redirect>> {
+    let ListingContext { house_service, price_history_service, analytics_service, visitor } =
+        listing;
     let db = conn.into_inner();
     if hno.is_none() {
         return Err(Flash::error(Redirect::to("/zufang"), "房屋编号不能为空"));
     }
 
+    let staff = user.as_ref().is_some_and(|u| u.is_staff());
     let house = house_service
-        .get_house_by_hno(db, hno.unwrap())
+        .get_visible_house(db, hno.unwrap(), user.as_ref().map(|u| u.user.uno), staff)
         .await
         .map_err(|e| e.to_redirect("/zufang"))?;
+    // Landlords checking their own listing are not counted
+    if user.as_ref().map_or(true, |u| u.user.uno != house.hlandlore) {
+        visitor.record(analytics_service, &[house.hno], StatsEvent::View);
+    }
     let orders = order_service
         .get_orders_by_hno(db, hno.unwrap())
         .await
         .map_err(|e| e.to_redirect("/zufang"))?;
+    let calendar = CalendarService::from_orders(house.hno, &orders);
     let orders = OrderService::filter_latest(&orders);
+    let is_favorite = match &user {
+        Some(u) => favorite_service
+            .is_favorite(db, u.user.uno, house.hno)
+            .await
+            .map_err(|e| e.to_redirect("/zufang"))?,
+        None => false,
+    };
+    let similar = recommendation_service
+        .get_similar_houses(db, &house, RecommendationService::DEFAULT_RECOMMENDATIONS)
+        .await
+        .map_err(|e| e.to_redirect("/zufang"))?;
+    let price_history = price_history_service
+        .get_history(db, house.hno)
+        .await
+        .map_err(|e| e.to_redirect("/zufang"))?;
+    let price_dropped_from = price_history_service
+        .get_price_drops(db, std::slice::from_ref(&house))
+        .await
+        .map_err(|e| e.to_redirect("/zufang"))?
+        .pop()
+        .flatten();
+    let unit_price_median = price_history_service
+        .get_unit_price_medians(
+            db,
+            house_service.region_service(),
+            std::slice::from_ref(&house),
+        )
+        .await
+        .map_err(|e| e.to_redirect("/zufang"))?
+        .pop()
+        .flatten();
     println!("house: {:?} -> orders: {:?}", house, orders);
     Ok(Template::render(
         "housedetail",
@@ -88,11 +237,19 @@ async fn detail(
             hflr: house.hflr,
             harea: house.harea,
             hequip: house.hsuite,
+            hdesc: house.hdesc,
             hprice: house.hprice,
+            hunit_price: house.hunit_price,
+            unit_price_median: unit_price_median,
+            price_history: price_history,
+            price_dropped_from: price_dropped_from,
             hlandlore: house.hlandlore,
             hunlisted: house.hunlisted,
             hdate: chrono::NaiveDate::from_ymd_opt(2021, 1, 1).unwrap(),
             orders: orders,
+            calendar: calendar,
+            is_favorite: is_favorite,
+            similar: similar,
             is_admin: user.map(|u| u.user.utype != UserType::User).unwrap_or(false),
         },
     ))
@@ -134,19 +291,31 @@ async fn mine(
     }
 }
 
-#[get("/my_orders")]
+#[get("/my_orders?<cursor>&<before>")]
 async fn my_orders(
+    cursor: Option<&str>,
+    before: Option<&str>,
     user: Claims,
     conn: Connection<'_, MXFDb>,
     order_service: &State<OrderService>,
 ) -> Result<Template, Flash<Redirect>> {
     println!("user: {:?}", user.user);
     let db = conn.into_inner();
-    let my_orders = order_service
-        .get_orders_by_htenant(db, user.user.uno)
+    let cursor = before
+        .or(cursor)
+        .map(Cursor::parse)
+        .transpose()
+        .map_err(|e| e.to_redirect(uri!(index)))?;
+    let page = order_service
+        .get_orders_by_htenant_after(
+            db,
+            user.user.uno,
+            cursor,
+            before.is_some(),
+            DEFAULT_ORDERS_PER_PAGE,
+        )
         .await
         .map_err(|e| e.to_redirect(uri!(index)))?;
-    let my_orders = OrderService::filter_latest(&my_orders);
     let shown = vec![false, true, true, false, true, true, true, false, false];
     let count = shown.iter().filter(|&n| *n).count();
 
@@ -155,28 +324,43 @@ async fn my_orders(
         context! {
             title: "我的订单",
             user: user.user,
-            orders: my_orders,
+            orders: page.items,
+            next_cursor: page.next_cursor,
+            prev_cursor: page.prev_cursor,
             shown: shown,
             count: count,
         },
     ))
 }
 
-#[get("/received_orders")]
+#[get("/received_orders?<cursor>&<before>")]
 async fn received_orders(
+    cursor: Option<&str>,
+    before: Option<&str>,
     user: Claims,
     conn: Connection<'_, MXFDb>,
     order_service: &State<OrderService>,
 ) -> Result<Template, Flash<Redirect>> {
     println!("user: {:?}", user.user);
     let db = conn.into_inner();
-    let received_orders = order_service
-        .get_orders_by_hlandlore(db, user.user.uno)
+    let cursor = before
+        .or(cursor)
+        .map(Cursor::parse)
+        .transpose()
+        .map_err(|e| e.to_redirect(uri!(index)))?;
+    let page = order_service
+        .get_orders_by_hlandlore_after(
+            db,
+            user.user.uno,
+            cursor,
+            before.is_some(),
+            DEFAULT_ORDERS_PER_PAGE,
+        )
         .await
         .map_err(|e| e.to_redirect(uri!(index)))?;
-    let received_orders = OrderService::filter_latest(&received_orders);
 
-    let confirm_tags = received_orders
+    let confirm_tags = page
+        .items
         .iter()
         .map(|o| if !o.is_confirmed() { "" } else { "disabled" })
         .collect::<Vec<&str>>();
@@ -188,7 +372,9 @@ async fn received_orders(
         context! {
             title: "收到的订单",
             user: user.user,
-            orders: received_orders,
+            orders: page.items,
+            next_cursor: page.next_cursor,
+            prev_cursor: page.prev_cursor,
             shown: shown,
             count: count,
             confirm: confirm_tags,
@@ -196,20 +382,31 @@ async fn received_orders(
     ))
 }
 
-#[get("/my_listings")]
+#[get("/my_listings?<tab>")]
 async fn my_listings(
+    tab: Option<&str>,
     user: Claims,
     conn: Connection<'_, MXFDb>,
     house_service: &State<HouseService>,
+    analytics_service: &State<AnalyticsService>,
 ) -> Result<Template, Flash<Redirect>> {
     println!("user: {:?}", user.user);
     let db = conn.into_inner();
+    let archived = tab == Some("archived");
     let my_listings = house_service
         .get_houses_by_landlore(db, user.user.uno, false)
         .await
+        .map_err(|e| e.to_redirect(uri!(index)))?
+        .into_iter()
+        .filter(|h| h.hunlisted != ListStatus::Deleted)
+        .filter(|h| (h.hunlisted == ListStatus::Archived) == archived)
+        .collect::<Vec<_>>();
+    let stats = analytics_service
+        .get_listing_stats(db, &my_listings, DEFAULT_STATS_DAYS)
+        .await
         .map_err(|e| e.to_redirect(uri!(index)))?;
 
-    let shown = vec![true; 9];
+    let shown = vec![true; 12];
 
     Ok(Template::render(
         "my_listings",
@@ -217,8 +414,116 @@ async fn my_listings(
             title: "收到的申请",
             user: user.user,
             listings: my_listings,
+            stats: stats,
+            stats_days: DEFAULT_STATS_DAYS,
+            archived: archived,
             shown: shown,
-            count: 9,
+            count: 12,
+        },
+    ))
+}
+
+#[get("/review")]
+async fn review_queue(
+    user: Claims,
+    conn: Connection<'_, MXFDb>,
+    house_service: &State<HouseService>,
+    fraud_service: &State<FraudService>,
+) -> Result<Template, Flash<Redirect>> {
+    if !user.is_staff() {
+        return Err(MXFError::NotStaff.to_redirect(uri!(index)));
+    }
+    let db = conn.into_inner();
+    let pending = house_service
+        .get_houses_pending_review(db)
+        .await
+        .map_err(|e| e.to_redirect(uri!(index)))?;
+    let flags = fraud_service
+        .get_unresolved_flags(db, &pending)
+        .await
+        .map_err(|e| e.to_redirect(uri!(index)))?;
+
+    Ok(Template::render(
+        "review",
+        context! {
+            title: "房源审核",
+            user: user.user,
+            listings: pending,
+            flags: flags,
+        },
+    ))
+}
+
+#[get("/my_favorites")]
+async fn my_favorites(
+    user: Claims,
+    conn: Connection<'_, MXFDb>,
+    favorite_service: &State<FavoriteService>,
+) -> Result<Template, Flash<Redirect>> {
+    let favorites = favorite_service
+        .get_favorites_by_uno(conn.into_inner(), user.user.uno)
+        .await
+        .map_err(|e| e.to_redirect(uri!(index)))?;
+
+    Ok(Template::render(
+        "favorites",
+        context! {
+            title: "我的收藏",
+            user: user.user,
+            favorites: favorites,
+        },
+    ))
+}
+
+#[get("/my_searches")]
+async fn my_searches(
+    user: Claims,
+    conn: Connection<'_, MXFDb>,
+    saved_search_service: &State<SavedSearchService>,
+) -> Result<Template, Flash<Redirect>> {
+    let searches = saved_search_service
+        .get_searches_by_uno(conn.into_inner(), user.user.uno)
+        .await
+        .map_err(|e| e.to_redirect(uri!(index)))?;
+
+    Ok(Template::render(
+        "saved_searches",
+        context! {
+            title: "我的订阅",
+            user: user.user,
+            searches: searches,
+        },
+    ))
+}
+
+#[get("/import")]
+async fn import_listings(user: Claims) -> Template {
+    Template::render(
+        "import",
+        context! {
+            title: "批量导入",
+            user: user.user,
+        },
+    )
+}
+
+#[get("/notifications")]
+async fn notifications(
+    user: Claims,
+    conn: Connection<'_, MXFDb>,
+    notification_service: &State<NotificationService>,
+) -> Result<Template, Flash<Redirect>> {
+    let notifications = notification_service
+        .get_notifications_by_uno(conn.into_inner(), user.user.uno)
+        .await
+        .map_err(|e| e.to_redirect(uri!(index)))?;
+
+    Ok(Template::render(
+        "notifications",
+        context! {
+            title: "我的通知",
+            user: user.user,
+            notifications: notifications,
         },
     ))
 }
@@ -238,6 +543,116 @@ async fn my_listings_need_login() -> Redirect {
     Redirect::to(uri!(login))
 }
 
+#[get("/my_favorites", rank = 2)]
+async fn my_favorites_need_login() -> Redirect {
+    Redirect::to(uri!(login))
+}
+
+#[get("/my_searches", rank = 2)]
+async fn my_searches_need_login() -> Redirect {
+    Redirect::to(uri!(login))
+}
+
+#[get("/import", rank = 2)]
+async fn import_listings_need_login() -> Redirect {
+    Redirect::to(uri!(login))
+}
+
+#[get("/notifications", rank = 2)]
+async fn notifications_need_login() -> Redirect {
+    Redirect::to(uri!(login))
+}
+
+#[get("/revisions?<hno>")]
+async fn revisions(
+    hno: u32,
+    user: Claims,
+    conn: Connection<'_, MXFDb>,
+    revision_service: &State<RevisionService>,
+) -> Result<Template, Flash<Redirect>> {
+    if !user.is_staff() {
+        return Err(MXFError::NotStaff.to_redirect(uri!(index)));
+    }
+    let revisions = revision_service
+        .get_revision_diffs(conn.into_inner(), hno)
+        .await
+        .map_err(|e| e.to_redirect(uri!(index)))?;
+
+    Ok(Template::render(
+        "revisions",
+        context! {
+            title: "修改记录",
+            hno: hno,
+            is_admin: user.is_admin(),
+            user: user.user,
+            revisions: revisions,
+        },
+    ))
+}
+
+#[get("/archived")]
+async fn archived_listings(
+    user: Claims,
+    conn: Connection<'_, MXFDb>,
+    house_service: &State<HouseService>,
+) -> Result<Template, Flash<Redirect>> {
+    if !user.is_admin() {
+        return Err(MXFError::NotAdmin.to_redirect(uri!(index)));
+    }
+    let archived = house_service
+        .get_archived_houses(conn.into_inner())
+        .await
+        .map_err(|e| e.to_redirect(uri!(index)))?;
+
+    Ok(Template::render(
+        "archived",
+        context! {
+            title: "已归档房源",
+            user: user.user,
+            listings: archived,
+        },
+    ))
+}
+
+#[get("/buckets")]
+async fn search_buckets(
+    user: Claims,
+    bucket_service: &State<BucketService>,
+) -> Result<Template, Flash<Redirect>> {
+    if !user.is_admin() {
+        return Err(MXFError::NotAdmin.to_redirect(uri!(index)));
+    }
+
+    Ok(Template::render(
+        "buckets",
+        context! {
+            title: "搜索区间设置",
+            user: user.user,
+            buckets: bucket_service.get_buckets().as_ref(),
+        },
+    ))
+}
+
+#[get("/buckets", rank = 2)]
+async fn search_buckets_need_login() -> Redirect {
+    Redirect::to(uri!(login))
+}
+
+#[get("/archived", rank = 2)]
+async fn archived_listings_need_login() -> Redirect {
+    Redirect::to(uri!(login))
+}
+
+#[get("/revisions", rank = 2)]
+async fn revisions_need_login() -> Redirect {
+    Redirect::to(uri!(login))
+}
+
+#[get("/review", rank = 2)]
+async fn review_queue_need_login() -> Redirect {
+    Redirect::to(uri!(login))
+}
+
 #[get("/mine", rank = 2)]
 async fn mine_need_login() -> Redirect {
     Redirect::to(uri!(login))
@@ -273,10 +688,21 @@ async fn register(flash: Option<FlashMessage<'_>>) -> Result<Template, Flash<Red
 }
 
 #[get("/new")]
-async fn new_house() -> Result<Template, Flash<Redirect>> {
+async fn new_house(
+    conn: Connection<'_, MXFDb>,
+    house_service: &State<HouseService>,
+) -> Result<Template, Flash<Redirect>> {
+    let districts = house_service
+        .district_names(conn.into_inner())
+        .await
+        .map_err(|e| e.to_redirect(uri!(index)))?;
     Ok(Template::render(
         "modifyhouse",
-        context! { modify: false, title: "新建房屋" },
+        context! {
+            modify: false,
+            title: "新建房屋",
+            districts: districts,
+        },
     ))
 }
 
@@ -287,13 +713,18 @@ async fn modify_house(
     conn: Connection<'_, MXFDb>,
     house_service: &State<HouseService>,
 ) -> Result<Template, Flash<Redirect>> {
+    let db = conn.into_inner();
     let house = house_service
-        .get_house_by_hno(conn.into_inner(), hno)
+        .get_visible_house(db, hno, Some(user.user.uno), user.is_staff())
         .await
         .map_err(|e| e.to_redirect("/zufang"))?;
     if house.hlandlore != user.user.uno {
         return Err(MXFError::NotLandlore(user.user.uno).to_redirect(uri!(index)));
     }
+    let districts = house_service
+        .district_names(db)
+        .await
+        .map_err(|e| e.to_redirect(uri!(index)))?;
     Ok(Template::render(
         "modifyhouse",
         context! {
@@ -306,17 +737,21 @@ async fn modify_house(
             hflr: house.hflr,
             harea: house.harea,
             hequip: house.hsuite,
+            hdesc: house.hdesc,
             hprice: house.hprice,
             hlandlore: house.hlandlore,
             hunlisted: house.hunlisted,
+            hreason: house.hreason,
             is_unlisted: house.hunlisted == ListStatus::Unlisted,
+            is_approved: house.hunlisted.is_approved(),
+            districts: districts,
         },
     ))
 }
 
 #[get("/modify", rank = 2)]
 async fn modify_house_no_hno(_user: Claims) -> Redirect {
-    Redirect::to(uri!(my_listings))
+    Redirect::to(uri!(my_listings(_)))
 }
 
 #[get("/modify?<_hno>", rank = 3)]
@@ -329,6 +764,7 @@ pub fn routes() -> Vec<Route> {
         index,
         index_alias,
         zufang,
+        search,
         detail,
         mine,
         mine_need_login,
@@ -338,6 +774,22 @@ pub fn routes() -> Vec<Route> {
         received_orders_need_login,
         my_listings,
         my_listings_need_login,
+        review_queue,
+        review_queue_need_login,
+        archived_listings,
+        archived_listings_need_login,
+        search_buckets,
+        search_buckets_need_login,
+        revisions,
+        revisions_need_login,
+        my_favorites,
+        my_favorites_need_login,
+        my_searches,
+        my_searches_need_login,
+        import_listings,
+        import_listings_need_login,
+        notifications,
+        notifications_need_login,
         login_success,
         login,
         register,
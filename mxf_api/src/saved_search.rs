@@ -0,0 +1,49 @@
+use rocket::serde::json::Json;
+use rocket::{Route, State};
+use sea_orm_rocket::Connection;
+
+use super::{Claims, MXFDb};
+
+use mxf_entity::errors::JieguoResponse;
+use mxf_entity::{SavedSearchData, SnoData};
+use mxf_service::{BucketService, SavedSearchService};
+
+#[post("/saved_search", format = "json", data = "<search_data>")]
+async fn save_search(
+    user: Claims,
+    conn: Connection<'_, MXFDb>,
+    saved_search_service: &State<SavedSearchService>,
+    bucket_service: &State<BucketService>,
+    search_data: Json<SavedSearchData>,
+) -> Result<Json<JieguoResponse>, Json<JieguoResponse>> {
+    let buckets = bucket_service.get_buckets();
+    let sno = saved_search_service
+        .save_search(conn.into_inner(), user.user.uno, &search_data, &buckets)
+        .await
+        .map_err(|e| e.to_json())?;
+
+    Ok(Json(JieguoResponse {
+        jieguo: true,
+        reason: Some(sno.to_string()),
+        errors: Vec::new(),
+    }))
+}
+
+#[post("/saved_search/delete", format = "json", data = "<sno_data>")]
+async fn delete_search(
+    user: Claims,
+    conn: Connection<'_, MXFDb>,
+    saved_search_service: &State<SavedSearchService>,
+    sno_data: Json<SnoData>,
+) -> Result<Json<JieguoResponse>, Json<JieguoResponse>> {
+    saved_search_service
+        .delete_search(conn.into_inner(), sno_data.sno, user.user.uno)
+        .await
+        .map_err(|e| e.to_json())?;
+
+    Ok(JieguoResponse::success_json())
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![save_search, delete_search]
+}
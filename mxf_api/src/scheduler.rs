@@ -0,0 +1,116 @@
+use chrono::Local;
+use rocket::fairing::AdHoc;
+use rocket::tokio;
+use sea_orm_rocket::Database;
+use std::time::Duration;
+
+use mxf_service::{
+    AnalyticsService, BucketService, ExpiryService, FavoriteService, HouseService,
+    SavedSearchService,
+};
+
+use super::MXFDb;
+
+/// Time between two runs of the periodic jobs
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Time between two writes of the listing view counters
+const STATS_FLUSH_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Runs the periodic jobs in the background once Rocket has launched.
+pub fn fairing() -> AdHoc {
+    AdHoc::on_liftoff("Scheduler", |rocket| {
+        Box::pin(async move {
+            let Some(db) = MXFDb::fetch(rocket) else {
+                println!("scheduler: database unavailable, not started");
+                return;
+            };
+            let conn = db.conn.clone();
+            let Some(bucket_service) = rocket.state::<BucketService>().cloned() else {
+                println!("scheduler: search buckets unavailable, not started");
+                return;
+            };
+            if let Err(e) = bucket_service.load(&conn).await {
+                println!("scheduler: search buckets not loaded, using defaults: {}", e);
+            }
+            if let Some(analytics_service) = rocket.state::<AnalyticsService>().cloned() {
+                let conn = conn.clone();
+                tokio::spawn(async move {
+                    let mut interval = tokio::time::interval(STATS_FLUSH_INTERVAL);
+                    loop {
+                        interval.tick().await;
+                        if let Err(e) = analytics_service.flush(&conn).await {
+                            println!("scheduler: listing stats flush failed: {}", e);
+                        }
+                    }
+                });
+            }
+            let Some(expiry_service) = rocket.state::<ExpiryService>().cloned() else {
+                println!("scheduler: listing expiry unavailable, not started");
+                return;
+            };
+            let house_service = rocket.state::<HouseService>().cloned();
+            if let Some(house_service) = &house_service {
+                if let Err(e) = house_service.seed_regions(&conn).await {
+                    println!("scheduler: regions not seeded: {}", e);
+                }
+            }
+            let alert_queue = house_service.as_ref().and_then(|s| s.alert_queue().take_receiver());
+            if let Some(alert_queue) = alert_queue {
+                let bucket_service = bucket_service.clone();
+                tokio::spawn(SavedSearchService::run_alerts(
+                    conn.clone(),
+                    bucket_service,
+                    alert_queue,
+                ));
+            }
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+                loop {
+                    interval.tick().await;
+                    let now = Local::now().naive_local();
+                    // Picks up bucket edits made through other instances
+                    if let Err(e) = bucket_service.load(&conn).await {
+                        println!("scheduler: search bucket reload failed: {}", e);
+                    }
+                    if let Err(e) = FavoriteService::notify_leases_ended(&conn, now).await {
+                        println!("scheduler: lease sweep failed: {}", e);
+                    }
+                    if let Err(e) = expiry_service.send_reminders(&conn, now).await {
+                        println!("scheduler: expiry reminders failed: {}", e);
+                    }
+                    match expiry_service.expire_stale(&conn, now).await {
+                        Ok(0) => (),
+                        Ok(expired) => {
+                            println!("scheduler: {} stale listings unlisted", expired);
+                            if let Some(house_service) = &house_service {
+                                house_service.listings_changed();
+                            }
+                        }
+                        Err(e) => println!("scheduler: listing expiry failed: {}", e),
+                    }
+                    // Daily, on the first tick of each day
+                    if let Err(e) = SavedSearchService::send_digests(&conn, now).await {
+                        println!("scheduler: saved search digest failed: {}", e);
+                    }
+                }
+            });
+        })
+    })
+}
+
+/// Writes the listing view counters still in memory when Rocket shuts down.
+pub fn shutdown_fairing() -> AdHoc {
+    AdHoc::on_shutdown("Listing stats flush", |rocket| {
+        Box::pin(async move {
+            let (Some(db), Some(analytics_service)) =
+                (MXFDb::fetch(rocket), rocket.state::<AnalyticsService>())
+            else {
+                return;
+            };
+            if let Err(e) = analytics_service.flush(&db.conn).await {
+                println!("scheduler: listing stats lost at shutdown: {}", e);
+            }
+        })
+    })
+}
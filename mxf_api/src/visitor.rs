@@ -0,0 +1,50 @@
+use mxf_entity::StatsEvent;
+use mxf_service::AnalyticsService;
+use rocket::request::{FromRequest, Outcome};
+
+use super::Claims;
+
+/// Who is looking at a page, for counting listing views. Logged in users are
+/// told apart by account, others by address and user agent.
+pub(crate) struct Visitor {
+    id: String,
+    is_bot: bool,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Visitor {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(request: &'r rocket::Request<'_>) -> Outcome<Self, Self::Error> {
+        let user_agent = request.headers().get_one("User-Agent");
+        let id = match request.guard::<Claims>().await {
+            Outcome::Success(claims) => format!("u{}", claims.user.uno),
+            _ => format!(
+                "{}|{}",
+                request
+                    .client_ip()
+                    .map(|ip| ip.to_string())
+                    .unwrap_or_default(),
+                user_agent.unwrap_or_default()
+            ),
+        };
+        Outcome::Success(Visitor {
+            id,
+            is_bot: AnalyticsService::is_bot(user_agent),
+        })
+    }
+}
+
+impl Visitor {
+    /// Counts `event` on the houses, unless the visitor is a bot.
+    pub(crate) fn record(
+        &self,
+        analytics_service: &AnalyticsService,
+        hnos: &[u32],
+        event: StatsEvent,
+    ) {
+        if !self.is_bot {
+            analytics_service.record(&self.id, hnos, event);
+        }
+    }
+}
@@ -1,20 +1,103 @@
+pub mod favorite;
+pub mod house_flag;
 pub mod house_listing;
+pub mod house_revision;
+pub mod house_stats;
+pub mod job_run;
+pub mod notification;
 pub mod order;
+pub mod price_history;
+pub mod region;
+pub mod saved_search;
+pub mod saved_search_alert;
+pub mod saved_search_match;
+pub mod search_bucket;
+pub mod search_index;
 pub mod user;
 
+pub use favorite::ActiveModel as FavoriteActiveModel;
+pub use favorite::Column as FavoriteColumn;
+pub use favorite::Entity as FavoriteEntity;
+pub use favorite::Model as FavoriteModel;
+
+pub use house_flag::ActiveModel as HouseFlagActiveModel;
+pub use house_flag::Column as HouseFlagColumn;
+pub use house_flag::Entity as HouseFlagEntity;
+pub use house_flag::FlagKind;
+pub use house_flag::Model as HouseFlagModel;
+
 pub use house_listing::ActiveModel as HouseListingActiveModel;
 pub use house_listing::Column as HouseListingColumn;
 pub use house_listing::Entity as HouseListingEntity;
 pub use house_listing::Model as HouseListingModel;
 pub use house_listing::ListStatus;
 
+pub use house_revision::ActiveModel as HouseRevisionActiveModel;
+pub use house_revision::Column as HouseRevisionColumn;
+pub use house_revision::Entity as HouseRevisionEntity;
+pub use house_revision::Model as HouseRevisionModel;
+
+pub use house_stats::ActiveModel as HouseStatsActiveModel;
+pub use house_stats::Column as HouseStatsColumn;
+pub use house_stats::Entity as HouseStatsEntity;
+pub use house_stats::Model as HouseStatsModel;
+
+pub use job_run::ActiveModel as JobRunActiveModel;
+pub use job_run::Column as JobRunColumn;
+pub use job_run::Entity as JobRunEntity;
+pub use job_run::Model as JobRunModel;
+
+pub use region::ActiveModel as RegionActiveModel;
+pub use region::Column as RegionColumn;
+pub use region::Entity as RegionEntity;
+pub use region::Model as RegionModel;
+pub use region::RegionLevel;
+
+pub use search_bucket::ActiveModel as SearchBucketActiveModel;
+pub use search_bucket::BucketKind;
+pub use search_bucket::Column as SearchBucketColumn;
+pub use search_bucket::Entity as SearchBucketEntity;
+pub use search_bucket::Model as SearchBucketModel;
+
 pub use user::ActiveModel as UserActiveModel;
 pub use user::Column as UserColumn;
 pub use user::Entity as UserEntity;
 pub use user::Model as UserModel;
 
+pub use notification::ActiveModel as NotificationActiveModel;
+pub use notification::Column as NotificationColumn;
+pub use notification::Entity as NotificationEntity;
+pub use notification::Model as NotificationModel;
+pub use notification::NotificationType;
+
 pub use order::ActiveModel as OrderActiveModel;
 pub use order::Column as OrderColumn;
 pub use order::Entity as OrderEntity;
 pub use order::Model as OrderModel;
 pub use order::OrderType;
+
+pub use price_history::ActiveModel as PriceHistoryActiveModel;
+pub use price_history::Column as PriceHistoryColumn;
+pub use price_history::Entity as PriceHistoryEntity;
+pub use price_history::Model as PriceHistoryModel;
+
+pub use saved_search::ActiveModel as SavedSearchActiveModel;
+pub use saved_search::AlertFrequency;
+pub use saved_search::Column as SavedSearchColumn;
+pub use saved_search::Entity as SavedSearchEntity;
+pub use saved_search::Model as SavedSearchModel;
+
+pub use saved_search_alert::ActiveModel as SavedSearchAlertActiveModel;
+pub use saved_search_alert::Column as SavedSearchAlertColumn;
+pub use saved_search_alert::Entity as SavedSearchAlertEntity;
+pub use saved_search_alert::Model as SavedSearchAlertModel;
+
+pub use saved_search_match::ActiveModel as SavedSearchMatchActiveModel;
+pub use saved_search_match::Column as SavedSearchMatchColumn;
+pub use saved_search_match::Entity as SavedSearchMatchEntity;
+pub use saved_search_match::Model as SavedSearchMatchModel;
+
+pub use search_index::ActiveModel as SearchIndexActiveModel;
+pub use search_index::Column as SearchIndexColumn;
+pub use search_index::Entity as SearchIndexEntity;
+pub use search_index::Model as SearchIndexModel;
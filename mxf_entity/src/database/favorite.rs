@@ -0,0 +1,43 @@
+use chrono::NaiveDateTime;
+use rocket::serde::{Deserialize, Serialize};
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "favorites")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub uno: u32,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub hno: u32,
+    pub fdate: NaiveDateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::house_listing::Entity",
+        from = "Column::Hno",
+        to = "super::house_listing::Column::Hno"
+    )]
+    HouseListing,
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::Uno",
+        to = "super::user::Column::Uno"
+    )]
+    User,
+}
+
+impl Related<super::house_listing::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::HouseListing.def()
+    }
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,57 @@
+use chrono::NaiveDateTime;
+use rocket::serde::{Deserialize, Serialize};
+use sea_orm::entity::prelude::*;
+
+/// Suspicion raised by the listing checks, shown to staff in the review queue.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "house_flags")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub fno: u32,
+    pub hno: u32,
+    pub fkind: FlagKind,
+    /// The suspected original of a duplicate.
+    pub fother: Option<u32>,
+    pub fdetail: String,
+    pub fdate: NaiveDateTime,
+    pub fresolved: bool,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+    PartialOrd,
+    Ord,
+    Copy,
+)]
+#[sea_orm(rs_type = "u32", db_type = "Integer")]
+pub enum FlagKind {
+    #[sea_orm(num_value = 0)]
+    Duplicate,
+    #[sea_orm(num_value = 1)]
+    PriceOutlier,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::house_listing::Entity",
+        from = "Column::Hno",
+        to = "super::house_listing::Column::Hno"
+    )]
+    HouseListing,
+}
+
+impl Related<super::house_listing::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::HouseListing.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
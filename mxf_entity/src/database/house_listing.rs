@@ -1,7 +1,8 @@
+use chrono::NaiveDateTime;
 use rocket::serde::{Deserialize, Serialize};
 use sea_orm::entity::prelude::*;
 
-#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
 #[sea_orm(table_name = "house_listings")]
 pub struct Model {
     #[sea_orm(primary_key)]
@@ -15,6 +16,82 @@ pub struct Model {
     pub hlandlore: u32,
     pub hsuite: String,
     pub hunlisted: ListStatus,
+    /// Latitude resolved by the offline geocoder, `None` if the address is unknown.
+    pub hlat: Option<f64>,
+    /// Longitude resolved by the offline geocoder, `None` if the address is unknown.
+    pub hlng: Option<f64>,
+    #[sea_orm(column_type = "Text")]
+    #[serde(default)]
+    pub hdesc: String,
+    /// Reason given by staff for the last rejection.
+    #[serde(default)]
+    pub hreason: Option<String>,
+    /// Agency's own reference, used to match rows of bulk imports.
+    #[serde(default)]
+    pub hextref: Option<String>,
+    /// When the house last went public.
+    #[serde(default)]
+    pub hlisted_at: Option<NaiveDateTime>,
+    /// When the landlord last edited the house or confirmed it is still
+    /// available. Listings expire when this gets too old.
+    #[serde(default)]
+    pub hrefreshed_at: Option<NaiveDateTime>,
+    /// Room counts parsed from `hlo`, `None` where the layout does not say.
+    #[serde(default)]
+    pub hbedrooms: Option<u32>,
+    #[serde(default)]
+    pub hliving_rooms: Option<u32>,
+    #[serde(default)]
+    pub hbathrooms: Option<u32>,
+    /// Most specific region the house is filed under.
+    #[serde(default)]
+    pub hregion: Option<u32>,
+    /// Monthly rent per m², kept by `with_unit_price`.
+    #[serde(default)]
+    pub hunit_price: Option<f64>,
+}
+
+impl Model {
+    /// Fills the room counts from `hlo` and rewrites its room counts from
+    /// them, so that layouts read the same whichever way they were typed.
+    /// Text after the room counts and layouts without any are kept as typed.
+    pub fn with_layout(mut self) -> Self {
+        let split = crate::Layout::split(&self.hlo)
+            .map(|(layout, rest)| (layout, format!("{}{}", layout, rest)));
+        let layout = match split {
+            Some((layout, hlo)) => {
+                self.hlo = hlo;
+                layout
+            }
+            None => crate::Layout::default(),
+        };
+        self.hbedrooms = layout.bedrooms;
+        self.hliving_rooms = layout.living_rooms;
+        self.hbathrooms = layout.bathrooms;
+        self
+    }
+
+    /// Monthly rent per m² to two decimals, `None` without an area.
+    pub fn unit_price(&self) -> Option<f64> {
+        (self.harea > 0).then(|| (self.hprice as f64 / self.harea as f64 * 100.0).round() / 100.0)
+    }
+
+    /// Whether user `uno`, if any, may see the house. Listed houses are
+    /// public, deleted ones are left to staff, and the rest are also shown to
+    /// their landlord.
+    pub fn is_visible_to(&self, uno: Option<u32>, staff: bool) -> bool {
+        match self.hunlisted {
+            ListStatus::Listed => true,
+            ListStatus::Deleted => staff,
+            _ => staff || uno == Some(self.hlandlore),
+        }
+    }
+
+    /// Fills `hunit_price` from `hprice` and `harea`.
+    pub fn with_unit_price(mut self) -> Self {
+        self.hunit_price = self.unit_price();
+        self
+    }
 }
 
 #[derive(
@@ -29,18 +106,37 @@ pub struct Model {
     PartialOrd,
     Ord,
     Copy,
+    Default,
 )]
 #[sea_orm(rs_type = "u32", db_type = "Integer")]
 pub enum ListStatus {
+    #[default]
     #[sea_orm(num_value = 0)]
     Listed,
     #[sea_orm(num_value = 1)]
     Unlisted,
+    #[sea_orm(num_value = 2)]
+    Draft,
+    #[sea_orm(num_value = 3)]
+    PendingReview,
+    #[sea_orm(num_value = 4)]
+    Rejected,
+    #[sea_orm(num_value = 5)]
+    Archived,
+    #[sea_orm(num_value = 6)]
+    Deleted,
 }
 
-impl Default for ListStatus {
-    fn default() -> Self {
-        ListStatus::Listed
+impl ListStatus {
+    /// Whether staff approved the listing at some point, so the landlord may
+    /// toggle it between listed and unlisted without another review.
+    pub fn is_approved(&self) -> bool {
+        *self == ListStatus::Listed || *self == ListStatus::Unlisted
+    }
+
+    /// Archived or soft-deleted: kept for order history but no longer editable.
+    pub fn is_archived(&self) -> bool {
+        *self == ListStatus::Archived || *self == ListStatus::Deleted
     }
 }
 
@@ -54,6 +150,24 @@ pub enum Relation {
         to = "super::user::Column::Uno"
     )]
     User,
+    #[sea_orm(has_one = "super::search_index::Entity")]
+    SearchIndex,
+    #[sea_orm(has_many = "super::favorite::Entity")]
+    Favorite,
+    #[sea_orm(has_many = "super::price_history::Entity")]
+    PriceHistory,
+    #[sea_orm(has_many = "super::house_revision::Entity")]
+    HouseRevision,
+    #[sea_orm(has_many = "super::house_flag::Entity")]
+    HouseFlag,
+    #[sea_orm(has_many = "super::house_stats::Entity")]
+    HouseStats,
+    #[sea_orm(
+        belongs_to = "super::region::Entity",
+        from = "Column::Hregion",
+        to = "super::region::Column::Rno"
+    )]
+    Region,
 }
 
 impl Related<super::order::Entity> for Entity {
@@ -62,6 +176,42 @@ impl Related<super::order::Entity> for Entity {
     }
 }
 
+impl Related<super::favorite::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Favorite.def()
+    }
+}
+
+impl Related<super::house_flag::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::HouseFlag.def()
+    }
+}
+
+impl Related<super::region::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Region.def()
+    }
+}
+
+impl Related<super::house_stats::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::HouseStats.def()
+    }
+}
+
+impl Related<super::house_revision::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::HouseRevision.def()
+    }
+}
+
+impl Related<super::price_history::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PriceHistory.def()
+    }
+}
+
 impl Related<super::user::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::User.def()
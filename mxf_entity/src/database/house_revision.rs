@@ -0,0 +1,39 @@
+use chrono::NaiveDateTime;
+use rocket::serde::{Deserialize, Serialize};
+use sea_orm::entity::prelude::*;
+
+/// Append-only record of a listing after each edit.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "house_revisions")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub rno: u32,
+    pub hno: u32,
+    /// uno of the landlord or staff member who made the edit, `None` for
+    /// edits made by the scheduler.
+    pub reditor: Option<u32>,
+    pub rdate: NaiveDateTime,
+    /// Comma-separated names of the changed columns; empty on creation.
+    pub rfields: String,
+    /// The whole listing as JSON, after the edit.
+    #[sea_orm(column_type = "Text")]
+    pub rsnapshot: String,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::house_listing::Entity",
+        from = "Column::Hno",
+        to = "super::house_listing::Column::Hno"
+    )]
+    HouseListing,
+}
+
+impl Related<super::house_listing::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::HouseListing.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,37 @@
+use chrono::NaiveDate;
+use rocket::serde::{Deserialize, Serialize};
+use sea_orm::entity::prelude::*;
+
+/// Daily counters of one house, flushed from memory by `AnalyticsService`.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "house_stats_daily")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub hno: u32,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub sdate: NaiveDate,
+    /// Appearances in search results, once per visitor and day.
+    pub simpressions: u32,
+    /// Detail page views, once per visitor and day.
+    pub sviews: u32,
+    pub sfavorites: u32,
+    pub srequests: u32,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::house_listing::Entity",
+        from = "Column::Hno",
+        to = "super::house_listing::Column::Hno"
+    )]
+    HouseListing,
+}
+
+impl Related<super::house_listing::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::HouseListing.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
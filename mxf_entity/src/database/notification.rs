@@ -0,0 +1,65 @@
+use chrono::NaiveDateTime;
+use rocket::serde::{Deserialize, Serialize};
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "notifications")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub nno: u32,
+    pub uno: u32,
+    pub hno: u32,
+    pub ntype: NotificationType,
+    pub ncontent: String,
+    pub ndate: NaiveDateTime,
+    pub nread: bool,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+    PartialOrd,
+    Ord,
+    Copy,
+)]
+#[sea_orm(rs_type = "u32", db_type = "Integer")]
+pub enum NotificationType {
+    #[sea_orm(num_value = 0)]
+    PriceChanged,
+    #[sea_orm(num_value = 1)]
+    Unlisted,
+    #[sea_orm(num_value = 2)]
+    AvailableAgain,
+    #[sea_orm(num_value = 3)]
+    SearchMatch,
+    #[sea_orm(num_value = 4)]
+    ReviewResult,
+    #[sea_orm(num_value = 5)]
+    ListingExpiring,
+    #[sea_orm(num_value = 6)]
+    ListingExpired,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::Uno",
+        to = "super::user::Column::Uno"
+    )]
+    User,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,32 @@
+use chrono::NaiveDateTime;
+use rocket::serde::{Deserialize, Serialize};
+use sea_orm::entity::prelude::*;
+
+/// One price a house was listed at, from `pdate` until the next record.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "house_price_history")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub pno: u32,
+    pub hno: u32,
+    pub pprice: u32,
+    pub pdate: NaiveDateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::house_listing::Entity",
+        from = "Column::Hno",
+        to = "super::house_listing::Column::Hno"
+    )]
+    HouseListing,
+}
+
+impl Related<super::house_listing::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::HouseListing.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
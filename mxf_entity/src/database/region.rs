@@ -0,0 +1,53 @@
+use rocket::serde::{Deserialize, Serialize};
+use sea_orm::entity::prelude::*;
+
+/// A node of the city > district > neighborhood tree listings are filed under.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "regions")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub rno: u32,
+    /// `None` for cities.
+    pub rparent: Option<u32>,
+    pub rname: String,
+    pub rlevel: RegionLevel,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+    PartialOrd,
+    Ord,
+    Copy,
+)]
+#[sea_orm(rs_type = "u32", db_type = "Integer")]
+pub enum RegionLevel {
+    #[sea_orm(num_value = 0)]
+    City,
+    #[sea_orm(num_value = 1)]
+    District,
+    #[sea_orm(num_value = 2)]
+    Neighborhood,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(belongs_to = "Entity", from = "Column::Rparent", to = "Column::Rno")]
+    Parent,
+    #[sea_orm(has_many = "super::house_listing::Entity")]
+    HouseListing,
+}
+
+impl Related<super::house_listing::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::HouseListing.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,71 @@
+use chrono::NaiveDateTime;
+use rocket::serde::{Deserialize, Serialize};
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "saved_searches")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub sno: u32,
+    pub uno: u32,
+    pub sname: String,
+    /// `HouseFilter::to_query_string` of the saved filter.
+    pub squery: String,
+    pub sfrequency: AlertFrequency,
+    pub sdate: NaiveDateTime,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+    PartialOrd,
+    Ord,
+    Copy,
+)]
+#[sea_orm(rs_type = "u32", db_type = "Integer")]
+pub enum AlertFrequency {
+    #[sea_orm(num_value = 0)]
+    Immediate,
+    #[sea_orm(num_value = 1)]
+    Daily,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::Uno",
+        to = "super::user::Column::Uno"
+    )]
+    User,
+    #[sea_orm(has_many = "super::saved_search_match::Entity")]
+    SavedSearchMatch,
+    #[sea_orm(has_many = "super::saved_search_alert::Entity")]
+    SavedSearchAlert,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl Related<super::saved_search_match::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::SavedSearchMatch.def()
+    }
+}
+
+impl Related<super::saved_search_alert::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::SavedSearchAlert.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
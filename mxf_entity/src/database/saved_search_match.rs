@@ -0,0 +1,32 @@
+use chrono::NaiveDateTime;
+use rocket::serde::{Deserialize, Serialize};
+use sea_orm::entity::prelude::*;
+
+/// A house matching a daily saved search, waiting for the next digest.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "saved_search_matches")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub sno: u32,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub hno: u32,
+    pub mdate: NaiveDateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::saved_search::Entity",
+        from = "Column::Sno",
+        to = "super::saved_search::Column::Sno"
+    )]
+    SavedSearch,
+}
+
+impl Related<super::saved_search::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::SavedSearch.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,44 @@
+use rocket::serde::{Deserialize, Serialize};
+use sea_orm::entity::prelude::*;
+
+/// One band of a search page filter, edited by admins.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "search_buckets")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub bkind: BucketKind,
+    /// Position within its kind, from 1; the value of the filter field.
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub bindex: u32,
+    pub blower: u32,
+    /// Exclusive; `None` leaves the bucket open upwards.
+    pub bupper: Option<u32>,
+    pub blabel: String,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    Hash,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+    Copy,
+)]
+#[sea_orm(rs_type = "u32", db_type = "Integer")]
+pub enum BucketKind {
+    #[sea_orm(num_value = 0)]
+    Floor,
+    #[sea_orm(num_value = 1)]
+    Area,
+    #[sea_orm(num_value = 2)]
+    Price,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -15,8 +15,8 @@ pub struct Model {
     pub uphone: String,
 }
 
-impl Model {
-    pub fn default() -> Self {
+impl Default for Model {
+    fn default() -> Self {
         Self {
             uno: 0,
             uname: "".to_string(),
@@ -1,9 +1,46 @@
+pub mod bucket_data;
+pub mod calendar_data;
 pub mod errors;
+pub mod estimate_data;
+pub mod export_data;
+pub mod facet_data;
+pub mod favorite_data;
 pub mod house_filter;
+pub mod import_data;
+pub mod layout;
+pub mod listing_data;
+pub mod map_marker;
 pub mod order_data;
+pub mod page_data;
+pub mod price_data;
+pub mod query_text;
+pub mod region_data;
+pub mod revision_data;
+pub mod saved_search_data;
+pub mod search_text;
 pub mod session_data;
+pub mod stats_data;
 
-pub use errors::MXFError;
-pub use house_filter::HouseFilter;
-pub use order_data::HnoData;
+pub use bucket_data::{Bucket, BucketOption, BucketsInput, FilterBuckets, SearchBuckets};
+pub use calendar_data::{AvailabilityCalendar, CalendarPeriod, PeriodKind};
+pub use errors::{FieldError, MXFError};
+pub use estimate_data::{Comparable, ComparableSource, RentEstimate};
+pub use export_data::{ExportFormat, ExportRow};
+pub use facet_data::{AmenityCount, FacetCounts};
+pub use favorite_data::FavoriteHouse;
+pub use house_filter::{FacetedFilter, HouseFilter};
+pub use import_data::{
+    ImportAction, ImportFormat, ImportJob, ImportOptions, ImportRow, ImportRowReport, ImportStatus,
+};
+pub use layout::Layout;
+pub use listing_data::{district_base, district_spellings, ListingInput};
+pub use map_marker::MapMarker;
+pub use order_data::{HnoData, ReviewData};
+pub use page_data::{Cursor, CursorPage};
+pub use price_data::DistrictPriceTrend;
+pub use query_text::parse_query;
+pub use region_data::RegionOption;
+pub use revision_data::{FieldChange, RevisionDiff, RnoData};
+pub use saved_search_data::{SavedSearchData, SnoData};
 pub use session_data::{LoginData, RegisterData};
+pub use stats_data::{DailyStats, ListingStats, SearchCacheStats, StatsEvent};
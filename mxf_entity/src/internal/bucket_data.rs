@@ -0,0 +1,262 @@
+use rocket::form::{self, DataField, FromForm, Options, ValueField};
+use serde::{Deserialize, Serialize};
+
+use crate::{BucketKind, MXFError, SearchBucketModel};
+
+/// `lower <= value < upper`; the last bucket of a kind may have no upper end.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Bucket {
+    pub lower: u32,
+    pub upper: Option<u32>,
+    pub label: String,
+}
+
+impl Bucket {
+    fn new(lower: u32, upper: Option<u32>, label: &str) -> Self {
+        Bucket { lower, upper, label: label.to_string() }
+    }
+}
+
+/// A bucket as a search page choice.
+#[derive(Serialize, Clone, Debug)]
+pub struct BucketOption {
+    /// Value of the filter field selecting the bucket.
+    pub value: usize,
+    pub label: String,
+    pub checked: bool,
+}
+
+/// Admin's new buckets for one kind.
+#[derive(Serialize, Deserialize)]
+pub struct BucketsInput {
+    pub kind: BucketKind,
+    pub buckets: Vec<Bucket>,
+}
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct SearchBuckets {
+    pub floors: Vec<Bucket>,
+    pub areas: Vec<Bucket>,
+    pub prices: Vec<Bucket>,
+}
+
+impl Default for SearchBuckets {
+    /// The buckets seeded by the migration, used until the table is read.
+    fn default() -> Self {
+        SearchBuckets {
+            floors: vec![
+                Bucket::new(0, Some(1), "底层"),
+                Bucket::new(1, Some(10), "低楼层"),
+                Bucket::new(10, Some(20), "中楼层"),
+                Bucket::new(20, Some(30), "高楼层"),
+                Bucket::new(30, None, "顶层"),
+            ],
+            areas: vec![
+                Bucket::new(0, Some(50), "50m²以下"),
+                Bucket::new(50, Some(70), "50-70m²"),
+                Bucket::new(70, Some(90), "70-90m²"),
+                Bucket::new(90, Some(110), "90-110m²"),
+                Bucket::new(110, Some(150), "110-150m²"),
+            ],
+            prices: vec![
+                Bucket::new(0, Some(2000), "2000元以下"),
+                Bucket::new(2000, Some(4000), "2000-4000元"),
+                Bucket::new(4000, Some(6000), "4000-6000元"),
+                Bucket::new(6000, Some(10000), "6000-10000元"),
+                Bucket::new(10000, Some(15000), "10000-15000元"),
+                Bucket::new(15000, None, "15000元以上"),
+            ],
+        }
+    }
+}
+
+impl SearchBuckets {
+    pub const MAX_BUCKETS: usize = 12;
+    const MAX_LABEL_LEN: usize = 32;
+
+    pub fn get(&self, kind: BucketKind) -> &[Bucket] {
+        match kind {
+            BucketKind::Floor => &self.floors,
+            BucketKind::Area => &self.areas,
+            BucketKind::Price => &self.prices,
+        }
+    }
+
+    /// Bucket selected by a filter field value, which counts from 1.
+    pub fn bucket(&self, kind: BucketKind, value: usize) -> Option<&Bucket> {
+        value.checked_sub(1).and_then(|i| self.get(kind).get(i))
+    }
+
+    /// Choices for a filter field, `value` being its current value.
+    pub fn options(&self, kind: BucketKind, value: usize) -> Vec<BucketOption> {
+        self.get(kind)
+            .iter()
+            .enumerate()
+            .map(|(i, bucket)| BucketOption {
+                value: i + 1,
+                label: bucket.label.clone(),
+                checked: i + 1 == value,
+            })
+            .collect()
+    }
+
+    /// Stored buckets; kinds without rows keep the default ones.
+    pub fn from_rows(mut rows: Vec<SearchBucketModel>) -> Self {
+        rows.sort_by_key(|row| row.bindex);
+        let mut buckets = SearchBuckets::default();
+        for kind in [BucketKind::Floor, BucketKind::Area, BucketKind::Price] {
+            let stored: Vec<Bucket> = rows
+                .iter()
+                .filter(|row| row.bkind == kind)
+                .map(|row| Bucket::new(row.blower, row.bupper, &row.blabel))
+                .collect();
+            if stored.is_empty() {
+                continue;
+            }
+            match kind {
+                BucketKind::Floor => buckets.floors = stored,
+                BucketKind::Area => buckets.areas = stored,
+                BucketKind::Price => buckets.prices = stored,
+            }
+        }
+        buckets
+    }
+
+    /// Buckets must be labelled, ascending and disjoint, and only the last
+    /// one may be open.
+    pub fn validate(buckets: &[Bucket]) -> Result<(), MXFError> {
+        if buckets.is_empty() || buckets.len() > Self::MAX_BUCKETS {
+            return Err(MXFError::InvalidBuckets(format!(
+                "需要 1 到 {} 个区间",
+                Self::MAX_BUCKETS
+            )));
+        }
+        for (i, bucket) in buckets.iter().enumerate() {
+            let label = bucket.label.trim();
+            if label.is_empty() || label.chars().count() > Self::MAX_LABEL_LEN {
+                return Err(MXFError::InvalidBuckets(format!(
+                    "第 {} 个区间的名称须为 1 到 {} 个字",
+                    i + 1,
+                    Self::MAX_LABEL_LEN
+                )));
+            }
+            match bucket.upper {
+                Some(upper) if upper <= bucket.lower => {
+                    return Err(MXFError::InvalidBuckets(format!(
+                        "第 {} 个区间的上限须大于下限",
+                        i + 1
+                    )));
+                }
+                None if i + 1 < buckets.len() => {
+                    return Err(MXFError::InvalidBuckets(format!(
+                        "只有最后一个区间可以没有上限，第 {} 个没有",
+                        i + 1
+                    )));
+                }
+                _ => (),
+            }
+            if let Some(next) = buckets.get(i + 1) {
+                if bucket.upper.is_some_and(|upper| next.lower < upper) {
+                    return Err(MXFError::InvalidBuckets(format!(
+                        "第 {} 个区间与第 {} 个重叠或顺序颠倒",
+                        i + 1,
+                        i + 2
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The buckets a `HouseFilter` reads `c`, `m` and `p` with, attached by
+/// `HouseFilter::with_buckets`. Never taken from the form itself.
+#[derive(Default, Clone, Copy, PartialEq, Debug)]
+pub struct FilterBuckets<'r>(pub(crate) Option<&'r SearchBuckets>);
+
+#[rocket::async_trait]
+impl<'r> FromForm<'r> for FilterBuckets<'r> {
+    type Context = ();
+
+    fn init(_opts: Options) -> Self::Context {}
+
+    fn push_value(_ctxt: &mut Self::Context, _field: ValueField<'r>) {}
+
+    async fn push_data(_ctxt: &mut Self::Context, _field: DataField<'r, '_>) {}
+
+    fn finalize(_ctxt: Self::Context) -> form::Result<'r, Self> {
+        Ok(FilterBuckets(None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bucket(lower: u32, upper: Option<u32>) -> Bucket {
+        Bucket::new(lower, upper, "区间")
+    }
+
+    fn rejected(buckets: &[Bucket], problem: &str) {
+        match SearchBuckets::validate(buckets) {
+            Err(MXFError::InvalidBuckets(message)) => {
+                assert!(message.contains(problem), "{}", message)
+            }
+            other => panic!("{:?} should be rejected, got {:?}", buckets, other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn defaults_are_valid() {
+        let buckets = SearchBuckets::default();
+        for kind in [BucketKind::Floor, BucketKind::Area, BucketKind::Price] {
+            assert!(SearchBuckets::validate(buckets.get(kind)).is_ok());
+        }
+    }
+
+    #[test]
+    fn accepts_gaps_and_open_last_bucket() {
+        let buckets = [bucket(0, Some(10)), bucket(20, Some(30)), bucket(30, None)];
+        assert!(SearchBuckets::validate(&buckets).is_ok());
+        assert!(SearchBuckets::validate(&[bucket(0, None)]).is_ok());
+    }
+
+    #[test]
+    fn rejects_bad_buckets() {
+        rejected(&[], "1 到 12");
+        rejected(&vec![bucket(0, Some(1)); SearchBuckets::MAX_BUCKETS + 1], "1 到 12");
+        rejected(&[Bucket::new(0, Some(10), "  ")], "名称");
+        rejected(&[Bucket::new(0, Some(10), &"长".repeat(33))], "名称");
+        rejected(&[bucket(10, Some(10))], "上限须大于下限");
+        rejected(&[bucket(10, Some(5))], "上限须大于下限");
+        rejected(&[bucket(0, None), bucket(10, Some(20))], "只有最后一个");
+        rejected(&[bucket(0, Some(20)), bucket(10, Some(30))], "重叠");
+        rejected(&[bucket(20, Some(30)), bucket(0, Some(10))], "重叠");
+    }
+
+    #[test]
+    fn from_rows_keeps_default_kinds() {
+        let rows = vec![
+            SearchBucketModel {
+                bkind: BucketKind::Price,
+                bindex: 2,
+                blower: 3000,
+                bupper: None,
+                blabel: "3000元以上".to_string(),
+            },
+            SearchBucketModel {
+                bkind: BucketKind::Price,
+                bindex: 1,
+                blower: 0,
+                bupper: Some(3000),
+                blabel: "3000元以下".to_string(),
+            },
+        ];
+        let buckets = SearchBuckets::from_rows(rows);
+        assert_eq!(buckets.prices, vec![
+            Bucket::new(0, Some(3000), "3000元以下"),
+            Bucket::new(3000, None, "3000元以上"),
+        ]);
+        assert_eq!(buckets.floors, SearchBuckets::default().floors);
+    }
+}
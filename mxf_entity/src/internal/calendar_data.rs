@@ -0,0 +1,29 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PeriodKind {
+    /// Covered by a confirmed lease.
+    Booked,
+    /// Covered by a lease request the landlord has not answered yet.
+    Pending,
+    Free,
+}
+
+/// Days from `start` up to, but not including, `end`. The last free period
+/// of a calendar has no end.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CalendarPeriod {
+    pub kind: PeriodKind,
+    pub start: NaiveDate,
+    pub end: Option<NaiveDate>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AvailabilityCalendar {
+    pub hno: u32,
+    /// Consecutive periods starting today.
+    pub periods: Vec<CalendarPeriod>,
+    /// First day not booked nor pending.
+    pub available_from: NaiveDate,
+}
@@ -11,6 +11,9 @@ use thiserror::Error;
 pub struct JieguoResponse {
     pub jieguo: bool,
     pub reason: Option<String>,
+    /// Problems with single input fields, for forms to show next to them.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
 }
 
 impl JieguoResponse {
@@ -18,10 +21,32 @@ impl JieguoResponse {
         Json(Self {
             jieguo: true,
             reason: None,
+            errors: Vec::new(),
         })
     }
 }
 
+#[derive(Serialize, Clone, Debug)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, message: String) -> Self {
+        Self {
+            field: field.to_string(),
+            message,
+        }
+    }
+}
+
+impl std::fmt::Display for FieldError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum MXFError {
 
@@ -61,9 +86,51 @@ pub enum MXFError {
     #[error("user is not admin")]
     NotAdmin,
 
+    #[error("user is not staff")]
+    NotStaff,
+
+    #[error("house is not pending review: {}", .0)]
+    NotPendingReview(u32),
+
+    #[error("house has an active lease or pending request: {}", .0)]
+    HouseInUse(u32),
+
+    #[error("house is archived: {}", .0)]
+    HouseArchived(u32),
+
     #[error("house unavailable")]
     HouseUnavailable(u32),
 
+    #[error("revision not found: {}", .0)]
+    RevisionNotFound(u32),
+
+    #[error("invalid search filter: {}", .0)]
+    InvalidFilter(String),
+
+    #[error("invalid cursor: {}", .0)]
+    InvalidCursor(String),
+
+    #[error("invalid query: {}", .0)]
+    InvalidQuery(String),
+
+    #[error("invalid search buckets: {}", .0)]
+    InvalidBuckets(String),
+
+    #[error("invalid listing: {}", .0.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("; "))]
+    InvalidListing(Vec<FieldError>),
+
+    #[error("invalid import file: {}", .0)]
+    InvalidImport(String),
+
+    #[error("import job not found: {}", .0)]
+    ImportNotFound(u32),
+
+    #[error("saved search not found: {}", .0)]
+    SavedSearchNotFound(u32),
+
+    #[error("invalid saved search: {}", .0.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("; "))]
+    InvalidSavedSearch(Vec<FieldError>),
+
     // server internal errors
 
     #[error("cache error")]
@@ -78,15 +145,22 @@ impl MXFError {
     where
         U: TryInto<Reference<'static>>,
     {
-        println!("error: {}", self.to_string());
+        println!("error: {}", self);
         Flash::error(Redirect::to(path), self.to_string())
     }
 
     pub fn to_json(&self) -> Json<JieguoResponse> {
-        println!("error: {}", self.to_string());
+        println!("error: {}", self);
+        let errors = match self {
+            MXFError::InvalidListing(errors) | MXFError::InvalidSavedSearch(errors) => {
+                errors.clone()
+            }
+            _ => Vec::new(),
+        };
         Json(JieguoResponse {
             jieguo: false,
             reason: Some(self.to_string()),
+            errors,
         })
     }
 }
@@ -0,0 +1,44 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+/// Where the price of a comparable house comes from.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+pub enum ComparableSource {
+    /// Asked by a house listed now.
+    Listed,
+    /// Last asked by a house no longer listed.
+    Past,
+    /// Asked when a lease of the house was confirmed.
+    Leased,
+}
+
+/// A house a rent estimate was based on, without its address, as landlords
+/// estimating a rent may look at any house of the district.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Comparable {
+    pub hno: u32,
+    pub source: ComparableSource,
+    pub hdistrict: String,
+    pub hlo: String,
+    pub hflr: u32,
+    pub harea: u32,
+    /// Monthly rent.
+    pub price: u32,
+    /// Monthly rent per m².
+    pub unit_price: f64,
+    /// When the price was asked or the lease confirmed, if known.
+    pub date: Option<NaiveDateTime>,
+    /// From 0 to 1, how closely the house resembles the one estimated.
+    pub similarity: f64,
+}
+
+/// Suggested monthly rent, `None` throughout when too few comparables were
+/// found.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct RentEstimate {
+    pub low: Option<u32>,
+    pub median: Option<u32>,
+    pub high: Option<u32>,
+    /// Most similar first.
+    pub comparables: Vec<Comparable>,
+}
@@ -0,0 +1,61 @@
+use rocket::form::FromFormField;
+use serde::{Deserialize, Serialize};
+
+use crate::{HouseListingModel, ListStatus};
+
+#[derive(FromFormField, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Jsonl,
+    Xlsx,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Jsonl => "jsonl",
+            ExportFormat::Xlsx => "xlsx",
+        }
+    }
+}
+
+/// A house as exported: the columns of the CSV and XLSX files, leaving out
+/// review notes and the landlord.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ExportRow {
+    pub hno: u32,
+    /// Only in a landlord's own portfolio.
+    pub hextref: Option<String>,
+    pub hdistrict: String,
+    pub haddr: String,
+    pub hlo: String,
+    pub hflr: u32,
+    pub harea: u32,
+    pub hprice: u32,
+    pub hsuite: String,
+    pub hunlisted: ListStatus,
+    pub hlat: Option<f64>,
+    pub hlng: Option<f64>,
+    pub hdesc: String,
+}
+
+impl ExportRow {
+    pub fn new(house: HouseListingModel, with_extref: bool) -> Self {
+        Self {
+            hno: house.hno,
+            hextref: house.hextref.filter(|_| with_extref),
+            hdistrict: house.hdistrict,
+            haddr: house.haddr,
+            hlo: house.hlo,
+            hflr: house.hflr,
+            harea: house.harea,
+            hprice: house.hprice,
+            hsuite: house.hsuite,
+            hunlisted: house.hunlisted,
+            hlat: house.hlat,
+            hlng: house.hlng,
+            hdesc: house.hdesc,
+        }
+    }
+}
@@ -0,0 +1,20 @@
+use serde::Serialize;
+
+/// Listed houses in each bucket of the search page, every facet counted with
+/// its own choice left out of the search. Index 0 of a bucket list counts all
+/// choices of that facet.
+#[derive(Serialize, Clone, Default, Debug)]
+pub struct FacetCounts {
+    pub floors: Vec<u64>,
+    pub areas: Vec<u64>,
+    pub prices: Vec<u64>,
+    pub suites: Vec<AmenityCount>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct AmenityCount {
+    pub name: String,
+    pub count: u64,
+    /// Query string of the search looking for this amenity instead.
+    pub query: String,
+}
@@ -0,0 +1,12 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+use crate::HouseListingModel;
+
+/// A favorited house with its current state, as shown on `/my_favorites`.
+#[derive(Serialize, Debug)]
+pub struct FavoriteHouse {
+    pub house: HouseListingModel,
+    pub fdate: NaiveDateTime,
+    pub available: bool,
+}
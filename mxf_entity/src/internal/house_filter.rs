@@ -1,23 +1,48 @@
-use rocket::form::FromForm;
+use chrono::NaiveDate;
+use rocket::form::{Form, FromForm, ValueField};
+use rocket::http::RawStr;
 use rocket::serde::ser::SerializeStruct;
 use rocket::serde::Serialize;
+use sea_orm::sea_query::{Expr, Order, SimpleExpr};
 use sea_orm::{ColumnTrait, Condition};
 use std::convert::From;
 
-use crate::HouseListingColumn;
+use crate::query_text::{amenity_alias, quote, quoted};
+use crate::search_text::segment;
+use crate::{
+    BucketKind, BucketOption, FacetCounts, FilterBuckets, HouseListingColumn, HouseListingEntity,
+    Layout, MXFError, SearchBuckets,
+};
 
-#[derive(FromForm, Default, Copy, Clone, Hash, PartialEq, Eq, Debug)]
+#[derive(FromForm, Default, Copy, Clone, PartialEq, Debug)]
 pub struct HouseFilter<'r> {
+    /// Free text matched against the search index.
+    #[field(name = "k", default = "")]
+    _keywords: &'r str,
+
     #[field(name = "q", default = "")]
     _district: &'r str,
 
+    /// Region id; houses filed under it or any region below it match.
+    #[field(name = "rg")]
+    _region: Option<u32>,
+
     #[field(name = "f", default = "")]
     _house_type: &'r str,
 
-    #[field(name = "c", default = 0, validate = range(0..=6))]
+    /// Buckets from `SearchBuckets`, counting from 1; 0 for any. Only read
+    /// once `with_buckets` has checked them.
+    #[field(name = "c", default = 0)]
     floor_enum: usize,
 
-    #[field(name = "m", default = 0, validate = range(0..=6))]
+    /// Floor range, overriding `c` like `bm` and `em` override `m`.
+    #[field(name = "bf")]
+    _floor_lower: Option<u32>,
+
+    #[field(name = "ef")]
+    _floor_upper: Option<u32>,
+
+    #[field(name = "m", default = 0)]
     area_enum: usize,
 
     #[field(name = "bm")]
@@ -32,104 +57,542 @@ pub struct HouseFilter<'r> {
     #[field(name = "ep")]
     _price_upper: Option<u32>,
 
+    /// Price band, overridden by `bp` and `ep` like `m` by `bm` and `em`.
+    #[field(name = "p", default = 0)]
+    price_enum: usize,
+
+    /// Monthly rent per m² range, upper end excluded.
+    #[field(name = "bu")]
+    _unit_price_lower: Option<u32>,
+
+    #[field(name = "eu")]
+    _unit_price_upper: Option<u32>,
+
     #[field(name = "s", default = "")]
     _suite: &'r str,
 
+    /// Room count ranges, both ends included.
+    #[field(name = "bs")]
+    _bedrooms_lower: Option<u32>,
+
+    #[field(name = "es")]
+    _bedrooms_upper: Option<u32>,
+
+    #[field(name = "bt")]
+    _living_rooms_lower: Option<u32>,
+
+    #[field(name = "et")]
+    _living_rooms_upper: Option<u32>,
+
+    #[field(name = "bw")]
+    _bathrooms_lower: Option<u32>,
+
+    #[field(name = "ew")]
+    _bathrooms_upper: Option<u32>,
+
+    #[field(name = "lat")]
+    _lat: Option<f64>,
+
+    #[field(name = "lng")]
+    _lng: Option<f64>,
+
+    /// Search radius around (`lat`, `lng`) in meters.
+    #[field(name = "r")]
+    _radius: Option<u32>,
+
+    #[field(name = "minlat")]
+    _min_lat: Option<f64>,
+
+    #[field(name = "maxlat")]
+    _max_lat: Option<f64>,
+
+    #[field(name = "minlng")]
+    _min_lng: Option<f64>,
+
+    #[field(name = "maxlng")]
+    _max_lng: Option<f64>,
+
+    /// Only houses whose price dropped recently.
+    #[field(name = "d", default = false)]
+    _price_dropped: bool,
+
+    /// Only houses neither let nor requested on this day, as `YYYY-MM-DD`.
+    #[field(name = "af", default = "", validate = with(|d: &&str| d.is_empty()
+        || NaiveDate::parse_from_str(d, HouseFilter::DATE_FORMAT).is_ok(), "invalid date"))]
+    _available_from: &'r str,
+
+    /// One of `SORTS`; by relevance to `k` if empty.
+    #[field(name = "o", default = "", validate = with(|o: &&str| o.is_empty()
+        || HouseFilter::SORTS.iter().any(|(v, _)| v == o), "unknown sort order"))]
+    _sort: &'r str,
+
     #[field(default = 1, validate = range(1..))]
     pub page: u64,
+
+    _buckets: FilterBuckets<'r>,
 }
 
 impl HouseFilter<'_> {
-    const AREAS: [u32; 6] = [0, 50, 70, 90, 110, 150];
-    const FLOORS: [u32; 6] = [0, 1, 10, 20, 30, 31];
-    const CHECKED: &'static str = "checked";
+    /// Amenities the search page offers to narrow down by.
+    pub const AMENITIES: [&'static str; 10] =
+        ["空调", "暖气", "冰箱", "洗衣机", "热水器", "电视", "宽带", "电梯", "燃气", "独立卫生间"];
+    const METERS_PER_DEGREE: f64 = 111_320.0;
+    /// A house counts as reduced while its price is below the highest price
+    /// it had within this many days, counting the one it had when they began.
+    pub const PRICE_DROP_DAYS: i64 = 30;
+    pub const DATE_FORMAT: &'static str = "%Y-%m-%d";
+    /// Sort orders besides relevance, with their names on the search page.
+    pub const SORTS: [(&'static str, &'static str); 2] =
+        [("unit_price_asc", "每平米租金从低到高"), ("unit_price_desc", "每平米租金从高到低")];
+
+    pub fn keywords(&self) -> Option<&str> {
+        if !self._keywords.trim().is_empty() {
+            Some(self._keywords.trim())
+        } else {
+            None
+        }
+    }
+
+    /// Relevance of each house to `keywords`, for ordering search results.
+    pub fn relevance(&self) -> Option<SimpleExpr> {
+        self.keywords().map(|k| {
+            Expr::cust_with_values(
+                "(SELECT MATCH(`content`) AGAINST (?) FROM `house_search_index` \
+                 WHERE `house_search_index`.`hno` = `house_listings`.`hno`)",
+                [segment(k)],
+            )
+        })
+    }
+
+    /// Column the results are sorted by before `hno`, and its direction.
+    pub fn sort_key(&self) -> Option<(SimpleExpr, Order)> {
+        let unit_price = || Expr::col((HouseListingEntity, HouseListingColumn::HunitPrice)).into();
+        match self.sort() {
+            Some("unit_price_asc") => Some((unit_price(), Order::Asc)),
+            Some("unit_price_desc") => Some((unit_price(), Order::Desc)),
+            _ => self.relevance().map(|r| (r, Order::Desc)),
+        }
+    }
+
+    pub fn sort(&self) -> Option<&str> {
+        if self._sort.is_empty() {
+            None
+        } else {
+            Some(self._sort)
+        }
+    }
 
     pub fn district(&self) -> Option<&str> {
-        if self._district.len() > 0 {
+        if !self._district.is_empty() {
             Some(self._district)
         } else {
             None
         }
     }
 
+    pub fn region(&self) -> Option<u32> {
+        self._region
+    }
+
+    /// The same search in another region, or in all regions for `None`.
+    pub fn with_region(&self, region: Option<u32>) -> Self {
+        let mut filter = *self;
+        filter._region = region;
+        filter
+    }
+
     pub fn house_type(&self) -> Option<&str> {
-        if self._house_type.len() > 0 {
+        if !self._house_type.is_empty() {
             Some(self._house_type)
         } else {
             None
         }
     }
 
+    /// (lower, upper) bedroom count.
+    pub fn bedrooms(&self) -> (Option<u32>, Option<u32>) {
+        (self._bedrooms_lower, self._bedrooms_upper)
+    }
+
+    pub fn living_rooms(&self) -> (Option<u32>, Option<u32>) {
+        (self._living_rooms_lower, self._living_rooms_upper)
+    }
+
+    pub fn bathrooms(&self) -> (Option<u32>, Option<u32>) {
+        (self._bathrooms_lower, self._bathrooms_upper)
+    }
+
     pub fn suite(&self) -> Option<&str> {
-        if self._suite.len() > 0 {
+        if !self._suite.is_empty() {
             Some(self._suite)
         } else {
             None
         }
     }
 
-    pub fn area_lower(&self) -> Option<u32> {
-        if let Some(area_lower) = self._area_lower {
-            Some(area_lower)
-        } else if 0 < self.area_enum && self.area_enum < Self::AREAS.len() {
-            Some(Self::AREAS[self.area_enum - 1])
-        } else {
-            None
+    /// Selected bucket of a kind, 0 for none, and its explicit bounds.
+    fn bucket_fields(&self, kind: BucketKind) -> (usize, Option<u32>, Option<u32>) {
+        match kind {
+            BucketKind::Floor => (self.floor_enum, self._floor_lower, self._floor_upper),
+            BucketKind::Area => (self.area_enum, self._area_lower, self._area_upper),
+            BucketKind::Price => (self.price_enum, self._price_lower, self._price_upper),
         }
     }
 
-    pub fn area_upper(&self) -> Option<u32> {
-        if let Some(area_upper) = self._area_upper {
-            Some(area_upper)
-        } else if 0 < self.area_enum && self.area_enum < Self::AREAS.len() {
-            Some(Self::AREAS[self.area_enum])
-        } else {
-            None
+    /// (lower, upper) searched for: the explicit bounds if either is given,
+    /// as the page shows the bucket unselected then, else the bucket's.
+    fn bounds(&self, kind: BucketKind) -> (Option<u32>, Option<u32>) {
+        let (value, lower, upper) = self.bucket_fields(kind);
+        if lower.is_some() || upper.is_some() {
+            return (lower, upper);
         }
+        self._buckets
+            .0
+            .and_then(|buckets| buckets.bucket(kind, value))
+            .map_or((None, None), |b| (Some(b.lower), b.upper))
+    }
+
+    pub fn area(&self) -> (Option<u32>, Option<u32>) {
+        self.bounds(BucketKind::Area)
+    }
+
+    pub fn area_lower(&self) -> Option<u32> {
+        self.area().0
+    }
+
+    pub fn area_upper(&self) -> Option<u32> {
+        self.area().1
+    }
+
+    pub fn floor(&self) -> (Option<u32>, Option<u32>) {
+        self.bounds(BucketKind::Floor)
     }
 
     pub fn floor_lower(&self) -> Option<u32> {
-        if 0 < self.floor_enum && self.floor_enum < Self::FLOORS.len() {
-            Some(Self::FLOORS[self.floor_enum - 1])
-        } else {
-            None
-        }
+        self.floor().0
     }
 
     pub fn floor_upper(&self) -> Option<u32> {
-        if 0 < self.floor_enum && self.floor_enum < Self::FLOORS.len() - 1 {
-            Some(Self::FLOORS[self.floor_enum])
-        } else {
-            None
-        }
+        self.floor().1
+    }
+
+    pub fn price(&self) -> (Option<u32>, Option<u32>) {
+        self.bounds(BucketKind::Price)
     }
 
     pub fn price_lower(&self) -> Option<u32> {
-        self._price_lower
+        self.price().0
     }
 
     pub fn price_upper(&self) -> Option<u32> {
-        self._price_upper
+        self.price().1
     }
 
-    fn floor_checked(&self) -> Vec<&str> {
-        let mut fc = vec![""; Self::FLOORS.len()];
-        fc[self.floor_enum] = Self::CHECKED;
-        fc
+    pub fn unit_price_lower(&self) -> Option<u32> {
+        self._unit_price_lower
     }
 
-    fn area_checked(&self) -> Vec<&str> {
-        let mut fc = vec![""; Self::AREAS.len()];
-        if self._area_lower.is_none() && self._area_upper.is_none() {
-            fc[self.area_enum] = Self::CHECKED;
+    pub fn unit_price_upper(&self) -> Option<u32> {
+        self._unit_price_upper
+    }
+
+    pub fn price_dropped(&self) -> bool {
+        self._price_dropped
+    }
+
+    pub fn available_from(&self) -> Option<NaiveDate> {
+        NaiveDate::parse_from_str(self._available_from.trim(), Self::DATE_FORMAT).ok()
+    }
+
+    /// Returns (lat, lng, radius in meters) when all three are given.
+    pub fn near(&self) -> Option<(f64, f64, u32)> {
+        match (self._lat, self._lng, self._radius) {
+            (Some(lat), Some(lng), Some(radius)) if radius > 0 => Some((lat, lng, radius)),
+            _ => None,
         }
-        fc
     }
+
+    /// Returns (min lat, min lng, max lat, max lng) when the whole box is given.
+    pub fn bounding_box(&self) -> Option<(f64, f64, f64, f64)> {
+        match (self._min_lat, self._min_lng, self._max_lat, self._max_lng) {
+            (Some(min_lat), Some(min_lng), Some(max_lat), Some(max_lng)) => {
+                Some((min_lat, min_lng, max_lat, max_lng))
+            }
+            _ => None,
+        }
+    }
+
+    /// Box enclosing the `near` circle, so the index on (hlat, hlng) can be used
+    /// before the exact spherical distance is checked.
+    fn near_bounding_box(&self) -> Option<(f64, f64, f64, f64)> {
+        self.near().map(|(lat, lng, radius)| {
+            let dlat = radius as f64 / Self::METERS_PER_DEGREE;
+            let dlng = dlat / lat.to_radians().cos().abs().max(0.01);
+            (lat - dlat, lng - dlng, lat + dlat, lng + dlng)
+        })
+    }
+
+    /// Explicit bounds override the bucket, which then shows as unselected.
+    fn options(&self, kind: BucketKind) -> Vec<BucketOption> {
+        let (value, lower, upper) = self.bucket_fields(kind);
+        let value = if lower.is_some() || upper.is_some() { 0 } else { value };
+        self._buckets.0.map_or(Vec::new(), |buckets| buckets.options(kind, value))
+    }
+
+    /// Conditions of each bucket of a kind, the first matching everything.
+    pub fn bucket_conditions(&self, kind: BucketKind) -> Vec<Condition> {
+        let count = self._buckets.0.map_or(0, |buckets| buckets.get(kind).len());
+        (0..=count)
+            .map(|value| {
+                let mut filter = HouseFilter { _buckets: self._buckets, ..Default::default() };
+                match kind {
+                    BucketKind::Floor => filter.floor_enum = value,
+                    BucketKind::Area => filter.area_enum = value,
+                    BucketKind::Price => filter.price_enum = value,
+                }
+                filter.into()
+            })
+            .collect()
+    }
+
+    /// The same search with the selected bucket of a kind written out as
+    /// explicit bounds, which keep their meaning when the buckets change.
+    pub fn with_bucket_bounds(&self, kind: BucketKind) -> Self {
+        let mut filter = *self;
+        let (lower, upper) = self.bounds(kind);
+        match kind {
+            BucketKind::Floor => {
+                (filter.floor_enum, filter._floor_lower, filter._floor_upper) = (0, lower, upper)
+            }
+            BucketKind::Area => {
+                (filter.area_enum, filter._area_lower, filter._area_upper) = (0, lower, upper)
+            }
+            BucketKind::Price => {
+                (filter.price_enum, filter._price_lower, filter._price_upper) = (0, lower, upper)
+            }
+        }
+        filter
+    }
+
+    /// The same search on any floor.
+    pub fn without_floor(&self) -> Self {
+        let mut filter = *self;
+        filter.floor_enum = 0;
+        filter._floor_lower = None;
+        filter._floor_upper = None;
+        filter
+    }
+
+    pub fn without_area(&self) -> Self {
+        let mut filter = *self;
+        filter.area_enum = 0;
+        filter._area_lower = None;
+        filter._area_upper = None;
+        filter
+    }
+
+    pub fn without_price(&self) -> Self {
+        let mut filter = *self;
+        filter.price_enum = 0;
+        filter._price_lower = None;
+        filter._price_upper = None;
+        filter
+    }
+}
+
+impl<'r> HouseFilter<'r> {
+    /// Checks the selected buckets against `buckets`, which must be those
+    /// the search page shows, and reads them from there from now on.
+    pub fn with_buckets(mut self, buckets: &'r SearchBuckets) -> Result<Self, MXFError> {
+        for (kind, name) in
+            [(BucketKind::Floor, "floor"), (BucketKind::Area, "area"), (BucketKind::Price, "price")]
+        {
+            if self.bucket_fields(kind).0 > buckets.get(kind).len() {
+                return Err(MXFError::InvalidFilter(format!("unknown {} bucket", name)));
+            }
+        }
+        self._buckets = FilterBuckets(Some(buckets));
+        Ok(self)
+    }
+
+    /// The same search looking for `suite` instead.
+    pub fn with_suite(&self, suite: &'r str) -> Self {
+        let mut filter = *self;
+        filter._suite = suite;
+        filter
+    }
+
+    /// Attaches the facet counts of this search for the template.
+    pub fn with_facets<'f>(self, facets: &'f FacetCounts) -> FacetedFilter<'r, 'f> {
+        FacetedFilter { filter: self, facets }
+    }
+
+    /// Splits a stored query string into percent-decoded (name, value) pairs
+    /// for `from_fields` to borrow from.
+    pub fn decode_query(query: &str) -> Vec<(String, String)> {
+        query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+                (
+                    RawStr::new(name).url_decode_lossy().into_owned(),
+                    RawStr::new(value).url_decode_lossy().into_owned(),
+                )
+            })
+            .collect()
+    }
+
+    /// Parses the fields produced by `decode_query` the same way Rocket
+    /// parses `/zufang?<house_filter..>`, then attaches `buckets`.
+    pub fn from_fields(
+        fields: &'r [(String, String)],
+        buckets: &'r SearchBuckets,
+    ) -> Result<Self, MXFError> {
+        Form::<HouseFilter<'r>>::parse_iter(
+            fields
+                .iter()
+                .map(|(name, value)| ValueField::from((name.as_str(), value.as_str()))),
+        )
+        .map_err(|e| MXFError::InvalidFilter(e.to_string()))?
+        .with_buckets(buckets)
+    }
+
+    /// Canonical query string of every set field except `page`, in a fixed order.
+    pub fn to_query_string(&self) -> String {
+        let text = |v: &str| (!v.trim().is_empty()).then(|| v.trim().to_string());
+        let fields: [(&str, Option<String>); 32] = [
+            ("k", text(self._keywords)),
+            ("q", text(self._district)),
+            ("rg", self._region.map(|v| v.to_string())),
+            ("f", text(self._house_type)),
+            ("c", (self.floor_enum > 0).then(|| self.floor_enum.to_string())),
+            ("bf", self._floor_lower.map(|v| v.to_string())),
+            ("ef", self._floor_upper.map(|v| v.to_string())),
+            ("m", (self.area_enum > 0).then(|| self.area_enum.to_string())),
+            ("bm", self._area_lower.map(|v| v.to_string())),
+            ("em", self._area_upper.map(|v| v.to_string())),
+            ("bp", self._price_lower.map(|v| v.to_string())),
+            ("ep", self._price_upper.map(|v| v.to_string())),
+            ("p", (self.price_enum > 0).then(|| self.price_enum.to_string())),
+            ("bu", self._unit_price_lower.map(|v| v.to_string())),
+            ("eu", self._unit_price_upper.map(|v| v.to_string())),
+            ("s", text(self._suite)),
+            ("bs", self._bedrooms_lower.map(|v| v.to_string())),
+            ("es", self._bedrooms_upper.map(|v| v.to_string())),
+            ("bt", self._living_rooms_lower.map(|v| v.to_string())),
+            ("et", self._living_rooms_upper.map(|v| v.to_string())),
+            ("bw", self._bathrooms_lower.map(|v| v.to_string())),
+            ("ew", self._bathrooms_upper.map(|v| v.to_string())),
+            ("lat", self._lat.map(|v| v.to_string())),
+            ("lng", self._lng.map(|v| v.to_string())),
+            ("r", self._radius.map(|v| v.to_string())),
+            ("minlat", self._min_lat.map(|v| v.to_string())),
+            ("maxlat", self._max_lat.map(|v| v.to_string())),
+            ("minlng", self._min_lng.map(|v| v.to_string())),
+            ("maxlng", self._max_lng.map(|v| v.to_string())),
+            ("d", self._price_dropped.then(|| "true".to_string())),
+            ("af", self.available_from().map(|d| d.format(Self::DATE_FORMAT).to_string())),
+            ("o", self.sort().map(str::to_string)),
+        ];
+        fields
+            .into_iter()
+            .filter_map(|(name, value)| {
+                value.map(|v| format!("{}={}", name, RawStr::new(&v).percent_encode()))
+            })
+            .collect::<Vec<String>>()
+            .join("&")
+    }
+
+    /// The search as a text query `parse_query` reads back, with buckets
+    /// written out as the ranges they stand for. Amenities not on the search
+    /// page are quoted, which `has:` takes as free text.
+    pub fn to_query_text(&self) -> String {
+        let mut terms = Vec::<String>::new();
+        if let Some(district) = self.district() {
+            terms.push(format!("district:{}", quote(district)));
+        }
+        if let Some(region) = self.region() {
+            terms.push(format!("region:{}", region));
+        }
+        if let Some(house_type) = self.house_type() {
+            terms.push(format!("layout:{}", quote(house_type)));
+        }
+        for (key, (lower, upper)) in [
+            ("price", self.price()),
+            ("area", self.area()),
+            ("floor", self.floor()),
+            ("unitprice", (self.unit_price_lower(), self.unit_price_upper())),
+        ] {
+            match (lower, upper) {
+                (Some(l), Some(u)) => terms.push(format!("{}:{}..{}", key, l, u)),
+                (Some(l), None) => terms.push(format!("{}>={}", key, l)),
+                (None, Some(u)) => terms.push(format!("{}<{}", key, u)),
+                (None, None) => (),
+            }
+        }
+        for (key, (lower, upper)) in [
+            ("bedrooms", self.bedrooms()),
+            ("livingrooms", self.living_rooms()),
+            ("bathrooms", self.bathrooms()),
+        ] {
+            match (lower, upper) {
+                (Some(l), Some(u)) if l == u => terms.push(format!("{}:{}", key, l)),
+                (Some(l), Some(u)) => terms.push(format!("{}:{}..={}", key, l, u)),
+                (Some(l), None) => terms.push(format!("{}>={}", key, l)),
+                (None, Some(u)) => terms.push(format!("{}<={}", key, u)),
+                (None, None) => (),
+            }
+        }
+        match self.suite() {
+            Some(suite) if Self::AMENITIES.contains(&suite) => {
+                terms.push(format!("has:{}", quote(amenity_alias(suite))))
+            }
+            Some(suite) => terms.push(format!("has:{}", quoted(suite))),
+            None => (),
+        }
+        if let Some(date) = self.available_from() {
+            terms.push(format!("available:{}", date.format(Self::DATE_FORMAT)));
+        }
+        if self.price_dropped() {
+            terms.push("is:dropped".to_string());
+        }
+        if let Some(sort) = self.sort() {
+            terms.push(format!("sort:{}", sort));
+        }
+        if let Some((lat, lng, radius)) = self.near() {
+            terms.push(format!("near:{},{},{}", lat, lng, radius));
+        }
+        if let Some((min_lat, min_lng, max_lat, max_lng)) = self.bounding_box() {
+            terms.push(format!("box:{},{},{},{}", min_lat, min_lng, max_lat, max_lng));
+        }
+        if let Some(keywords) = self.keywords() {
+            terms.extend(keywords.split_whitespace().map(quote));
+        }
+        terms.join(" ")
+    }
+}
+
+/// `lower <= column <= upper`, for whichever ends are given.
+fn room_range(
+    column: HouseListingColumn,
+    (lower, upper): (Option<u32>, Option<u32>),
+) -> Condition {
+    Condition::all()
+        .add_option(lower.map(|l| column.gte(l)))
+        .add_option(upper.map(|u| column.lte(u)))
 }
 
 impl From<HouseFilter<'_>> for Condition {
     fn from(value: HouseFilter) -> Self {
         Condition::all()
+            .add_option(value.keywords().map(|k| {
+                Expr::cust_with_values(
+                    "`house_listings`.`hno` IN (SELECT `hno` FROM `house_search_index` \
+                     WHERE MATCH(`content`) AGAINST (?))",
+                    [segment(k)],
+                )
+            }))
             .add_option(
                 value
                     .floor_lower()
@@ -162,19 +625,95 @@ impl From<HouseFilter<'_>> for Condition {
             )
             .add_option(
                 value
-                    .district()
-                    .map(|d| HouseListingColumn::Hdistrict.contains(d)),
+                    .unit_price_lower()
+                    .map(|ul| HouseListingColumn::HunitPrice.gte(ul)),
             )
             .add_option(
                 value
-                    .house_type()
-                    .map(|ht| HouseListingColumn::Hlo.contains(ht)),
+                    .unit_price_upper()
+                    .map(|uu| HouseListingColumn::HunitPrice.lt(uu)),
             )
+            .add_option(
+                value
+                    .district()
+                    .map(|d| HouseListingColumn::Hdistrict.contains(d)),
+            )
+            // Regions are at most three levels deep
+            .add_option(value.region().map(|rno| {
+                Expr::cust_with_values(
+                    "`house_listings`.`hregion` IN (SELECT `rno` FROM `regions` \
+                     WHERE `rno` = ? OR `rparent` = ? \
+                     OR `rparent` IN (SELECT `rno` FROM `regions` WHERE `rparent` = ?))",
+                    [rno, rno, rno],
+                )
+            }))
+            // Layouts like "2室" match the room counts exactly, so that they do
+            // not also match "12室"; other text is looked for in `hlo`.
+            .add_option(value.house_type().map(|ht| match Layout::parse(ht) {
+                Some(layout) => room_range(
+                    HouseListingColumn::Hbedrooms,
+                    (layout.bedrooms, layout.bedrooms),
+                )
+                .add(room_range(
+                    HouseListingColumn::HlivingRooms,
+                    (layout.living_rooms, layout.living_rooms),
+                ))
+                .add(room_range(
+                    HouseListingColumn::Hbathrooms,
+                    (layout.bathrooms, layout.bathrooms),
+                )),
+                None => Condition::all().add(HouseListingColumn::Hlo.contains(ht)),
+            }))
+            .add(room_range(HouseListingColumn::Hbedrooms, value.bedrooms()))
+            .add(room_range(HouseListingColumn::HlivingRooms, value.living_rooms()))
+            .add(room_range(HouseListingColumn::Hbathrooms, value.bathrooms()))
             .add_option(
                 value
                     .suite()
                     .map(|s| HouseListingColumn::Hsuite.contains(s)),
             )
+            .add_option(value.bounding_box().map(|(min_lat, min_lng, max_lat, max_lng)| {
+                Condition::all()
+                    .add(HouseListingColumn::Hlat.between(min_lat, max_lat))
+                    .add(HouseListingColumn::Hlng.between(min_lng, max_lng))
+            }))
+            .add_option(value.near_bounding_box().map(|(min_lat, min_lng, max_lat, max_lng)| {
+                Condition::all()
+                    .add(HouseListingColumn::Hlat.between(min_lat, max_lat))
+                    .add(HouseListingColumn::Hlng.between(min_lng, max_lng))
+            }))
+            .add_option(value.near().map(|(lat, lng, radius)| {
+                Expr::cust_with_values(
+                    "ST_Distance_Sphere(POINT(`hlng`, `hlat`), POINT(?, ?)) <= ?",
+                    [lng, lat, radius as f64],
+                )
+            }))
+            .add_option(value.price_dropped().then(|| {
+                Expr::cust_with_values(
+                    "`house_listings`.`hprice` < (SELECT MAX(`pprice`) \
+                     FROM `house_price_history` AS `ph` \
+                     WHERE `ph`.`hno` = `house_listings`.`hno` \
+                     AND (`ph`.`pdate` >= NOW() - INTERVAL ? DAY \
+                     OR `ph`.`pno` = (SELECT `pno` FROM `house_price_history` AS `pb` \
+                     WHERE `pb`.`hno` = `house_listings`.`hno` \
+                     AND `pb`.`pdate` < NOW() - INTERVAL ? DAY \
+                     ORDER BY `pb`.`pdate` DESC, `pb`.`pno` DESC LIMIT 1)))",
+                    [HouseFilter::PRICE_DROP_DAYS, HouseFilter::PRICE_DROP_DAYS],
+                )
+            }))
+            // Latest order of each chain: requests (1) and leases, cancelled
+            // or not yet (2, 3), hold the house from ostart until oend.
+            // Dates in the past are taken as today.
+            .add_option(value.available_from().map(|date| {
+                let date = date.format(HouseFilter::DATE_FORMAT).to_string();
+                Expr::cust_with_values(
+                    "`house_listings`.`hno` NOT IN (SELECT `hno` FROM `orders` \
+                     GROUP BY `hno`, `ostatus` HAVING MAX(`otype`) IN (1, 2, 3) \
+                     AND MIN(`ostart`) < GREATEST(CAST(? AS DATE), CURDATE()) + INTERVAL 1 DAY \
+                     AND MAX(`oend`) > GREATEST(CAST(? AS DATE), CURDATE()))",
+                    [date.clone(), date],
+                )
+            }))
     }
 }
 
@@ -183,27 +722,64 @@ impl Serialize for HouseFilter<'_> {
     where
         S: serde::Serializer,
     {
-        let mut s = serializer.serialize_struct("HouseFilter", 10)?;
+        let mut s = serializer.serialize_struct("HouseFilter", 33)?;
+        s.serialize_field("k", &self.keywords())?;
         s.serialize_field("q", &self.district())?;
+        s.serialize_field("rg", &self._region)?;
         s.serialize_field("f", &self.house_type())?;
-        s.serialize_field("c", &self.floor_checked())?;
-        s.serialize_field("m", &self.area_checked())?;
+        s.serialize_field("c", &self.options(BucketKind::Floor))?;
+        s.serialize_field("bf", &self._floor_lower)?;
+        s.serialize_field("ef", &self._floor_upper)?;
+        s.serialize_field("m", &self.options(BucketKind::Area))?;
         s.serialize_field("bm", &self._area_lower)?;
         s.serialize_field("em", &self._area_upper)?;
         s.serialize_field("bp", &self._price_lower)?;
         s.serialize_field("ep", &self._price_upper)?;
+        s.serialize_field("p", &self.options(BucketKind::Price))?;
+        s.serialize_field("bu", &self._unit_price_lower)?;
+        s.serialize_field("eu", &self._unit_price_upper)?;
         s.serialize_field("s", &self.suite())?;
+        s.serialize_field("bs", &self._bedrooms_lower)?;
+        s.serialize_field("es", &self._bedrooms_upper)?;
+        s.serialize_field("bt", &self._living_rooms_lower)?;
+        s.serialize_field("et", &self._living_rooms_upper)?;
+        s.serialize_field("bw", &self._bathrooms_lower)?;
+        s.serialize_field("ew", &self._bathrooms_upper)?;
+        s.serialize_field("lat", &self._lat)?;
+        s.serialize_field("lng", &self._lng)?;
+        s.serialize_field("r", &self._radius)?;
+        s.serialize_field("minlat", &self._min_lat)?;
+        s.serialize_field("maxlat", &self._max_lat)?;
+        s.serialize_field("minlng", &self._min_lng)?;
+        s.serialize_field("maxlng", &self._max_lng)?;
+        s.serialize_field("d", &self._price_dropped)?;
+        s.serialize_field("af", &self.available_from().map(|d| d.to_string()))?;
+        s.serialize_field("o", &self._sort)?;
         s.serialize_field("page", &self.page)?;
         s.end()
     }
 }
 
+/// A search with its facet counts, serialized as the search plus `facets`.
+#[derive(Serialize)]
+pub struct FacetedFilter<'r, 'f> {
+    #[serde(flatten)]
+    filter: HouseFilter<'r>,
+    facets: &'f FacetCounts,
+}
+
 impl std::fmt::Display for HouseFilter<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut repr = Vec::<String>::new();
+        if let Some(keywords) = self.keywords() {
+            repr.push(format!("keywords: {}", keywords));
+        }
         if let Some(district) = self.district() {
             repr.push(format!("district: {}", district));
         }
+        if let Some(region) = self.region() {
+            repr.push(format!("region: {}", region));
+        }
         if let Some(house_type) = self.house_type() {
             repr.push(format!("house_type: {}", house_type));
         }
@@ -213,17 +789,153 @@ impl std::fmt::Display for HouseFilter<'_> {
         if let Some(area_upper) = self.area_upper() {
             repr.push(format!("area_upper: {}", area_upper));
         }
+        if let Some(price_lower) = self.price_lower() {
+            repr.push(format!("price_lower: {}", price_lower));
+        }
+        if let Some(price_upper) = self.price_upper() {
+            repr.push(format!("price_upper: {}", price_upper));
+        }
         if let Some(floor_lower) = self.floor_lower() {
             repr.push(format!("floor_lower: {}", floor_lower));
         }
         if let Some(floor_upper) = self.floor_upper() {
             repr.push(format!("floor_upper: {}", floor_upper));
         }
+        if let Some(unit_price_lower) = self.unit_price_lower() {
+            repr.push(format!("unit_price_lower: {}", unit_price_lower));
+        }
+        if let Some(unit_price_upper) = self.unit_price_upper() {
+            repr.push(format!("unit_price_upper: {}", unit_price_upper));
+        }
         if let Some(suite) = self.suite() {
             repr.push(format!("suite: {}", suite));
         }
+        for (name, (lower, upper)) in [
+            ("bedrooms", self.bedrooms()),
+            ("living_rooms", self.living_rooms()),
+            ("bathrooms", self.bathrooms()),
+        ] {
+            if lower.is_some() || upper.is_some() {
+                let end = |v: Option<u32>| v.map(|v| v.to_string()).unwrap_or_default();
+                repr.push(format!("{}: {}..={}", name, end(lower), end(upper)));
+            }
+        }
+        if let Some((lat, lng, radius)) = self.near() {
+            repr.push(format!("near: ({}, {}) within {}m", lat, lng, radius));
+        }
+        if let Some((min_lat, min_lng, max_lat, max_lng)) = self.bounding_box() {
+            repr.push(format!(
+                "bounding_box: ({}, {})-({}, {})",
+                min_lat, min_lng, max_lat, max_lng
+            ));
+        }
+        if self.price_dropped() {
+            repr.push("price_dropped".to_string());
+        }
+        if let Some(date) = self.available_from() {
+            repr.push(format!("available_from: {}", date));
+        }
+        if let Some(sort) = self.sort() {
+            repr.push(format!("sort: {}", sort));
+        }
         // repr.push(format!("page: {}", self.page));
         write!(f, "HouseFilter({})", repr.join(", "))?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_query;
+
+    fn fields(query: &str) -> Vec<(String, String)> {
+        HouseFilter::decode_query(query)
+    }
+
+    /// Reads `text` back from what it parses to.
+    fn reprint(text: &str) -> String {
+        let fields = parse_query(text).unwrap_or_else(|e| panic!("{}: {}", text, e));
+        HouseFilter::from_fields(&fields, &SearchBuckets::default()).unwrap().to_query_text()
+    }
+
+    #[test]
+    fn text_round_trip() {
+        for text in [
+            "district:海淀 price:3000..5000 area>=60 floor<10",
+            "region:12 layout:2室1厅 unitprice:50..80",
+            "bedrooms:2 livingrooms:1..=2 bathrooms<=1",
+            "has:elevator",
+            "has:\"阳台\"",
+            "has:\"南北 通透\" available:2024-09-01 is:dropped sort:unit_price_desc",
+            "near:39.9,116.4,1500 box:39.8,116.3,40,116.5",
+            "近地铁 \"a:b\" 精装",
+            "",
+        ] {
+            assert_eq!(reprint(text), text);
+        }
+    }
+
+    #[test]
+    fn query_string_round_trip() {
+        let buckets = SearchBuckets::default();
+        for query in [
+            "c=2&m=3&p=6",
+            "c=5&ef=10",
+            "m=2&bm=60",
+            "p=3&bp=4500&ep=5000",
+            "s=%E9%98%B3%E5%8F%B0",
+            "s=%E7%94%B5%E6%A2%AF&k=%E8%BF%91%E5%9C%B0%E9%93%81",
+            "bs=3&es=3&bw=1&d=true&af=2024-09-01&o=unit_price_asc",
+        ] {
+            let fields = fields(query);
+            let filter = HouseFilter::from_fields(&fields, &buckets).unwrap();
+            let text = filter.to_query_text();
+            let parsed = parse_query(&text).unwrap_or_else(|e| panic!("{}: {}", text, e));
+            let again = HouseFilter::from_fields(&parsed, &buckets).unwrap();
+            assert_eq!(again.to_query_text(), text, "{}", query);
+            assert_eq!(again.floor(), filter.floor(), "{}", query);
+            assert_eq!(again.area(), filter.area(), "{}", query);
+            assert_eq!(again.price(), filter.price(), "{}", query);
+            assert_eq!(again.suite(), filter.suite(), "{}", query);
+            assert_eq!(again.keywords(), filter.keywords(), "{}", query);
+        }
+    }
+
+    #[test]
+    fn explicit_bounds_override_bucket() {
+        let buckets = SearchBuckets::default();
+        let fields = fields("c=5&ef=10&m=2");
+        let filter = HouseFilter::from_fields(&fields, &buckets).unwrap();
+        assert_eq!(filter.floor(), (None, Some(10)));
+        let area = &buckets.areas[1];
+        assert_eq!(filter.area(), (Some(area.lower), area.upper));
+        assert_eq!(
+            filter.to_query_text(),
+            format!("area:{}..{} floor<10", area.lower, area.upper.unwrap())
+        );
+    }
+
+    #[test]
+    fn buckets_are_checked_and_written_out() {
+        let buckets = SearchBuckets::default();
+        let unknown = fields("c=6");
+        assert!(HouseFilter::from_fields(&unknown, &buckets).is_err());
+
+        let selected = fields("c=2&p=3&bp=4500");
+        let filter = HouseFilter::from_fields(&selected, &buckets).unwrap();
+        let pinned = filter
+            .with_bucket_bounds(BucketKind::Floor)
+            .with_bucket_bounds(BucketKind::Price);
+        assert_eq!(pinned.to_query_string(), "bf=1&ef=10&bp=4500");
+        assert_eq!(pinned.floor(), filter.floor());
+        assert_eq!(pinned.price(), filter.price());
+
+        // Other buckets no longer change what it means
+        let fewer = SearchBuckets { floors: buckets.floors[..1].to_vec(), ..buckets.clone() };
+        let query = pinned.to_query_string();
+        let stored = HouseFilter::decode_query(&query);
+        let reread = HouseFilter::from_fields(&stored, &fewer).unwrap();
+        assert_eq!(reread.floor(), (Some(1), Some(10)));
+    }
+}
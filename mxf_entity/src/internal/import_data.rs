@@ -0,0 +1,68 @@
+use rocket::form::{FromForm, FromFormField};
+use serde::{Deserialize, Serialize};
+
+#[derive(FromFormField, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum ImportFormat {
+    Csv,
+    Xlsx,
+}
+
+/// Query parameters of an import upload.
+#[derive(FromForm, Clone, Copy, Debug, PartialEq)]
+pub struct ImportOptions {
+    pub format: ImportFormat,
+    /// Reports what would be done without writing anything.
+    pub dry_run: bool,
+    /// Updates the houses already imported under the same external id.
+    pub upsert: bool,
+}
+
+/// One data row of an import file, with its columns mapped to the listing.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ImportRow {
+    pub extref: Option<String>,
+    pub hdistrict: String,
+    pub haddr: String,
+    pub hlo: String,
+    pub hflr: u32,
+    pub harea: u32,
+    pub hprice: u32,
+    pub hsuite: String,
+    pub hdesc: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum ImportAction {
+    Create,
+    Update,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ImportRowReport {
+    /// Line in the file, counting the header as line 1.
+    pub row: usize,
+    pub extref: Option<String>,
+    /// What was done, or would be done in a dry run. `None` on errors.
+    pub action: Option<ImportAction>,
+    pub hno: Option<u32>,
+    pub errors: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum ImportStatus {
+    Running,
+    Finished,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ImportJob {
+    pub id: u32,
+    pub uno: u32,
+    pub dry_run: bool,
+    pub upsert: bool,
+    pub status: ImportStatus,
+    pub total: usize,
+    pub processed: usize,
+    pub failed: usize,
+    pub rows: Vec<ImportRowReport>,
+}
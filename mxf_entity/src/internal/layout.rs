@@ -0,0 +1,127 @@
+use serde::Serialize;
+
+/// Room counts of a layout such as "3室2厅1卫". Parts missing from the text
+/// are `None` rather than zero.
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Layout {
+    pub bedrooms: Option<u32>,
+    pub living_rooms: Option<u32>,
+    pub bathrooms: Option<u32>,
+}
+
+impl Layout {
+    /// Value of a number written in digits or in Chinese ("两", "十二").
+    fn number(token: &str) -> Option<u32> {
+        if let Ok(n) = token.parse() {
+            return Some(n);
+        }
+        let digit = |c: char| match c {
+            '两' => Some(2),
+            _ => "零一二三四五六七八九".chars().position(|d| d == c).map(|d| d as u32),
+        };
+        match token.split_once('十') {
+            None => {
+                let mut chars = token.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => digit(c),
+                    _ => None,
+                }
+            }
+            Some((tens, ones)) => {
+                let tens = if tens.is_empty() { Some(1) } else { Self::number(tens) }?;
+                let ones = if ones.is_empty() { Some(0) } else { Self::number(ones) }?;
+                (tens < 10 && ones < 10).then_some(tens * 10 + ones)
+            }
+        }
+    }
+
+    fn is_numeral(c: char) -> bool {
+        c.is_ascii_digit() || "零一二两三四五六七八九十".contains(c)
+    }
+
+    /// Reads the number written right before each of 室 (or 房, 居), 厅 and
+    /// 卫, so "12室" has twelve bedrooms and not two. Returns `None` when no
+    /// room count is found at all.
+    pub fn parse(text: &str) -> Option<Self> {
+        Self::split(text).map(|(layout, _)| layout)
+    }
+
+    /// Like [`Layout::parse`], also returning the text that follows the last
+    /// room count, e.g. " 南北通透" for "3室2厅 南北通透".
+    pub fn split(text: &str) -> Option<(Self, &str)> {
+        let mut layout = Self::default();
+        let mut token = String::new();
+        let mut rest = text;
+        for (i, c) in text.char_indices() {
+            if Self::is_numeral(c) {
+                token.push(c);
+                continue;
+            }
+            let part = match c {
+                '室' | '房' | '居' => Some(&mut layout.bedrooms),
+                '厅' => Some(&mut layout.living_rooms),
+                '卫' => Some(&mut layout.bathrooms),
+                _ => None,
+            };
+            if let Some(part) = part {
+                if part.is_none() {
+                    *part = Self::number(&token);
+                    if part.is_some() {
+                        rest = &text[i + c.len_utf8()..];
+                    }
+                }
+            }
+            token.clear();
+        }
+        (layout != Self::default()).then_some((layout, rest))
+    }
+}
+
+impl std::fmt::Display for Layout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (count, unit) in [
+            (self.bedrooms, "室"),
+            (self.living_rooms, "厅"),
+            (self.bathrooms, "卫"),
+        ] {
+            if let Some(count) = count {
+                write!(f, "{}{}", count, unit)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(bedrooms: u32, living_rooms: Option<u32>, bathrooms: Option<u32>) -> Layout {
+        Layout { bedrooms: Some(bedrooms), living_rooms, bathrooms }
+    }
+
+    #[test]
+    fn parse_reads_digits_and_chinese_numerals() {
+        assert_eq!(Layout::parse("3室2厅1卫"), Some(layout(3, Some(2), Some(1))));
+        assert_eq!(Layout::parse("两室一厅"), Some(layout(2, Some(1), None)));
+        assert_eq!(Layout::parse("2房1厅"), Some(layout(2, Some(1), None)));
+        assert_eq!(Layout::parse("十二室"), Some(layout(12, None, None)));
+        assert_eq!(Layout::parse("二十一室"), Some(layout(21, None, None)));
+        assert_eq!(Layout::parse("12室"), Some(layout(12, None, None)));
+        assert_eq!(Layout::parse("一居室"), Some(layout(1, None, None)));
+    }
+
+    #[test]
+    fn parse_without_room_counts() {
+        for text in ["", "开间", "室厅卫", "一百室"] {
+            assert_eq!(Layout::parse(text), None, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn split_keeps_trailing_text() {
+        let (parsed, rest) = Layout::split("两室一厅 南北通透").unwrap();
+        assert_eq!((parsed.to_string().as_str(), rest), ("2室1厅", " 南北通透"));
+        assert_eq!(Layout::split("3室2厅1卫").unwrap().1, "");
+    }
+}
@@ -0,0 +1,279 @@
+use serde::Deserialize;
+
+use crate::errors::FieldError;
+use crate::{HouseListingModel, ListStatus, MXFError};
+
+/// The district without its trailing "区", so that "朝阳" and "朝阳区"
+/// compare equal.
+pub fn district_base(district: &str) -> &str {
+    district.trim().trim_end_matches('区')
+}
+
+/// Both spellings of `district`, without and with "区", for queries.
+pub fn district_spellings(district: &str) -> [String; 2] {
+    let base = district_base(district);
+    [base.to_string(), format!("{}区", base)]
+}
+
+/// Listing form as posted to `/new` and `/modify`. Numbers are taken as
+/// given, so that a missing or out of range value is reported on its field
+/// instead of failing the whole request as malformed JSON.
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct ListingInput {
+    pub hno: u32,
+    pub hdistrict: String,
+    pub haddr: String,
+    pub hlo: String,
+    pub hflr: Option<f64>,
+    pub harea: Option<f64>,
+    pub hprice: Option<f64>,
+    pub hsuite: String,
+    pub hdesc: String,
+    /// Status asked for by the landlord: `Listed`, `Unlisted` or `Draft`.
+    /// Whether it is granted is up to `HouseService`.
+    pub hunlisted: String,
+}
+
+impl ListingInput {
+    pub const FLOORS: (u32, u32) = (1, 100);
+    /// Square meters.
+    pub const AREAS: (u32, u32) = (5, 2000);
+    /// Yuan per month.
+    pub const PRICES: (u32, u32) = (100, 500_000);
+    pub const MAX_ADDR_LEN: usize = 255;
+    pub const MAX_LAYOUT_LEN: usize = 32;
+    pub const MAX_SUITE_LEN: usize = 255;
+    pub const MAX_DESC_LEN: usize = 5000;
+
+    fn text(
+        errors: &mut Vec<FieldError>,
+        field: &str,
+        label: &str,
+        value: &str,
+        required: bool,
+        max_len: usize,
+    ) -> String {
+        let value = value.trim();
+        if required && value.is_empty() {
+            errors.push(FieldError::new(field, format!("{}不能为空", label)));
+        } else if value.chars().count() > max_len {
+            errors.push(FieldError::new(field, format!("{}不能超过 {} 个字符", label, max_len)));
+        }
+        value.to_string()
+    }
+
+    fn number(
+        errors: &mut Vec<FieldError>,
+        field: &str,
+        label: &str,
+        value: Option<f64>,
+        (min, max): (u32, u32),
+    ) -> u32 {
+        match value {
+            None => errors.push(FieldError::new(field, format!("{}不能为空", label))),
+            Some(v) if v.fract() != 0.0 => {
+                errors.push(FieldError::new(field, format!("{}必须是整数", label)))
+            }
+            Some(v) if v < min as f64 || v > max as f64 => errors.push(FieldError::new(
+                field,
+                format!("{}应在 {} 到 {} 之间", label, min, max),
+            )),
+            Some(v) => return v as u32,
+        }
+        0
+    }
+
+    /// The district as spelled in `districts`, accepting it without the
+    /// trailing "区".
+    fn district<'a>(value: &str, districts: &[&'a str]) -> Option<&'a str> {
+        let value = district_base(value);
+        districts.iter().copied().find(|d| district_base(d) == value)
+    }
+
+    fn district_field(errors: &mut Vec<FieldError>, value: &str, districts: &[&str]) -> String {
+        match Self::district(value, districts) {
+            Some(d) => d.to_string(),
+            None if value.trim().is_empty() => {
+                errors.push(FieldError::new("hdistrict", "区域不能为空".to_string()));
+                String::new()
+            }
+            None => {
+                errors.push(FieldError::new("hdistrict", format!("未知区域：{}", value.trim())));
+                String::new()
+            }
+        }
+    }
+
+    /// A draft house with the fields both forms check, and its room counts.
+    fn house(
+        hno: u32,
+        hdistrict: String,
+        hlo: String,
+        hflr: u32,
+        harea: u32,
+        hsuite: String,
+    ) -> HouseListingModel {
+        HouseListingModel {
+            hno,
+            hdistrict,
+            haddr: String::new(),
+            hlo,
+            hflr,
+            harea,
+            hprice: 0,
+            hlandlore: 0,
+            hsuite,
+            hunlisted: ListStatus::Draft,
+            hlat: None,
+            hlng: None,
+            hdesc: String::new(),
+            hreason: None,
+            hextref: None,
+            hlisted_at: None,
+            hrefreshed_at: None,
+            hbedrooms: None,
+            hliving_rooms: None,
+            hbathrooms: None,
+            hregion: None,
+            hunit_price: None,
+        }
+        .with_layout()
+    }
+
+    /// Checks every field against the rules above and `districts`, the known
+    /// district names, reporting all problems at once.
+    pub fn validate(self, districts: &[&str]) -> Result<HouseListingModel, MXFError> {
+        let mut errors = Vec::new();
+        let hdistrict = Self::district_field(&mut errors, &self.hdistrict, districts);
+        let haddr = Self::text(&mut errors, "haddr", "地址", &self.haddr, true, Self::MAX_ADDR_LEN);
+        let hlo = Self::text(&mut errors, "hlo", "房型", &self.hlo, true, Self::MAX_LAYOUT_LEN);
+        let hflr = Self::number(&mut errors, "hflr", "楼层", self.hflr, Self::FLOORS);
+        let harea = Self::number(&mut errors, "harea", "面积", self.harea, Self::AREAS);
+        let hprice = Self::number(&mut errors, "hprice", "价格", self.hprice, Self::PRICES);
+        let hsuite =
+            Self::text(&mut errors, "hsuite", "主要设施", &self.hsuite, false, Self::MAX_SUITE_LEN);
+        let hdesc =
+            Self::text(&mut errors, "hdesc", "房源描述", &self.hdesc, false, Self::MAX_DESC_LEN);
+        let hunlisted = match self.hunlisted.as_str() {
+            "" | "Listed" => ListStatus::Listed,
+            "Unlisted" => ListStatus::Unlisted,
+            "Draft" => ListStatus::Draft,
+            other => {
+                errors.push(FieldError::new("hunlisted", format!("不能设为此状态：{}", other)));
+                ListStatus::Listed
+            }
+        };
+        if !errors.is_empty() {
+            return Err(MXFError::InvalidListing(errors));
+        }
+        Ok(HouseListingModel {
+            haddr,
+            hprice,
+            hunlisted,
+            hdesc,
+            ..Self::house(self.hno, hdistrict, hlo, hflr, harea, hsuite)
+        })
+    }
+
+    /// Checks only what a rent estimate needs: district, floor and area, and
+    /// layout and amenities if given. The price is left at 0.
+    pub fn validate_for_estimate(self, districts: &[&str]) -> Result<HouseListingModel, MXFError> {
+        let mut errors = Vec::new();
+        let hdistrict = Self::district_field(&mut errors, &self.hdistrict, districts);
+        let hlo = Self::text(&mut errors, "hlo", "房型", &self.hlo, false, Self::MAX_LAYOUT_LEN);
+        let hflr = Self::number(&mut errors, "hflr", "楼层", self.hflr, Self::FLOORS);
+        let harea = Self::number(&mut errors, "harea", "面积", self.harea, Self::AREAS);
+        let hsuite =
+            Self::text(&mut errors, "hsuite", "主要设施", &self.hsuite, false, Self::MAX_SUITE_LEN);
+        if !errors.is_empty() {
+            return Err(MXFError::InvalidListing(errors));
+        }
+        Ok(Self::house(self.hno, hdistrict, hlo, hflr, harea, hsuite))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DISTRICTS: [&str; 2] = ["海淀区", "朝阳区"];
+
+    fn input() -> ListingInput {
+        ListingInput {
+            hdistrict: "海淀区".to_string(),
+            haddr: "中关村大街 1 号".to_string(),
+            hlo: "2室1厅".to_string(),
+            hflr: Some(5.0),
+            harea: Some(50.0),
+            hprice: Some(5000.0),
+            ..Default::default()
+        }
+    }
+
+    fn fields(result: Result<HouseListingModel, MXFError>) -> Vec<String> {
+        match result {
+            Err(MXFError::InvalidListing(errors)) => errors.into_iter().map(|e| e.field).collect(),
+            other => panic!("expected field errors, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn validate_checks_bounds() {
+        let house = input().validate(&DISTRICTS).unwrap();
+        assert_eq!((house.hflr, house.harea, house.hprice), (5, 50, 5000));
+        assert_eq!(house.hunlisted, ListStatus::Listed);
+        assert_eq!(house.hbedrooms, Some(2));
+        for (flr, area, price) in [(1.0, 5.0, 100.0), (100.0, 2000.0, 500_000.0)] {
+            let house = ListingInput {
+                hflr: Some(flr),
+                harea: Some(area),
+                hprice: Some(price),
+                ..input()
+            };
+            assert!(house.validate(&DISTRICTS).is_ok(), "{} {} {}", flr, area, price);
+        }
+        for (flr, area, price) in [(0.0, 5.0, 100.0), (1.0, 2001.0, 100.0), (1.0, 5.0, 99.0)] {
+            let house = ListingInput {
+                hflr: Some(flr),
+                harea: Some(area),
+                hprice: Some(price),
+                ..input()
+            };
+            assert_eq!(fields(house.validate(&DISTRICTS)).len(), 1, "{} {} {}", flr, area, price);
+        }
+    }
+
+    #[test]
+    fn validate_rejects_fractional_numbers() {
+        let house = ListingInput { harea: Some(50.5), ..input() };
+        assert_eq!(fields(house.validate(&DISTRICTS)), ["harea"]);
+    }
+
+    #[test]
+    fn validate_spells_district_as_listed() {
+        for typed in ["海淀", " 海淀区 "] {
+            let house = ListingInput { hdistrict: typed.to_string(), ..input() };
+            assert_eq!(house.validate(&DISTRICTS).unwrap().hdistrict, "海淀区");
+        }
+        let house = ListingInput { hdistrict: "通州".to_string(), ..input() };
+        assert_eq!(fields(house.validate(&DISTRICTS)), ["hdistrict"]);
+    }
+
+    #[test]
+    fn validate_reports_every_field() {
+        let house = ListingInput {
+            hdistrict: String::new(),
+            haddr: " ".to_string(),
+            hflr: None,
+            hprice: Some(1e9),
+            hdesc: "房".repeat(ListingInput::MAX_DESC_LEN + 1),
+            hunlisted: "Deleted".to_string(),
+            ..input()
+        };
+        assert_eq!(
+            fields(house.validate(&DISTRICTS)),
+            ["hdistrict", "haddr", "hflr", "hprice", "hdesc", "hunlisted"]
+        );
+    }
+}
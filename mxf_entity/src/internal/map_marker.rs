@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+/// A cluster of houses shown as one marker on the map view.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MapMarker {
+    pub lat: f64,
+    pub lng: f64,
+    pub count: u32,
+    /// Set only when the marker stands for a single house.
+    pub hno: Option<u32>,
+}
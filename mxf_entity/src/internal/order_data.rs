@@ -4,3 +4,10 @@ use serde::{Deserialize, Serialize};
 pub struct HnoData {
     pub hno: u32,
 }
+
+#[derive(Serialize, Deserialize)]
+pub struct ReviewData {
+    pub hno: u32,
+    pub approve: bool,
+    pub reason: Option<String>,
+}
@@ -0,0 +1,95 @@
+use sea_orm::sea_query::{Expr, Order, SimpleExpr};
+use sea_orm::{ColumnTrait, Condition};
+use serde::Serialize;
+
+use crate::MXFError;
+
+/// Where a keyset-paginated list continues: the sort value of the last item
+/// shown, if the list is sorted by one, and its id.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cursor {
+    pub key: Option<f64>,
+    pub id: u32,
+}
+
+impl Cursor {
+    /// Reads `id` or `key_id`, as written by `to_string`. Keys must be finite,
+    /// as no row sorts after NaN.
+    pub fn parse(cursor: &str) -> Result<Self, MXFError> {
+        let invalid = || MXFError::InvalidCursor(cursor.to_string());
+        let (key, id) = match cursor.rsplit_once('_') {
+            Some((key, id)) => {
+                let key = key.parse::<f64>().ok().filter(|k| k.is_finite()).ok_or_else(invalid)?;
+                (Some(key), id)
+            }
+            None => (None, cursor),
+        };
+        Ok(Cursor { key, id: id.parse().map_err(|_| invalid())? })
+    }
+
+    /// Rows after this cursor in `sort` order, ties broken by ascending `id`.
+    /// MySQL puts NULL keys first when ascending and last when descending.
+    pub fn after<C: ColumnTrait>(&self, sort: Option<(SimpleExpr, Order)>, id: C) -> Condition {
+        let after_id = id.gt(self.id);
+        let Some((key, order)) = sort else {
+            return Condition::all().add(after_id);
+        };
+        let key = || Expr::expr(key.clone());
+        let descending = matches!(order, Order::Desc);
+        match self.key {
+            Some(k) => {
+                let beyond = if descending { key().lt(k) } else { key().gt(k) };
+                Condition::any()
+                    .add(beyond)
+                    .add(Condition::all().add(key().eq(k)).add(after_id))
+                    .add_option(descending.then(|| key().is_null()))
+            }
+            None => Condition::any()
+                .add(Condition::all().add(key().is_null()).add(after_id))
+                .add_option((!descending).then(|| key().is_not_null())),
+        }
+    }
+}
+
+impl std::fmt::Display for Cursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.key {
+            Some(key) => write!(f, "{}_{}", key, self.id),
+            None => write!(f, "{}", self.id),
+        }
+    }
+}
+
+/// One page of a keyset-paginated list.
+#[derive(Serialize)]
+pub struct CursorPage<T> {
+    pub items: Vec<T>,
+    /// Cursor of the next page, `None` on the last one.
+    pub next_cursor: Option<String>,
+    /// Cursor to page back from, for lists that can be paged back, `None` on
+    /// the first page.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prev_cursor: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_round_trips() {
+        for cursor in [Cursor { key: None, id: 7 }, Cursor { key: Some(42.5), id: 7 }] {
+            assert_eq!(Cursor::parse(&cursor.to_string()).unwrap(), cursor);
+        }
+        assert_eq!(Cursor::parse("-3_7").unwrap(), Cursor { key: Some(-3.0), id: 7 });
+    }
+
+    #[test]
+    fn parse_rejects_malformed_cursors() {
+        let malformed = ["", "x", "1.5", "1_x", "_7"];
+        let non_finite = ["NaN_7", "nan_7", "inf_7", "-inf_7", "1e999_7"];
+        for cursor in malformed.into_iter().chain(non_finite) {
+            assert!(Cursor::parse(cursor).is_err(), "{:?} accepted", cursor);
+        }
+    }
+}
@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+/// Asking prices of one district in one month, from the price history.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DistrictPriceTrend {
+    pub district: String,
+    /// `YYYY-MM`.
+    pub month: String,
+    pub average_price: u32,
+    /// Prices recorded, including those of new houses.
+    pub records: u32,
+    /// Records lower than the previous price of the same house.
+    pub drops: u32,
+}
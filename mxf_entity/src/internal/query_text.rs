@@ -0,0 +1,589 @@
+use chrono::NaiveDate;
+
+use crate::{HouseFilter, MXFError};
+
+/// How a query key maps onto `HouseFilter` fields.
+#[derive(Clone, Copy)]
+enum KeyKind {
+    /// Free text field.
+    Text(&'static str),
+    /// Region id.
+    Id(&'static str),
+    /// (lower, upper) fields; `true` when the upper end is included.
+    Range(&'static str, &'static str, bool),
+    Amenity,
+    Date,
+    /// `is:` flags.
+    Flag,
+    /// One of `HouseFilter::SORTS`.
+    Sort,
+    /// `near:lat,lng,radius`.
+    Near,
+    /// `box:minlat,minlng,maxlat,maxlng`.
+    Box,
+}
+
+/// Keys with their aliases; the first name of each is the canonical one.
+const KEYS: [(&[&str], KeyKind); 17] = [
+    (&["district", "区域", "q"], KeyKind::Text("q")),
+    (&["region", "商圈", "rg"], KeyKind::Id("rg")),
+    (&["layout", "房型", "f"], KeyKind::Text("f")),
+    (&["keyword", "关键词", "k"], KeyKind::Text("k")),
+    (&["price", "价格", "租金"], KeyKind::Range("bp", "ep", false)),
+    (&["area", "面积"], KeyKind::Range("bm", "em", false)),
+    (&["floor", "楼层"], KeyKind::Range("bf", "ef", false)),
+    (&["unitprice", "单价"], KeyKind::Range("bu", "eu", false)),
+    (&["bedrooms", "卧室"], KeyKind::Range("bs", "es", true)),
+    (&["livingrooms", "客厅"], KeyKind::Range("bt", "et", true)),
+    (&["bathrooms", "卫生间"], KeyKind::Range("bw", "ew", true)),
+    (&["has", "设施"], KeyKind::Amenity),
+    (&["available", "入住"], KeyKind::Date),
+    (&["is"], KeyKind::Flag),
+    (&["near", "附近"], KeyKind::Near),
+    (&["box", "范围"], KeyKind::Box),
+    (&["sort", "排序"], KeyKind::Sort),
+];
+
+/// English names accepted by `has:` for `HouseFilter::AMENITIES`.
+const AMENITY_ALIASES: [(&str, &str); 10] = [
+    ("ac", "空调"),
+    ("heating", "暖气"),
+    ("fridge", "冰箱"),
+    ("washer", "洗衣机"),
+    ("waterheater", "热水器"),
+    ("tv", "电视"),
+    ("wifi", "宽带"),
+    ("elevator", "电梯"),
+    ("gas", "燃气"),
+    ("bathroom", "独立卫生间"),
+];
+
+const FLAGS: [&str; 2] = ["dropped", "降价"];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Op {
+    Colon,
+    Eq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    fn as_str(self) -> &'static str {
+        match self {
+            Op::Colon => ":",
+            Op::Eq => "=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+        }
+    }
+}
+
+/// One whitespace-separated term of a query, as typed.
+struct Term<'t> {
+    index: usize,
+    text: &'t str,
+}
+
+impl Term<'_> {
+    fn error(&self, problem: impl std::fmt::Display) -> MXFError {
+        MXFError::InvalidQuery(format!("第 {} 项“{}”：{}", self.index, self.text, problem))
+    }
+}
+
+/// `lower <= value < upper`. The numbers typed are `u32`, kept as `u64` so
+/// that adding 1 for `>`, `<=`, `=` and `a..=b` cannot overflow.
+#[derive(Default, Clone, Copy, PartialEq, Debug)]
+struct Bounds {
+    lower: Option<u64>,
+    upper: Option<u64>,
+}
+
+impl Bounds {
+    /// Narrows to both ranges, so `area>=60 area<100` reads naturally.
+    fn intersect(self, other: Bounds) -> Bounds {
+        Bounds {
+            lower: self.lower.max(other.lower),
+            upper: match (self.upper, other.upper) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            },
+        }
+    }
+}
+
+/// Parses a text query such as
+/// `district:海淀 price:3000..5000 area>=60 floor<10 has:elevator` into the
+/// fields of `/zufang?<house_filter..>`, for `HouseFilter::from_fields`.
+///
+/// Words without a key are keywords. Values with spaces go in double
+/// quotes. Ranges are `a..b` (upper end excluded), `a..=b`, `a..` or `..b`,
+/// and compare with `=`, `<`, `<=`, `>` and `>=` as well. `has:` takes the
+/// amenities of the search page, or any other text in quotes.
+pub fn parse_query(text: &str) -> Result<Vec<(String, String)>, MXFError> {
+    let mut keywords = Vec::<String>::new();
+    let mut fields = Vec::<(String, String)>::new();
+    let mut ranges = Vec::<(&'static str, &'static str, bool, Bounds)>::new();
+
+    for (i, raw) in split_terms(text)?.into_iter().enumerate() {
+        let term = Term { index: i + 1, text: raw };
+        let Some((key, op, value)) = split_operator(raw) else {
+            keywords.push(unquote(&term, raw)?);
+            continue;
+        };
+        let is_quoted = value.starts_with('"');
+        let value = unquote(&term, value)?;
+        let kind = lookup_key(&term, key)?;
+        if value.is_empty() {
+            return Err(term.error(format!("“{}{}”后缺少值", key, op.as_str())));
+        }
+        if op != Op::Colon && !matches!(kind, KeyKind::Range(..)) {
+            return Err(term.error(format!("{} 只能用“:”，不能用“{}”", key, op.as_str())));
+        }
+        let mut set = |name: &str, value: String| -> Result<(), MXFError> {
+            if fields.iter().any(|(n, _)| n == name) {
+                return Err(term.error(format!("{} 重复出现", key)));
+            }
+            fields.push((name.to_string(), value));
+            Ok(())
+        };
+        match kind {
+            KeyKind::Text("k") => keywords.push(value),
+            KeyKind::Text(name) => set(name, value)?,
+            KeyKind::Id(name) => {
+                let id = value
+                    .parse::<u32>()
+                    .map_err(|_| term.error(format!("{} 须为编号，“{}”不是", key, value)))?;
+                set(name, id.to_string())?;
+            }
+            KeyKind::Range(lower, upper, inclusive) => {
+                let bounds = parse_bounds(&term, op, &value)?;
+                match ranges.iter_mut().find(|r| r.0 == lower) {
+                    Some(range) => range.3 = range.3.intersect(bounds),
+                    None => ranges.push((lower, upper, inclusive, bounds)),
+                }
+            }
+            // Quoted, any amenity the house description may list
+            KeyKind::Amenity if is_quoted => set("s", value)?,
+            KeyKind::Amenity => {
+                let amenity = AMENITY_ALIASES
+                    .iter()
+                    .find(|(alias, _)| alias.eq_ignore_ascii_case(&value))
+                    .map(|(_, name)| *name)
+                    .or_else(|| HouseFilter::AMENITIES.iter().copied().find(|a| *a == value))
+                    .ok_or_else(|| {
+                        let known = AMENITY_ALIASES
+                            .iter()
+                            .map(|(alias, name)| format!("{}({})", alias, name))
+                            .collect::<Vec<_>>()
+                            .join("、");
+                        term.error(format!(
+                            "没有设施“{}”，可选：{}；其他设施请加引号，如 has:\"阳台\"",
+                            value, known
+                        ))
+                    })?;
+                set("s", amenity.to_string())?;
+            }
+            KeyKind::Date => {
+                NaiveDate::parse_from_str(&value, HouseFilter::DATE_FORMAT).map_err(|_| {
+                    term.error(format!("日期须为 YYYY-MM-DD 格式，如 2024-09-01，而不是“{}”", value))
+                })?;
+                set("af", value)?;
+            }
+            KeyKind::Flag => {
+                if !FLAGS.contains(&value.as_str()) {
+                    return Err(term.error(format!("未知标记“{}”，可选：is:dropped", value)));
+                }
+                set("d", "true".to_string())?;
+            }
+            KeyKind::Sort => {
+                if !HouseFilter::SORTS.iter().any(|(v, _)| *v == value) {
+                    let known = HouseFilter::SORTS
+                        .iter()
+                        .map(|(v, label)| format!("{}({})", v, label))
+                        .collect::<Vec<_>>()
+                        .join("、");
+                    return Err(term.error(format!("未知排序“{}”，可选：{}", value, known)));
+                }
+                set("o", value)?;
+            }
+            KeyKind::Near => {
+                let [lat, lng, radius] = parse_numbers(&term, &value, "near:纬度,经度,半径米数")?;
+                if radius < 1.0 || radius.fract() != 0.0 || radius > u32::MAX as f64 {
+                    return Err(term.error("半径须为正整数米数"));
+                }
+                set("lat", lat.to_string())?;
+                set("lng", lng.to_string())?;
+                set("r", (radius as u32).to_string())?;
+            }
+            KeyKind::Box => {
+                let [min_lat, min_lng, max_lat, max_lng] =
+                    parse_numbers(&term, &value, "box:最小纬度,最小经度,最大纬度,最大经度")?;
+                set("minlat", min_lat.to_string())?;
+                set("minlng", min_lng.to_string())?;
+                set("maxlat", max_lat.to_string())?;
+                set("maxlng", max_lng.to_string())?;
+            }
+        }
+    }
+
+    for (lower_name, upper_name, inclusive, bounds) in ranges {
+        let key = range_key(lower_name);
+        let empty = || MXFError::InvalidQuery(format!("{} 的范围为空", key));
+        if let (Some(lower), Some(upper)) = (bounds.lower, bounds.upper) {
+            if upper <= lower {
+                return Err(empty());
+            }
+        }
+        // Nothing is above `u32::MAX`, and everything is below it plus one.
+        let lower = bounds.lower.map(u32::try_from).transpose().map_err(|_| empty())?;
+        let upper = bounds.upper.filter(|&u| u <= u32::MAX as u64);
+        // Room counts store the last value included, the rest the first excluded.
+        let upper = match (upper, inclusive) {
+            (Some(0), true) => {
+                return Err(MXFError::InvalidQuery(format!("{} 不能小于 0", key)));
+            }
+            (Some(upper), true) => Some(upper - 1),
+            (upper, _) => upper,
+        };
+        for (name, value) in [(lower_name, lower.map(u64::from)), (upper_name, upper)] {
+            if let Some(value) = value {
+                fields.push((name.to_string(), value.to_string()));
+            }
+        }
+    }
+    if !keywords.is_empty() {
+        fields.push(("k".to_string(), keywords.join(" ")));
+    }
+    Ok(fields)
+}
+
+/// Splits on whitespace outside double quotes, keeping the quotes.
+fn split_terms(text: &str) -> Result<Vec<&str>, MXFError> {
+    let mut terms = Vec::new();
+    let mut start = None;
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if quoted && c == '\\' {
+            escaped = true;
+        } else if c == '"' {
+            quoted = !quoted;
+        } else if c.is_whitespace() && !quoted {
+            if let Some(s) = start.take() {
+                terms.push(&text[s..i]);
+            }
+            continue;
+        }
+        start.get_or_insert(i);
+    }
+    if let Some(s) = start {
+        if quoted {
+            return Err(MXFError::InvalidQuery(format!(
+                "第 {} 项“{}”：引号没有闭合",
+                terms.len() + 1,
+                &text[s..]
+            )));
+        }
+        terms.push(&text[s..]);
+    }
+    Ok(terms)
+}
+
+/// Splits `key<op>value` at the first operator outside quotes.
+fn split_operator(term: &str) -> Option<(&str, Op, &str)> {
+    let end = term.find('"').unwrap_or(term.len());
+    let at = term[..end].find([':', '：', '=', '<', '>'])?;
+    let rest = &term[at..];
+    let (op, len) = if rest.starts_with(':') {
+        (Op::Colon, 1)
+    } else if rest.starts_with('：') {
+        (Op::Colon, '：'.len_utf8())
+    } else if rest.starts_with(">=") {
+        (Op::Ge, 2)
+    } else if rest.starts_with("<=") {
+        (Op::Le, 2)
+    } else if rest.starts_with('>') {
+        (Op::Gt, 1)
+    } else if rest.starts_with('<') {
+        (Op::Lt, 1)
+    } else {
+        (Op::Eq, 1)
+    };
+    Some((&term[..at], op, &rest[len..]))
+}
+
+fn unquote(term: &Term, value: &str) -> Result<String, MXFError> {
+    if !value.contains('"') {
+        return Ok(value.to_string());
+    }
+    let inner = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .ok_or_else(|| term.error("引号须包住整个值，如 district:\"海淀 区\""))?;
+    let mut unquoted = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unquoted.extend(chars.next()),
+            '"' => return Err(term.error("值中的引号须写作 \\\"")),
+            c => unquoted.push(c),
+        }
+    }
+    Ok(unquoted)
+}
+
+fn lookup_key(term: &Term, key: &str) -> Result<KeyKind, MXFError> {
+    let lower = key.to_lowercase();
+    if let Some((_, kind)) = KEYS.iter().find(|(names, _)| names.contains(&lower.as_str())) {
+        return Ok(*kind);
+    }
+    if key.is_empty() {
+        return Err(term.error("缺少条件名，如 price:3000..5000"));
+    }
+    let known = KEYS.iter().map(|(names, _)| names[0]);
+    let suggestion = known
+        .clone()
+        .map(|name| (edit_distance(&lower, name), name))
+        .filter(|(distance, name)| *distance <= name.chars().count() / 3 + 1)
+        .min()
+        .map(|(_, name)| format!("，是否想输入“{}”？", name))
+        .unwrap_or_else(|| "。".to_string());
+    Err(term.error(format!(
+        "未知条件“{}”{}可用条件：{}",
+        key,
+        suggestion,
+        known.collect::<Vec<_>>().join("、")
+    )))
+}
+
+fn range_key(lower_field: &str) -> &'static str {
+    KEYS.iter()
+        .find(|(_, kind)| matches!(kind, KeyKind::Range(lower, ..) if *lower == lower_field))
+        .map_or("", |(names, _)| names[0])
+}
+
+/// Reads the range a comparison or `a..b` describes.
+fn parse_bounds(term: &Term, op: Op, value: &str) -> Result<Bounds, MXFError> {
+    let number = |v: &str| -> Result<u64, MXFError> {
+        let v = v.trim();
+        match v.parse::<u32>() {
+            Ok(n) => Ok(n.into()),
+            Err(_) if !v.is_empty() && v.bytes().all(|b| b.is_ascii_digit()) => {
+                Err(term.error(format!("“{}”过大，不能超过 {}", v, u32::MAX)))
+            }
+            Err(_) => Err(term.error(format!(
+                "“{}”不是非负整数；范围写作 3000..5000、3000..、..5000",
+                v
+            ))),
+        }
+    };
+    let bounds = match op {
+        Op::Colon => match value.split_once("..") {
+            Some((lower, upper)) => {
+                let (upper, inclusive) = match upper.strip_prefix('=') {
+                    Some(upper) => (upper, true),
+                    None => (upper, false),
+                };
+                if lower.is_empty() && upper.is_empty() {
+                    return Err(term.error("范围至少要有一端"));
+                }
+                let lower = (!lower.is_empty()).then(|| number(lower)).transpose()?;
+                let upper = (!upper.is_empty()).then(|| number(upper)).transpose()?;
+                Bounds { lower, upper: upper.map(|u| u + inclusive as u64) }
+            }
+            None => {
+                let n = number(value)?;
+                Bounds { lower: Some(n), upper: Some(n + 1) }
+            }
+        },
+        Op::Eq => {
+            let n = number(value)?;
+            Bounds { lower: Some(n), upper: Some(n + 1) }
+        }
+        Op::Lt => Bounds { lower: None, upper: Some(number(value)?) },
+        Op::Le => Bounds { lower: None, upper: Some(number(value)? + 1) },
+        Op::Gt => Bounds { lower: Some(number(value)? + 1), upper: None },
+        Op::Ge => Bounds { lower: Some(number(value)?), upper: None },
+    };
+    Ok(bounds)
+}
+
+fn parse_numbers<const N: usize>(
+    term: &Term,
+    value: &str,
+    usage: &str,
+) -> Result<[f64; N], MXFError> {
+    let numbers = value
+        .split(',')
+        .map(|v| v.trim().parse::<f64>().ok().filter(|n| n.is_finite()))
+        .collect::<Option<Vec<f64>>>()
+        .and_then(|numbers| <[f64; N]>::try_from(numbers).ok());
+    numbers.ok_or_else(|| term.error(format!("须写作 {}", usage)))
+}
+
+/// Levenshtein distance, for suggesting the key a typo meant.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let next = (diagonal + (ca != *cb) as usize).min(row[j] + 1).min(row[j + 1] + 1);
+            diagonal = row[j + 1];
+            row[j + 1] = next;
+        }
+    }
+    row[b.len()]
+}
+
+/// Quotes a value when it would not read back as a single term, or a
+/// keyword that would read as a condition.
+pub(crate) fn quote(value: &str) -> String {
+    let plain = !value.is_empty()
+        && !value.chars().any(|c| {
+            c.is_whitespace() || matches!(c, '"' | '\\' | ':' | '：' | '=' | '<' | '>')
+        });
+    if plain {
+        value.to_string()
+    } else {
+        quoted(value)
+    }
+}
+
+/// `value` in double quotes, escaping quotes and backslashes.
+pub(crate) fn quoted(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// English name `has:` prints for an amenity, if it has one.
+pub(crate) fn amenity_alias(amenity: &str) -> &str {
+    AMENITY_ALIASES
+        .iter()
+        .find(|(_, name)| *name == amenity)
+        .map_or(amenity, |(alias, _)| alias)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Vec<(String, String)> {
+        parse_query(text).unwrap_or_else(|e| panic!("{}: {}", text, e))
+    }
+
+    fn field<'a>(fields: &'a [(String, String)], name: &str) -> Option<&'a str> {
+        fields.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    fn rejected(text: &str, problem: &str) {
+        match parse_query(text) {
+            Err(MXFError::InvalidQuery(message)) => {
+                assert!(message.contains(problem), "{}: {}", text, message)
+            }
+            other => panic!("{} should be rejected, got {:?}", text, other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn ranges() {
+        let fields = parse("price:3000..5000 area>=60 floor<10 unitprice:..=80");
+        assert_eq!(field(&fields, "bp"), Some("3000"));
+        assert_eq!(field(&fields, "ep"), Some("5000"));
+        assert_eq!(field(&fields, "bm"), Some("60"));
+        assert_eq!(field(&fields, "em"), None);
+        assert_eq!(field(&fields, "bf"), None);
+        assert_eq!(field(&fields, "ef"), Some("10"));
+        assert_eq!(field(&fields, "eu"), Some("81"));
+
+        let fields = parse("price>3000 price<=5000 price=4000");
+        assert_eq!(field(&fields, "bp"), Some("4000"));
+        assert_eq!(field(&fields, "ep"), Some("4001"));
+    }
+
+    #[test]
+    fn room_counts_are_inclusive() {
+        let fields = parse("bedrooms:2 livingrooms:1..=2 bathrooms<3");
+        assert_eq!(field(&fields, "bs"), Some("2"));
+        assert_eq!(field(&fields, "es"), Some("2"));
+        assert_eq!(field(&fields, "bt"), Some("1"));
+        assert_eq!(field(&fields, "et"), Some("2"));
+        assert_eq!(field(&fields, "bw"), None);
+        assert_eq!(field(&fields, "ew"), Some("2"));
+        rejected("bedrooms<0", "不能小于 0");
+    }
+
+    #[test]
+    fn large_numbers() {
+        rejected("price<=18446744073709551615", "过大");
+        rejected("price:99999999999999999999999", "过大");
+        rejected("price>4294967295", "范围为空");
+
+        // At most `u32::MAX` is no upper end at all
+        let fields = parse("price<=4294967295");
+        assert_eq!(field(&fields, "ep"), None);
+        let fields = parse("bedrooms:4294967295");
+        assert_eq!(field(&fields, "bs"), Some("4294967295"));
+        assert_eq!(field(&fields, "es"), None);
+        let fields = parse("area:4294967294");
+        assert_eq!(field(&fields, "em"), Some("4294967295"));
+    }
+
+    #[test]
+    fn empty_ranges() {
+        rejected("price:5000..3000", "范围为空");
+        rejected("price:3000..3000", "范围为空");
+        rejected("area>=100 area<60", "范围为空");
+        assert_eq!(field(&parse("price:3000..=3000"), "ep"), Some("3001"));
+    }
+
+    #[test]
+    fn malformed_terms() {
+        rejected("price:abc", "不是非负整数");
+        rejected("price:-5", "不是非负整数");
+        rejected("price:", "缺少值");
+        rejected("district>海淀", "只能用“:”");
+        rejected("has:elevator has:wifi", "重复出现");
+        rejected("prise:3000", "price");
+        rejected("district:\"海淀", "");
+        rejected("has:游泳池", "没有设施");
+        rejected("is:cheap", "未知标记");
+        rejected("sort:price", "未知排序");
+        rejected("available:2024/09/01", "YYYY-MM-DD");
+        rejected("region:海淀", "编号");
+        rejected("near:39.9,116.4,0", "半径");
+        rejected("box:39.9,116.4", "");
+    }
+
+    #[test]
+    fn keys_and_keywords() {
+        let fields = parse("区域:海淀 房型:\"2室1厅\" 近地铁 has:elevator is:降价 \"南北 通透\"");
+        assert_eq!(field(&fields, "q"), Some("海淀"));
+        assert_eq!(field(&fields, "f"), Some("2室1厅"));
+        assert_eq!(field(&fields, "s"), Some("电梯"));
+        assert_eq!(field(&fields, "d"), Some("true"));
+        assert_eq!(field(&fields, "k"), Some("近地铁 南北 通透"));
+
+        let fields = parse("near:39.9,116.4,1500 box:39.8,116.3,40,116.5 sort:unit_price_asc");
+        assert_eq!(field(&fields, "lat"), Some("39.9"));
+        assert_eq!(field(&fields, "r"), Some("1500"));
+        assert_eq!(field(&fields, "maxlat"), Some("40"));
+        assert_eq!(field(&fields, "o"), Some("unit_price_asc"));
+        assert!(parse("").is_empty());
+    }
+
+    #[test]
+    fn quoting() {
+        for value in ["海淀", "a b", "a\"b", "k:v", "x<y", "c:\\d"] {
+            let fields = parse(&format!("keyword:{}", quote(value)));
+            assert_eq!(field(&fields, "k"), Some(value));
+        }
+    }
+}
@@ -0,0 +1,13 @@
+use serde::Serialize;
+
+use crate::RegionModel;
+
+/// A region to drill down into from the search page.
+#[derive(Serialize, Clone, Debug)]
+pub struct RegionOption {
+    pub region: RegionModel,
+    /// Listed houses in the region matching the rest of the search.
+    pub count: u64,
+    /// Query string of the search narrowed to the region.
+    pub query: String,
+}
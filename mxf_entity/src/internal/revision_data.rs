@@ -0,0 +1,25 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FieldChange {
+    pub field: String,
+    pub label: String,
+    pub before: String,
+    pub after: String,
+}
+
+/// A revision with its changes against the revision before it.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RevisionDiff {
+    pub rno: u32,
+    pub hno: u32,
+    pub reditor: Option<u32>,
+    pub rdate: NaiveDateTime,
+    pub changes: Vec<FieldChange>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RnoData {
+    pub rno: u32,
+}
@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+
+use crate::saved_search::AlertFrequency;
+
+#[derive(Serialize, Deserialize)]
+pub struct SavedSearchData {
+    pub name: String,
+    /// Query string of a `/zufang` search, with or without the leading `?`.
+    pub query: String,
+    pub frequency: AlertFrequency,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SnoData {
+    pub sno: u32,
+}
@@ -0,0 +1,19 @@
+use jieba_rs::Jieba;
+use lazy_static::lazy_static;
+
+lazy_static! {
+    /// Loading the dictionary takes a while, so share one segmenter.
+    static ref JIEBA: Jieba = Jieba::new();
+}
+
+/// Segments Chinese text into space-separated words, the form stored in
+/// `house_search_index.content` and sent to `MATCH ... AGAINST`.
+pub fn segment(text: &str) -> String {
+    JIEBA
+        .cut_for_search(text, true)
+        .into_iter()
+        .map(str::trim)
+        .filter(|w| w.chars().any(char::is_alphanumeric))
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
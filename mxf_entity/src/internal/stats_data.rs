@@ -0,0 +1,46 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StatsEvent {
+    Impression,
+    View,
+    Favorite,
+    Request,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct DailyStats {
+    pub date: Option<NaiveDate>,
+    pub impressions: u32,
+    pub views: u32,
+    pub favorites: u32,
+    pub requests: u32,
+}
+
+/// Totals and daily trend of a listing over a period, from impressions down
+/// to lease requests.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ListingStats {
+    pub hno: u32,
+    pub total: DailyStats,
+    /// Percent of impressions that led to a view.
+    pub view_rate: u32,
+    /// Percent of views that led to a lease request.
+    pub request_rate: u32,
+    /// One entry per day, oldest first, including days without activity.
+    pub trend: Vec<DailyStats>,
+}
+
+/// Hits and misses of the search result cache since the server started.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SearchCacheStats {
+    pub page_hits: u64,
+    pub page_misses: u64,
+    pub facet_hits: u64,
+    pub facet_misses: u64,
+    /// Times a listing change emptied the cache.
+    pub invalidations: u64,
+    /// Searches and facet counts held right now.
+    pub entries: u64,
+}
@@ -0,0 +1,225 @@
+use chrono::{Duration, Local, NaiveDate};
+use sea_orm::sea_query::{Expr, OnConflict};
+use sea_orm::*;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+
+use mxf_entity::{
+    DailyStats, HouseListingModel, HouseStatsActiveModel, HouseStatsColumn, HouseStatsEntity,
+    ListingStats, MXFError, StatsEvent,
+};
+
+#[derive(Default)]
+struct Pending {
+    /// Day the dedup set below belongs to.
+    date: Option<NaiveDate>,
+    /// Hashes of (visitor, hno, event) already counted today.
+    seen: HashSet<u64>,
+    counts: HashMap<(u32, NaiveDate), DailyStats>,
+}
+
+/// Counts impressions, views, favorites and lease requests per listing and
+/// day. Events are only added up in memory on the request path; the totals
+/// are written to `house_stats_daily` by the scheduler.
+#[derive(Clone)]
+pub struct AnalyticsService {
+    pending: Arc<Mutex<Pending>>,
+}
+
+impl AnalyticsService {
+    pub const MAX_TREND_DAYS: u32 = 90;
+    /// Events remembered per day for deduplication. Past this many, events
+    /// are still counted but no longer remembered.
+    const MAX_SEEN: usize = 500_000;
+
+    /// User agent fragments of crawlers, scripts and headless browsers.
+    const BOT_MARKERS: [&'static str; 10] = [
+        "bot", "spider", "crawl", "slurp", "curl", "wget", "python", "java/", "headless",
+        "preview",
+    ];
+
+    pub fn init() -> Self {
+        Self {
+            pending: Arc::new(Mutex::new(Pending::default())),
+        }
+    }
+
+    /// Requests without a user agent are treated as bots too.
+    pub fn is_bot(user_agent: Option<&str>) -> bool {
+        match user_agent.map(str::trim) {
+            None | Some("") => true,
+            Some(ua) => {
+                let ua = ua.to_lowercase();
+                Self::BOT_MARKERS.iter().any(|m| ua.contains(m))
+            }
+        }
+    }
+
+    fn add(stats: &mut DailyStats, event: StatsEvent, n: u32) {
+        match event {
+            StatsEvent::Impression => stats.impressions += n,
+            StatsEvent::View => stats.views += n,
+            StatsEvent::Favorite => stats.favorites += n,
+            StatsEvent::Request => stats.requests += n,
+        }
+    }
+
+    fn merge(into: &mut DailyStats, other: &DailyStats) {
+        into.impressions += other.impressions;
+        into.views += other.views;
+        into.favorites += other.favorites;
+        into.requests += other.requests;
+    }
+
+    /// Counts `event` on each house, at most once per visitor and day.
+    pub fn record(&self, visitor: &str, hnos: &[u32], event: StatsEvent) {
+        let today = Local::now().date_naive();
+        let mut pending = self.pending.lock().unwrap();
+        if pending.date != Some(today) {
+            pending.date = Some(today);
+            pending.seen.clear();
+        }
+        for &hno in hnos {
+            let mut hasher = DefaultHasher::new();
+            (visitor, hno, event).hash(&mut hasher);
+            let key = hasher.finish();
+            if pending.seen.contains(&key) {
+                continue;
+            }
+            if pending.seen.len() < Self::MAX_SEEN {
+                pending.seen.insert(key);
+            }
+            let stats = pending.counts.entry((hno, today)).or_insert_with(|| DailyStats {
+                date: Some(today),
+                ..Default::default()
+            });
+            Self::add(stats, event, 1);
+        }
+    }
+
+    /// Adds the counts gathered since the last flush to the daily table.
+    /// Counts are put back if the write fails, to be retried next time.
+    pub async fn flush(&self, db: &DbConn) -> Result<usize, MXFError> {
+        let counts = std::mem::take(&mut self.pending.lock().unwrap().counts);
+        if counts.is_empty() {
+            return Ok(0);
+        }
+        let rows = counts.iter().map(|(&(hno, sdate), stats)| HouseStatsActiveModel {
+            hno: Set(hno),
+            sdate: Set(sdate),
+            simpressions: Set(stats.impressions),
+            sviews: Set(stats.views),
+            sfavorites: Set(stats.favorites),
+            srequests: Set(stats.requests),
+        });
+        let increment = |column: HouseStatsColumn, name: &str| {
+            (column, Expr::col(column).add(Expr::cust(format!("VALUES(`{}`)", name))))
+        };
+        let result = HouseStatsEntity::insert_many(rows)
+            .on_conflict(
+                OnConflict::columns([HouseStatsColumn::Hno, HouseStatsColumn::Sdate])
+                    .values([
+                        increment(HouseStatsColumn::Simpressions, "simpressions"),
+                        increment(HouseStatsColumn::Sviews, "sviews"),
+                        increment(HouseStatsColumn::Sfavorites, "sfavorites"),
+                        increment(HouseStatsColumn::Srequests, "srequests"),
+                    ])
+                    .to_owned(),
+            )
+            .exec(db)
+            .await;
+        if let Err(e) = result {
+            let mut pending = self.pending.lock().unwrap();
+            for (key, stats) in counts {
+                Self::merge(pending.counts.entry(key).or_default(), &stats);
+            }
+            return Err(e.into());
+        }
+        Ok(counts.len())
+    }
+
+    /// Totals and per-day trend over the last `days` days of each house, in
+    /// the order of `houses`. Counts not yet flushed are included.
+    pub async fn get_listing_stats(
+        &self,
+        db: &DbConn,
+        houses: &[HouseListingModel],
+        days: u32,
+    ) -> Result<Vec<ListingStats>, MXFError> {
+        let days = days.clamp(1, Self::MAX_TREND_DAYS);
+        let today = Local::now().date_naive();
+        let since = today - Duration::days(days as i64 - 1);
+        let mut daily: HashMap<(u32, NaiveDate), DailyStats> = HashMap::new();
+        for row in HouseStatsEntity::find()
+            .filter(HouseStatsColumn::Hno.is_in(houses.iter().map(|h| h.hno)))
+            .filter(HouseStatsColumn::Sdate.gte(since))
+            .all(db)
+            .await?
+        {
+            daily.insert(
+                (row.hno, row.sdate),
+                DailyStats {
+                    date: Some(row.sdate),
+                    impressions: row.simpressions,
+                    views: row.sviews,
+                    favorites: row.sfavorites,
+                    requests: row.srequests,
+                },
+            );
+        }
+        for (key, stats) in self.pending.lock().unwrap().counts.iter() {
+            if key.1 >= since {
+                Self::merge(daily.entry(*key).or_default(), stats);
+            }
+        }
+
+        // Views also come from links, without an impression, so rates are capped
+        let percent = |part: u32, whole: u32| {
+            (part as u64 * 100).checked_div(whole as u64).map_or(0, |p| p.min(100) as u32)
+        };
+        Ok(houses
+            .iter()
+            .map(|h| {
+                let mut total = DailyStats::default();
+                let trend: Vec<DailyStats> = (0..days as i64)
+                    .map(|i| {
+                        let date = since + Duration::days(i);
+                        let mut stats = daily.remove(&(h.hno, date)).unwrap_or_default();
+                        stats.date = Some(date);
+                        Self::merge(&mut total, &stats);
+                        stats
+                    })
+                    .collect();
+                ListingStats {
+                    hno: h.hno,
+                    view_rate: percent(total.views, total.impressions),
+                    request_rate: percent(total.requests, total.views),
+                    total,
+                    trend,
+                }
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_counts_each_visitor_once_a_day() {
+        let analytics = AnalyticsService::init();
+        analytics.record("a", &[1, 2], StatsEvent::View);
+        analytics.record("a", &[1], StatsEvent::View);
+        analytics.record("b", &[1], StatsEvent::View);
+        analytics.record("a", &[1], StatsEvent::Favorite);
+        let pending = analytics.pending.lock().unwrap();
+        let today = Local::now().date_naive();
+        let views = |hno| pending.counts[&(hno, today)].views;
+        assert_eq!((views(1), views(2)), (2, 1));
+        assert_eq!(pending.counts[&(1, today)].favorites, 1);
+        assert_eq!(pending.seen.len(), 4);
+    }
+}
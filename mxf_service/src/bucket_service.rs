@@ -0,0 +1,87 @@
+use sea_orm::*;
+use std::sync::{Arc, RwLock};
+
+use mxf_entity::{
+    BucketKind, BucketsInput, HouseFilter, MXFError, SavedSearchActiveModel, SavedSearchEntity,
+    SearchBucketActiveModel, SearchBucketColumn, SearchBucketEntity, SearchBuckets,
+};
+
+/// Keeps the search page buckets in line with the `search_buckets` table.
+#[derive(Clone)]
+pub struct BucketService {
+    /// Buckets the search page is using, replaced whenever admins edit them.
+    buckets: Arc<RwLock<Arc<SearchBuckets>>>,
+}
+
+impl BucketService {
+    /// Uses the buckets seeded by the migration until `load` reads the table.
+    pub fn init() -> Self {
+        Self {
+            buckets: Arc::new(RwLock::new(Arc::new(SearchBuckets::default()))),
+        }
+    }
+
+    /// Reads the stored buckets and makes every search use them.
+    pub async fn load(&self, db: &DbConn) -> Result<(), MXFError> {
+        let rows = SearchBucketEntity::find().all(db).await?;
+        *self.buckets.write().unwrap() = Arc::new(SearchBuckets::from_rows(rows));
+        Ok(())
+    }
+
+    pub fn get_buckets(&self) -> Arc<SearchBuckets> {
+        self.buckets.read().unwrap().clone()
+    }
+
+    /// Replaces all buckets of one kind. Saved searches selecting one of the
+    /// old buckets get its bounds instead, so that they still find the same
+    /// houses.
+    pub async fn save_buckets(&self, db: &DbConn, input: BucketsInput) -> Result<(), MXFError> {
+        SearchBuckets::validate(&input.buckets)?;
+        let rows = input
+            .buckets
+            .iter()
+            .enumerate()
+            .map(|(i, bucket)| SearchBucketActiveModel {
+                bkind: Set(input.kind),
+                bindex: Set(i as u32 + 1),
+                blower: Set(bucket.lower),
+                bupper: Set(bucket.upper),
+                blabel: Set(bucket.label.trim().to_string()),
+            });
+        let txn = db.begin().await?;
+        SearchBucketEntity::delete_many()
+            .filter(SearchBucketColumn::Bkind.eq(input.kind))
+            .exec(&txn)
+            .await?;
+        SearchBucketEntity::insert_many(rows).exec(&txn).await?;
+        Self::write_out_buckets(&txn, &self.get_buckets(), input.kind).await?;
+        txn.commit().await?;
+        println!("Search buckets {:?} replaced", input.kind);
+        self.load(db).await
+    }
+
+    /// Rewrites the saved searches selecting a bucket of `kind` in `old`
+    /// with the bounds of that bucket.
+    async fn write_out_buckets(
+        txn: &DatabaseTransaction,
+        old: &SearchBuckets,
+        kind: BucketKind,
+    ) -> Result<(), MXFError> {
+        for search in SavedSearchEntity::find().all(txn).await? {
+            let fields = HouseFilter::decode_query(&search.squery);
+            let query = match HouseFilter::from_fields(&fields, old) {
+                Ok(filter) => filter.with_bucket_bounds(kind).to_query_string(),
+                Err(e) => {
+                    println!("saved search {} left as is: {}", search.sno, e);
+                    continue;
+                }
+            };
+            if query != search.squery {
+                let mut search: SavedSearchActiveModel = search.into();
+                search.squery = Set(query);
+                search.update(txn).await?;
+            }
+        }
+        Ok(())
+    }
+}
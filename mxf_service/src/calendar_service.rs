@@ -0,0 +1,182 @@
+use chrono::{Duration, Local, NaiveDate, NaiveDateTime, NaiveTime};
+use sea_orm::*;
+
+use mxf_entity::{
+    AvailabilityCalendar, CalendarPeriod, MXFError, OrderColumn, OrderEntity, OrderModel,
+    PeriodKind,
+};
+
+use crate::OrderService;
+
+/// Works out when a house is let, requested or free from its order chains.
+pub struct CalendarService;
+
+impl CalendarService {
+    pub fn init() -> Self {
+        Self {}
+    }
+
+    /// A lease ending during a day still takes that day.
+    fn end_day(end: NaiveDateTime) -> NaiveDate {
+        if end.time() == NaiveTime::MIN {
+            end.date()
+        } else {
+            end.date() + Duration::days(1)
+        }
+    }
+
+    /// Periods from `today` on, built from the latest order of each chain.
+    /// Where a confirmed lease and a request overlap, the lease wins.
+    pub fn build(orders: &Vec<OrderModel>, today: NaiveDate) -> Vec<CalendarPeriod> {
+        let taken: Vec<(PeriodKind, NaiveDate, NaiveDate)> = OrderService::filter_latest(orders)
+            .iter()
+            .filter_map(|o| {
+                let kind = OrderService::hold(o)?;
+                let (start, end) = (o.ostart.date().max(today), Self::end_day(o.oend));
+                (start < end).then_some((kind, start, end))
+            })
+            .collect();
+
+        let mut bounds: Vec<NaiveDate> = taken
+            .iter()
+            .flat_map(|&(_, start, end)| [start, end])
+            .chain([today])
+            .collect();
+        bounds.sort();
+        bounds.dedup();
+
+        let mut periods: Vec<CalendarPeriod> = Vec::new();
+        for (i, &start) in bounds.iter().enumerate() {
+            let covering = |kind| {
+                taken
+                    .iter()
+                    .any(|&(k, s, e)| k == kind && s <= start && start < e)
+            };
+            let kind = if covering(PeriodKind::Booked) {
+                PeriodKind::Booked
+            } else if covering(PeriodKind::Pending) {
+                PeriodKind::Pending
+            } else {
+                PeriodKind::Free
+            };
+            let end = bounds.get(i + 1).copied();
+            match periods.last_mut() {
+                Some(last) if last.kind == kind => last.end = end,
+                _ => periods.push(CalendarPeriod { kind, start, end }),
+            }
+        }
+        periods
+    }
+
+    /// Calendar of house `hno` from all of its orders.
+    pub fn from_orders(hno: u32, orders: &Vec<OrderModel>) -> AvailabilityCalendar {
+        let today = Local::now().date_naive();
+        let periods = Self::build(orders, today);
+        // The last period always runs on without end and is free
+        let available_from = periods
+            .iter()
+            .find(|p| p.kind == PeriodKind::Free)
+            .map_or(today, |p| p.start);
+        AvailabilityCalendar {
+            hno,
+            periods,
+            available_from,
+        }
+    }
+
+    pub async fn get_calendar(
+        &self,
+        db: &DbConn,
+        hno: u32,
+    ) -> Result<AvailabilityCalendar, MXFError> {
+        let orders = OrderEntity::find()
+            .filter(OrderColumn::Hno.eq(hno))
+            .all(db)
+            .await?;
+        Ok(Self::from_orders(hno, &orders))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mxf_entity::OrderType;
+
+    fn date(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 9, d).unwrap()
+    }
+
+    /// The only order of chain `ostatus`, from midnight on `start` to `end`.
+    fn order(ostatus: u32, otype: OrderType, start: NaiveDate, end: NaiveDate) -> OrderModel {
+        OrderModel {
+            ono: ostatus,
+            hno: 1,
+            hlandlore: 2,
+            htenant: 3,
+            odate: start.and_time(NaiveTime::MIN),
+            otype,
+            ostart: start.and_time(NaiveTime::MIN),
+            oend: end.and_time(NaiveTime::MIN),
+            ostatus,
+        }
+    }
+
+    fn period(kind: PeriodKind, start: NaiveDate, end: Option<NaiveDate>) -> CalendarPeriod {
+        CalendarPeriod { kind, start, end }
+    }
+
+    #[test]
+    fn build_splits_days_by_hold() {
+        assert_eq!(
+            CalendarService::build(&Vec::new(), date(1)),
+            vec![period(PeriodKind::Free, date(1), None)]
+        );
+        let orders = vec![
+            order(1, OrderType::LeaseConfirm, date(5), date(20)),
+            // Overlaps the lease, which wins
+            order(2, OrderType::LeaseRequest, date(15), date(25)),
+            order(3, OrderType::CancelConfirm, date(25), date(28)),
+        ];
+        assert_eq!(
+            CalendarService::build(&orders, date(1)),
+            vec![
+                period(PeriodKind::Free, date(1), Some(date(5))),
+                period(PeriodKind::Booked, date(5), Some(date(20))),
+                period(PeriodKind::Pending, date(20), Some(date(25))),
+                period(PeriodKind::Free, date(25), None),
+            ]
+        );
+        // Periods start today at the earliest
+        assert_eq!(
+            CalendarService::build(&orders, date(10))[0],
+            period(PeriodKind::Booked, date(10), Some(date(20)))
+        );
+        // A lease ending during a day takes that day
+        let mut lease = order(1, OrderType::LeaseConfirm, date(5), date(20));
+        lease.oend = date(20).and_hms_opt(12, 0, 0).unwrap();
+        assert_eq!(
+            CalendarService::build(&vec![lease], date(5)),
+            vec![
+                period(PeriodKind::Booked, date(5), Some(date(21))),
+                period(PeriodKind::Free, date(21), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn from_orders_is_available_after_holds() {
+        let today = Local::now().date_naive();
+        let calendar = CalendarService::from_orders(1, &Vec::new());
+        assert_eq!(calendar.available_from, today);
+        let orders = vec![order(
+            1,
+            OrderType::LeaseConfirm,
+            today - Duration::days(10),
+            today + Duration::days(30),
+        )];
+        let calendar = CalendarService::from_orders(1, &orders);
+        assert_eq!(calendar.hno, 1);
+        assert_eq!(calendar.available_from, today + Duration::days(30));
+        assert_eq!(calendar.periods[0].start, today);
+    }
+}
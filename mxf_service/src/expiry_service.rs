@@ -0,0 +1,153 @@
+use chrono::{Duration, Local, NaiveDateTime};
+use sea_orm::*;
+use serde::Deserialize;
+
+use mxf_entity::{
+    HouseListingActiveModel, HouseListingColumn, HouseListingEntity, ListStatus, MXFError,
+    NotificationType,
+};
+
+use crate::{FavoriteService, HouseService, JobService, NotificationService, RevisionService};
+
+/// Days a listing may go without a refresh, read from the `expiry` table of
+/// the Rocket config.
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(default)]
+pub struct ExpiryPolicy {
+    /// Days after which the landlord is reminded.
+    pub remind_after_days: i64,
+    /// Days after which the house is unlisted.
+    pub expire_after_days: i64,
+}
+
+impl Default for ExpiryPolicy {
+    fn default() -> Self {
+        ExpiryPolicy {
+            remind_after_days: 30,
+            expire_after_days: 45,
+        }
+    }
+}
+
+/// Takes listings off `/zufang` once their landlord stops looking after them.
+/// A listing counts from its last refresh: landlords are reminded after
+/// `remind_after_days` and the house is unlisted after `expire_after_days`.
+/// Expired houses go back through review before they are listed again.
+#[derive(Clone)]
+pub struct ExpiryService {
+    policy: ExpiryPolicy,
+}
+
+impl ExpiryService {
+    /// `hreason` of houses unlisted by expiry, telling them apart from houses
+    /// their landlord unlisted.
+    pub const EXPIRED_REASON: &'static str = "长期未更新，已自动下架";
+    const REMINDERS_JOB: &'static str = "expiry_reminders";
+
+    pub fn init(policy: ExpiryPolicy) -> Self {
+        Self { policy }
+    }
+
+    /// Reminds the landlords of listings that became due for a reminder
+    /// since the last run, up to `until`, so each is reminded once.
+    pub async fn send_reminders(&self, db: &DbConn, until: NaiveDateTime) -> Result<(), MXFError> {
+        let since = JobService::last_run(db, Self::REMINDERS_JOB, until).await?;
+        let age = Duration::days(self.policy.remind_after_days);
+        let due = HouseListingEntity::find()
+            .filter(HouseListingColumn::Hunlisted.eq(ListStatus::Listed))
+            .filter(HouseListingColumn::HrefreshedAt.gt(since - age))
+            .filter(HouseListingColumn::HrefreshedAt.lte(until - age))
+            .all(db)
+            .await?;
+        let content = format!(
+            "房源已 {} 天未更新，将在 {} 天后自动下架。如仍可出租，请点击“仍可出租”",
+            self.policy.remind_after_days,
+            self.policy.expire_after_days - self.policy.remind_after_days
+        );
+        for house in due {
+            NotificationService::notify_all(
+                db,
+                &[house.hlandlore],
+                house.hno,
+                NotificationType::ListingExpiring,
+                &content,
+            )
+            .await?;
+        }
+        JobService::finish(db, Self::REMINDERS_JOB, until).await
+    }
+
+    /// Unlists every listing not refreshed for `expire_after_days` at `now`.
+    /// Returns the number of houses unlisted.
+    pub async fn expire_stale(&self, db: &DbConn, now: NaiveDateTime) -> Result<usize, MXFError> {
+        let cutoff = now - Duration::days(self.policy.expire_after_days);
+        let stale = HouseListingEntity::find()
+            .filter(HouseListingColumn::Hunlisted.eq(ListStatus::Listed))
+            .filter(
+                Condition::any()
+                    .add(HouseListingColumn::HrefreshedAt.lte(cutoff))
+                    .add(HouseListingColumn::HrefreshedAt.is_null()),
+            )
+            .all(db)
+            .await?;
+        let expired = stale.len();
+        for house in stale {
+            let mut updated: HouseListingActiveModel = house.clone().into();
+            updated.hunlisted = Set(ListStatus::Unlisted);
+            updated.hreason = Set(Some(Self::EXPIRED_REASON.to_string()));
+            let updated = updated.update(db).await?;
+            println!("Expire house {}", house.hno);
+            RevisionService::record(db, Some(&house), &updated, None).await?;
+            NotificationService::notify_all(
+                db,
+                &[house.hlandlore],
+                house.hno,
+                NotificationType::ListingExpired,
+                &format!(
+                    "房源已 {} 天未更新，已自动下架。如仍可出租，请点击“仍可出租”，审核通过后重新上架",
+                    self.policy.expire_after_days
+                ),
+            )
+            .await?;
+            FavoriteService::notify_watchers(db, house.hno, NotificationType::Unlisted, "房源已下架")
+                .await?;
+        }
+        Ok(expired)
+    }
+
+    /// Landlord `uno` confirms house `hno` is still available. Listed houses
+    /// start a new lifetime; houses unlisted by expiry are submitted for
+    /// review, as nobody has looked at them for a while. Houses the landlord
+    /// unlisted stay unlisted. Returns the status of the house afterwards.
+    pub async fn refresh_house(
+        &self,
+        db: &DbConn,
+        house_service: &HouseService,
+        hno: u32,
+        uno: u32,
+    ) -> Result<ListStatus, MXFError> {
+        let house = house_service.get_house_by_hno(db, hno).await?;
+        if house.hlandlore != uno {
+            return Err(MXFError::NotLandlore(uno));
+        }
+        if house.hunlisted.is_archived() {
+            return Err(MXFError::HouseArchived(hno));
+        }
+        let now = Local::now().naive_local();
+        let relist = house.hunlisted == ListStatus::Unlisted
+            && house.hreason.as_deref() == Some(Self::EXPIRED_REASON);
+        let mut refreshed: HouseListingActiveModel = house.clone().into();
+        refreshed.hrefreshed_at = Set(Some(now));
+        if relist {
+            refreshed.hunlisted = Set(ListStatus::PendingReview);
+            refreshed.hreason = Set(None);
+        }
+        let refreshed = refreshed.update(db).await?;
+        house_service.listings_changed();
+        if relist {
+            println!("Submit expired house {} for review by {}", hno, uno);
+            RevisionService::record(db, Some(&house), &refreshed, Some(uno)).await?;
+        }
+        Ok(refreshed.hunlisted)
+    }
+}
@@ -0,0 +1,161 @@
+use futures::stream::{self, Stream, StreamExt};
+use rust_xlsxwriter::Workbook;
+use sea_orm::*;
+
+use mxf_entity::{ExportFormat, ExportRow, HouseListingEntity, MXFError};
+
+/// Writes houses out as CSV, JSON Lines or XLSX. Headers match the labels
+/// accepted by `ImportService`, so an exported portfolio can be imported back.
+/// External ids are only written to portfolios.
+pub struct ExportService;
+
+impl ExportService {
+    const HEADERS: [&'static str; 13] = [
+        "房源编号", "外部编号", "区域", "地址", "房型", "楼层", "面积", "价格", "主要设施", "状态",
+        "纬度", "经度", "描述",
+    ];
+
+    /// Columns written as numbers in XLSX, so the sheet can be sorted and summed.
+    const NUMERIC_COLUMNS: [usize; 6] = [0, 5, 6, 7, 10, 11];
+
+    /// Excel only reads UTF-8 CSV files starting with a byte order mark.
+    const UTF8_BOM: &'static [u8] = b"\xEF\xBB\xBF";
+
+    fn fields(house: &ExportRow) -> Vec<String> {
+        let coordinate = |c: Option<f64>| c.map(|c| c.to_string()).unwrap_or_default();
+        vec![
+            house.hno.to_string(),
+            house.hextref.clone().unwrap_or_default(),
+            house.hdistrict.clone(),
+            house.haddr.clone(),
+            house.hlo.clone(),
+            house.hflr.to_string(),
+            house.harea.to_string(),
+            house.hprice.to_string(),
+            house.hsuite.clone(),
+            format!("{:?}", house.hunlisted),
+            coordinate(house.hlat),
+            coordinate(house.hlng),
+            house.hdesc.clone(),
+        ]
+    }
+
+    fn csv_record<I, T>(record: I) -> Result<Vec<u8>, MXFError>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<[u8]>,
+    {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer
+            .write_record(record)
+            .map_err(|e| MXFError::UnknownError(e.to_string()))?;
+        writer
+            .into_inner()
+            .map_err(|e| MXFError::UnknownError(e.to_string()))
+    }
+
+    fn encode(format: ExportFormat, house: &ExportRow) -> Result<Vec<u8>, MXFError> {
+        match format {
+            ExportFormat::Jsonl => {
+                let mut line =
+                    serde_json::to_vec(house).map_err(|e| MXFError::UnknownError(e.to_string()))?;
+                line.push(b'\n');
+                Ok(line)
+            }
+            _ => Self::csv_record(Self::fields(house)),
+        }
+    }
+
+    /// Streams the selected houses as CSV or JSON Lines, one chunk per row, so
+    /// that no more than one row is held in memory. A database error ends the
+    /// stream early, as the response has already started.
+    async fn stream<'a>(
+        db: &'a DbConn,
+        select: Select<HouseListingEntity>,
+        format: ExportFormat,
+        with_extref: bool,
+    ) -> Result<impl Stream<Item = Vec<u8>> + Send + 'a, MXFError> {
+        let header = match format {
+            ExportFormat::Jsonl => Vec::new(),
+            _ => [Self::UTF8_BOM.to_vec(), Self::csv_record(Self::HEADERS)?].concat(),
+        };
+        let rows = select
+            .stream(db)
+            .await?
+            .take_while(|house| {
+                if let Err(e) = house {
+                    println!("export stopped: {}", e);
+                }
+                futures::future::ready(house.is_ok())
+            })
+            .filter_map(move |house| {
+                let chunk = house
+                    .map_err(MXFError::from)
+                    .and_then(|h| Self::encode(format, &ExportRow::new(h, with_extref)));
+                futures::future::ready(chunk.map_err(|e| println!("export row skipped: {}", e)).ok())
+            });
+        Ok(stream::once(futures::future::ready(header)).chain(rows))
+    }
+
+    /// XLSX files are zip archives and can only be written out once complete,
+    /// so the whole file is built in memory before it is sent.
+    async fn xlsx(
+        db: &DbConn,
+        select: Select<HouseListingEntity>,
+        with_extref: bool,
+    ) -> Result<Vec<u8>, MXFError> {
+        let xlsx_error = |e: rust_xlsxwriter::XlsxError| MXFError::UnknownError(e.to_string());
+        let mut workbook = Workbook::new();
+        let worksheet = workbook.add_worksheet_with_constant_memory();
+        worksheet
+            .write_row(0, 0, Self::HEADERS)
+            .map_err(xlsx_error)?;
+        let mut houses = select.stream(db).await?;
+        let mut row = 1;
+        while let Some(house) = houses.next().await {
+            let house = house?;
+            let fields = Self::fields(&ExportRow::new(house, with_extref));
+            for (col, value) in fields.iter().enumerate() {
+                match value.parse::<f64>() {
+                    Ok(number) if Self::NUMERIC_COLUMNS.contains(&col) => {
+                        worksheet.write_number(row, col as u16, number)
+                    }
+                    _ => worksheet.write_string(row, col as u16, value),
+                }
+                .map_err(xlsx_error)?;
+            }
+            row += 1;
+        }
+        workbook.save_to_buffer().map_err(xlsx_error)
+    }
+
+    /// The export as response body chunks. Owns the connection, so the stream
+    /// can outlive the request handler. `with_extref` is for a landlord's own
+    /// houses only.
+    pub fn export(
+        db: DbConn,
+        select: Select<HouseListingEntity>,
+        format: ExportFormat,
+        with_extref: bool,
+    ) -> impl Stream<Item = Vec<u8>> + Send {
+        async_stream::stream! {
+            match format {
+                ExportFormat::Xlsx => match Self::xlsx(&db, select, with_extref).await {
+                    Ok(bytes) => {
+                        yield bytes;
+                    }
+                    Err(e) => println!("export failed: {}", e),
+                },
+                _ => match Self::stream(&db, select, format, with_extref).await {
+                    Ok(chunks) => {
+                        let mut chunks = Box::pin(chunks);
+                        while let Some(chunk) = chunks.next().await {
+                            yield chunk;
+                        }
+                    }
+                    Err(e) => println!("export failed: {}", e),
+                },
+            }
+        }
+    }
+}
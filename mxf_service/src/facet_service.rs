@@ -0,0 +1,97 @@
+use sea_orm::sea_query::{Alias, Expr, Func};
+use sea_orm::*;
+
+use mxf_entity::{
+    AmenityCount, BucketKind, FacetCounts, HouseFilter, HouseListingColumn, HouseListingEntity,
+    ListStatus, MXFError,
+};
+
+use crate::SearchCache;
+
+/// Counts the listed houses in every bucket of the search page.
+pub struct FacetService;
+
+impl FacetService {
+    pub fn init() -> Self {
+        Self {}
+    }
+
+    /// Facet counts of a search. All buckets are counted in one pass over the
+    /// houses matching the search without any facet; each bucket then also
+    /// requires the search without its own facet.
+    pub async fn get_facet_counts(
+        &self,
+        db: &DbConn,
+        search_cache: &SearchCache,
+        house_filter: HouseFilter<'_>,
+    ) -> Result<FacetCounts, MXFError> {
+        if let Some(facets) = search_cache.facets(house_filter) {
+            return Ok(facets);
+        }
+
+        let generation = search_cache.generation();
+        let listed = || HouseListingColumn::Hunlisted.eq(ListStatus::Listed);
+        let buckets = |kind| house_filter.bucket_conditions(kind);
+        let facets: [(&str, HouseFilter, Vec<Condition>); 4] = [
+            ("floor", house_filter.without_floor(), buckets(BucketKind::Floor)),
+            ("area", house_filter.without_area(), buckets(BucketKind::Area)),
+            ("price", house_filter.without_price(), buckets(BucketKind::Price)),
+            (
+                "suite",
+                house_filter.with_suite(""),
+                HouseFilter::AMENITIES
+                    .iter()
+                    .map(|a| Condition::all().add(HouseListingColumn::Hsuite.contains(*a)))
+                    .collect(),
+            ),
+        ];
+        let base = house_filter
+            .without_floor()
+            .without_area()
+            .without_price()
+            .with_suite("");
+        let mut select = HouseListingEntity::find()
+            .select_only()
+            .filter(Condition::from(base))
+            .filter(listed());
+        for (name, relaxed, buckets) in facets.iter() {
+            for (i, bucket) in buckets.iter().enumerate() {
+                let matches = Condition::from(*relaxed).add(listed()).add(bucket.clone());
+                QueryTrait::query(&mut select).expr_as(
+                    Func::count(Expr::case(matches, 1)),
+                    Alias::new(format!("{}_{}", name, i)),
+                );
+            }
+        }
+        let row = db
+            .query_one(select.build(db.get_database_backend()))
+            .await?
+            .ok_or(MXFError::UnknownError("No facet counts returned".to_string()))?;
+        let count = |name: &str, i: usize| -> Result<u64, MXFError> {
+            Ok(row.try_get::<i64>("", &format!("{}_{}", name, i))? as u64)
+        };
+        let counts = |(name, _, buckets): &(&str, HouseFilter, Vec<Condition>)| {
+            (0..buckets.len())
+                .map(|i| count(name, i))
+                .collect::<Result<Vec<u64>, MXFError>>()
+        };
+
+        let suites = counts(&facets[3])?
+            .into_iter()
+            .zip(HouseFilter::AMENITIES)
+            .map(|(count, name)| AmenityCount {
+                name: name.to_string(),
+                count,
+                query: house_filter.with_suite(name).to_query_string(),
+            })
+            .collect();
+        let facets = FacetCounts {
+            floors: counts(&facets[0])?,
+            areas: counts(&facets[1])?,
+            prices: counts(&facets[2])?,
+            suites,
+        };
+        search_cache.insert_facets(generation, house_filter, facets.clone());
+        Ok(facets)
+    }
+}
@@ -0,0 +1,160 @@
+use chrono::{Local, NaiveDateTime};
+use sea_orm::*;
+use std::collections::{HashMap, HashSet};
+
+use mxf_entity::{
+    FavoriteActiveModel, FavoriteColumn, FavoriteEntity, FavoriteHouse, HouseListingColumn,
+    HouseListingEntity, ListStatus, MXFError, NotificationType, OrderColumn, OrderEntity,
+    OrderModel, OrderType,
+};
+
+use crate::{JobService, NotificationService, OrderService};
+
+pub struct FavoriteService;
+
+impl FavoriteService {
+    const LEASES_ENDED_JOB: &'static str = "leases_ended";
+
+    pub fn init() -> Self {
+        Self {}
+    }
+
+    pub async fn is_favorite(&self, db: &DbConn, uno: u32, hno: u32) -> Result<bool, MXFError> {
+        Ok(FavoriteEntity::find_by_id((uno, hno)).one(db).await?.is_some())
+    }
+
+    /// Adds house `hno` to the favorites of `uno`, if the user may see it.
+    pub async fn add_favorite(
+        &self,
+        db: &DbConn,
+        uno: u32,
+        hno: u32,
+        staff: bool,
+    ) -> Result<(), MXFError> {
+        if self.is_favorite(db, uno, hno).await? {
+            return Ok(());
+        }
+        HouseListingEntity::find_by_id(hno)
+            .one(db)
+            .await?
+            .filter(|house| house.is_visible_to(Some(uno), staff))
+            .ok_or(MXFError::HouseUnavailable(hno))?;
+        let favorite = FavoriteActiveModel {
+            uno: Set(uno),
+            hno: Set(hno),
+            fdate: Set(Local::now().naive_local()),
+        };
+        favorite.insert(db).await?;
+        Ok(())
+    }
+
+    pub async fn remove_favorite(&self, db: &DbConn, uno: u32, hno: u32) -> Result<(), MXFError> {
+        FavoriteEntity::delete_by_id((uno, hno)).exec(db).await?;
+        Ok(())
+    }
+
+    /// If ok, returns the favorites of `uno` with their current availability.
+    /// Houses taken off the site, or never approved, are left out.
+    pub async fn get_favorites_by_uno(
+        &self,
+        db: &DbConn,
+        uno: u32,
+    ) -> Result<Vec<FavoriteHouse>, MXFError> {
+        let favorites = FavoriteEntity::find()
+            .filter(FavoriteColumn::Uno.eq(uno))
+            .order_by_desc(FavoriteColumn::Fdate)
+            .find_also_related(HouseListingEntity)
+            .all(db)
+            .await?;
+        let hnos: Vec<u32> = favorites.iter().map(|(f, _)| f.hno).collect();
+        let mut orders: HashMap<u32, Vec<OrderModel>> = HashMap::new();
+        for order in OrderEntity::find()
+            .filter(OrderColumn::Hno.is_in(hnos))
+            .all(db)
+            .await?
+        {
+            orders.entry(order.hno).or_default().push(order);
+        }
+
+        let now = Local::now().naive_local();
+        Ok(favorites
+            .into_iter()
+            .filter_map(|(favorite, house)| house.map(|h| (favorite, h)))
+            .filter(|(_, house)| house.hunlisted.is_approved())
+            .map(|(favorite, house)| {
+                let occupied = orders
+                    .get(&house.hno)
+                    .map(|o| OrderService::is_occupied(o, now))
+                    .unwrap_or(false);
+                FavoriteHouse {
+                    available: house.hunlisted == ListStatus::Listed && !occupied,
+                    house,
+                    fdate: favorite.fdate,
+                }
+            })
+            .collect())
+    }
+
+    /// Notifies every user who favorited house `hno`.
+    pub async fn notify_watchers(
+        db: &DbConn,
+        hno: u32,
+        ntype: NotificationType,
+        content: &str,
+    ) -> Result<(), MXFError> {
+        let watchers: Vec<u32> = FavoriteEntity::find()
+            .select_only()
+            .column(FavoriteColumn::Uno)
+            .filter(FavoriteColumn::Hno.eq(hno))
+            .into_tuple()
+            .all(db)
+            .await?;
+        NotificationService::notify_all(db, &watchers, hno, ntype, content).await
+    }
+
+    /// Notifies watchers of houses whose confirmed lease ended since the last
+    /// run, up to `until`. Leases cancelled after their confirmation never
+    /// held the house.
+    pub async fn notify_leases_ended(db: &DbConn, until: NaiveDateTime) -> Result<(), MXFError> {
+        let since = JobService::last_run(db, Self::LEASES_ENDED_JOB, until).await?;
+        let ended: HashSet<u32> = OrderEntity::find()
+            .select_only()
+            .column(OrderColumn::Ostatus)
+            .filter(OrderColumn::Otype.eq(OrderType::LeaseConfirm))
+            .filter(OrderColumn::Oend.gt(since))
+            .filter(OrderColumn::Oend.lte(until))
+            .into_tuple::<u32>()
+            .all(db)
+            .await?
+            .into_iter()
+            .collect();
+        let chains = OrderEntity::find()
+            .filter(OrderColumn::Ostatus.is_in(ended))
+            .all(db)
+            .await?;
+        let hnos: HashSet<u32> = OrderService::filter_latest(&chains)
+            .iter()
+            .filter(|o| o.otype == OrderType::LeaseConfirm && o.oend > since && o.oend <= until)
+            .map(|o| o.hno)
+            .collect();
+        // Only houses still on the site can be leased again
+        let hnos: Vec<u32> = HouseListingEntity::find()
+            .select_only()
+            .column(HouseListingColumn::Hno)
+            .filter(HouseListingColumn::Hno.is_in(hnos))
+            .filter(HouseListingColumn::Hunlisted.eq(ListStatus::Listed))
+            .into_tuple()
+            .all(db)
+            .await?;
+        for hno in hnos {
+            Self::notify_watchers(
+                db,
+                hno,
+                NotificationType::AvailableAgain,
+                "租约已结束，您收藏的房源可以再次申请租赁",
+            )
+            .await?;
+        }
+        JobService::finish(db, Self::LEASES_ENDED_JOB, until).await
+    }
+}
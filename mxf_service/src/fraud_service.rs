@@ -0,0 +1,214 @@
+use chrono::Local;
+use sea_orm::sea_query::Expr;
+use sea_orm::*;
+use std::collections::HashMap;
+
+use mxf_entity::{
+    district_spellings, FlagKind, HouseFlagActiveModel, HouseFlagColumn, HouseFlagEntity,
+    HouseFlagModel, HouseListingColumn, HouseListingEntity, HouseListingModel, ListStatus,
+    MXFError,
+};
+
+/// Flags listings that look like reposts of another listing, or whose price
+/// is far off the district, for staff to check before they go public.
+pub struct FraudService;
+
+impl FraudService {
+    /// Areas within this fraction of each other count as the same flat.
+    const AREA_TOLERANCE: f64 = 0.05;
+    /// Fewer listed houses than this in a district give no usable median.
+    const MIN_DISTRICT_SAMPLES: usize = 5;
+    /// Price per m² this many times above or below the median is an outlier.
+    const OUTLIER_FACTOR: f64 = 2.0;
+
+    pub fn init() -> Self {
+        Self {}
+    }
+
+    /// Address without spacing, punctuation or full-width forms, so that
+    /// "海淀区 中关村大街１号-3" and "海淀区中关村大街1号3" compare equal.
+    pub fn normalize_address(addr: &str) -> String {
+        addr.chars()
+            .map(|c| match c {
+                '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+                _ => c,
+            })
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect()
+    }
+
+    async fn find_duplicates(
+        db: &DbConn,
+        house: &HouseListingModel,
+    ) -> Result<Vec<HouseFlagModel>, MXFError> {
+        let address = Self::normalize_address(&house.haddr);
+        if address.is_empty() {
+            return Ok(Vec::new());
+        }
+        let tolerance = (house.harea as f64 * Self::AREA_TOLERANCE).max(2.0) as u32;
+        let candidates = HouseListingEntity::find()
+            .filter(HouseListingColumn::Hdistrict.is_in(district_spellings(&house.hdistrict)))
+            .filter(HouseListingColumn::Hflr.eq(house.hflr))
+            .filter(HouseListingColumn::Harea.between(
+                house.harea.saturating_sub(tolerance),
+                house.harea + tolerance,
+            ))
+            .filter(HouseListingColumn::Hno.ne(house.hno))
+            .filter(HouseListingColumn::Hunlisted.ne(ListStatus::Deleted))
+            .all(db)
+            .await?;
+        Ok(candidates
+            .into_iter()
+            .filter(|other| {
+                let other_address = Self::normalize_address(&other.haddr);
+                !other_address.is_empty()
+                    && (other_address.contains(&address) || address.contains(&other_address))
+            })
+            .map(|other| HouseFlagModel {
+                fno: 0,
+                hno: house.hno,
+                fkind: FlagKind::Duplicate,
+                fother: Some(other.hno),
+                fdetail: format!(
+                    "与房源 {} 的地址、楼层、面积相同{}",
+                    other.hno,
+                    if other.hlandlore == house.hlandlore { "" } else { "（不同房东发布）" }
+                ),
+                fdate: Local::now().naive_local(),
+                fresolved: false,
+            })
+            .collect())
+    }
+
+    async fn check_price(
+        db: &DbConn,
+        house: &HouseListingModel,
+    ) -> Result<Option<HouseFlagModel>, MXFError> {
+        if house.harea == 0 {
+            return Ok(None);
+        }
+        let mut per_m2: Vec<f64> = HouseListingEntity::find()
+            .select_only()
+            .column(HouseListingColumn::Hprice)
+            .column(HouseListingColumn::Harea)
+            .filter(HouseListingColumn::Hdistrict.is_in(district_spellings(&house.hdistrict)))
+            .filter(HouseListingColumn::Hunlisted.eq(ListStatus::Listed))
+            .filter(HouseListingColumn::Hno.ne(house.hno))
+            .filter(HouseListingColumn::Harea.gt(0))
+            .into_tuple::<(u32, u32)>()
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|(price, area)| price as f64 / area as f64)
+            .collect();
+        if per_m2.len() < Self::MIN_DISTRICT_SAMPLES {
+            return Ok(None);
+        }
+        per_m2.sort_by(|a, b| a.total_cmp(b));
+        let mid = per_m2.len() / 2;
+        let median = if per_m2.len() % 2 == 0 {
+            (per_m2[mid - 1] + per_m2[mid]) / 2.0
+        } else {
+            per_m2[mid]
+        };
+        let price = house.hprice as f64 / house.harea as f64;
+        if median <= 0.0
+            || (price <= median * Self::OUTLIER_FACTOR && price >= median / Self::OUTLIER_FACTOR)
+        {
+            return Ok(None);
+        }
+        Ok(Some(HouseFlagModel {
+            fno: 0,
+            hno: house.hno,
+            fkind: FlagKind::PriceOutlier,
+            fother: None,
+            fdetail: format!(
+                "每平米租金 {:.0} 元，为{}中位数 {:.0} 元的 {:.1} 倍",
+                price,
+                house.hdistrict,
+                median,
+                price / median
+            ),
+            fdate: Local::now().naive_local(),
+            fresolved: false,
+        }))
+    }
+
+    /// Runs every check on a house about to be saved, leaving out suspicions
+    /// staff already cleared for it. Nothing is stored.
+    pub async fn detect(
+        db: &DbConn,
+        house: &HouseListingModel,
+    ) -> Result<Vec<HouseFlagModel>, MXFError> {
+        let mut flags = Self::find_duplicates(db, house).await?;
+        flags.extend(Self::check_price(db, house).await?);
+        let cleared = HouseFlagEntity::find()
+            .filter(HouseFlagColumn::Hno.eq(house.hno))
+            .filter(HouseFlagColumn::Fresolved.eq(true))
+            .all(db)
+            .await?;
+        flags.retain(|f| !cleared.iter().any(|c| c.fkind == f.fkind && c.fother == f.fother));
+        Ok(flags)
+    }
+
+    /// Replaces the unresolved flags of `hno` with `flags`.
+    pub async fn save_flags(
+        db: &DbConn,
+        hno: u32,
+        flags: Vec<HouseFlagModel>,
+    ) -> Result<(), MXFError> {
+        HouseFlagEntity::delete_many()
+            .filter(HouseFlagColumn::Hno.eq(hno))
+            .filter(HouseFlagColumn::Fresolved.eq(false))
+            .exec(db)
+            .await?;
+        if flags.is_empty() {
+            return Ok(());
+        }
+        println!("House {} flagged: {:?}", hno, flags);
+        let flags = flags.into_iter().map(|f| HouseFlagActiveModel {
+            fno: NotSet,
+            hno: Set(hno),
+            fkind: Set(f.fkind),
+            fother: Set(f.fother),
+            fdetail: Set(f.fdetail),
+            fdate: Set(f.fdate),
+            fresolved: Set(false),
+        });
+        HouseFlagEntity::insert_many(flags).exec(db).await?;
+        Ok(())
+    }
+
+    /// Marks the flags of `hno` as handled once staff reviewed the house.
+    pub async fn resolve_flags(db: &DbConn, hno: u32) -> Result<(), MXFError> {
+        HouseFlagEntity::update_many()
+            .col_expr(HouseFlagColumn::Fresolved, Expr::value(true))
+            .filter(HouseFlagColumn::Hno.eq(hno))
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+
+    /// Unresolved flags of each house, in the order of `houses`.
+    pub async fn get_unresolved_flags(
+        &self,
+        db: &DbConn,
+        houses: &[HouseListingModel],
+    ) -> Result<Vec<Vec<HouseFlagModel>>, MXFError> {
+        let mut by_hno: HashMap<u32, Vec<HouseFlagModel>> = HashMap::new();
+        for flag in HouseFlagEntity::find()
+            .filter(HouseFlagColumn::Hno.is_in(houses.iter().map(|h| h.hno)))
+            .filter(HouseFlagColumn::Fresolved.eq(false))
+            .order_by_asc(HouseFlagColumn::Fno)
+            .all(db)
+            .await?
+        {
+            by_hno.entry(flag.hno).or_default().push(flag);
+        }
+        Ok(houses
+            .iter()
+            .map(|h| by_hno.remove(&h.hno).unwrap_or_default())
+            .collect())
+    }
+}
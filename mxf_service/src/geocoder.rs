@@ -0,0 +1,135 @@
+use mxf_entity::district_base;
+
+/// Gazetteer of districts and streets bundled into the binary, one
+/// `kind district name lat lng` record per tab-separated line.
+const GAZETTEER: &str = include_str!("../data/gazetteer.tsv");
+
+#[derive(Clone)]
+struct Place {
+    district: String,
+    name: String,
+    lat: f64,
+    lng: f64,
+}
+
+/// Offline geocoder resolving `hdistrict` + `haddr` to coordinates.
+#[derive(Clone)]
+pub struct Geocoder {
+    city: Option<Place>,
+    districts: Vec<Place>,
+    streets: Vec<Place>,
+}
+
+impl Geocoder {
+    pub fn bundled() -> Self {
+        Self::from_tsv(GAZETTEER)
+    }
+
+    pub fn from_tsv(tsv: &str) -> Self {
+        let mut city = None;
+        let mut districts = Vec::new();
+        let mut streets = Vec::new();
+        for line in tsv.lines() {
+            if line.starts_with('#') || line.trim().is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split('\t').collect();
+            if fields.len() != 5 {
+                println!("gazetteer: skipping malformed line {:?}", line);
+                continue;
+            }
+            let (Ok(lat), Ok(lng)) = (fields[3].parse::<f64>(), fields[4].parse::<f64>()) else {
+                println!("gazetteer: skipping malformed line {:?}", line);
+                continue;
+            };
+            let place = Place {
+                district: fields[1].to_string(),
+                name: fields[2].to_string(),
+                lat,
+                lng,
+            };
+            match fields[0] {
+                "city" => city = Some(place),
+                "district" => districts.push(place),
+                "street" => streets.push(place),
+                _ => println!("gazetteer: unknown kind {:?}", fields[0]),
+            }
+        }
+        Self { city, districts, streets }
+    }
+
+    /// City the gazetteer covers, if it names one.
+    pub fn city(&self) -> Option<&str> {
+        self.city.as_ref().map(|p| p.name.as_str())
+    }
+
+    pub fn districts(&self) -> impl Iterator<Item = &str> {
+        self.districts.iter().map(|p| p.name.as_str())
+    }
+
+    /// Streets as (district, name).
+    pub fn streets(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.streets.iter().map(|p| (p.district.as_str(), p.name.as_str()))
+    }
+
+    /// Districts are typed freely ("海淀" / "海淀区"), so compare without the suffix.
+    fn district_matches(place_district: &str, district: &str) -> bool {
+        let base = district_base(district);
+        !base.is_empty() && district_base(place_district) == base
+    }
+
+    /// Returns (lat, lng) of the most specific gazetteer entry found in the
+    /// address, falling back to the district centroid.
+    pub fn geocode(&self, district: &str, addr: &str) -> Option<(f64, f64)> {
+        let street = self
+            .streets
+            .iter()
+            .filter(|p| addr.contains(p.name.as_str()))
+            .max_by_key(|p| {
+                (
+                    Self::district_matches(&p.district, district),
+                    p.name.chars().count(),
+                )
+            });
+        if let Some(p) = street {
+            return Some((p.lat, p.lng));
+        }
+        self.districts
+            .iter()
+            .find(|p| Self::district_matches(&p.name, district) || addr.contains(p.name.as_str()))
+            .map(|p| (p.lat, p.lng))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TSV: &str = "# kind\tdistrict\tname\tlat\tlng
+city\t北京市\t北京市\t39.9\t116.4
+district\t海淀区\t海淀区\t39.96\t116.30
+district\t朝阳区\t朝阳区\t39.92\t116.49
+street\t海淀区\t中关村\t39.98\t116.31
+street\t海淀区\t中关村大街\t39.97\t116.32
+street\t海淀区\t建国路\t1.0\t1.0
+street\t朝阳区\t建国路\t39.91\t116.47
+street\t朝阳区\t坏行\tnot a number\t116.0
+";
+
+    #[test]
+    fn geocode_prefers_specific_entries() {
+        let geocoder = Geocoder::from_tsv(TSV);
+        // The longest street name found wins
+        assert_eq!(geocoder.geocode("海淀区", "中关村大街 1 号"), Some((39.97, 116.32)));
+        // Streets of the same name are told apart by district, with or without "区"
+        assert_eq!(geocoder.geocode("朝阳", "建国路 88 号"), Some((39.91, 116.47)));
+        assert_eq!(geocoder.geocode("海淀区", "建国路 88 号"), Some((1.0, 1.0)));
+        // Unknown streets fall back to the district centroid
+        assert_eq!(geocoder.geocode("朝阳区", "不存在路 1 号"), Some((39.92, 116.49)));
+        assert_eq!(geocoder.geocode("", "海淀区某小区"), Some((39.96, 116.30)));
+        // Malformed lines are skipped
+        assert_eq!(geocoder.geocode("朝阳区", "坏行"), Some((39.92, 116.49)));
+        assert_eq!(geocoder.geocode("通州区", "某路"), None);
+        assert_eq!(geocoder.city(), Some("北京市"));
+    }
+}
@@ -1,21 +1,94 @@
-use mini_moka::sync::Cache;
+use chrono::Local;
+use sea_orm::sea_query::{Expr, OnConflict};
 use sea_orm::*;
-use std::time::Duration;
+use std::collections::HashMap;
 
+use mxf_entity::search_text::segment;
 use mxf_entity::{
-    HouseFilter, HouseListingColumn, HouseListingEntity, HouseListingModel, HouseListingActiveModel, MXFError, ListStatus
+    Cursor, CursorPage, FavoriteColumn, FavoriteEntity, HouseFilter, HouseFlagColumn, HouseFlagEntity, HouseListingColumn, HouseListingEntity, HouseListingModel, HouseListingActiveModel,
+    HouseRevisionColumn, HouseRevisionEntity, HouseStatsColumn, HouseStatsEntity,
+    ListingInput, MXFError, ListStatus, MapMarker, NotificationColumn, NotificationEntity,
+    NotificationType, OrderColumn, OrderEntity, PriceHistoryColumn, PriceHistoryEntity,
+    SavedSearchAlertColumn, SavedSearchAlertEntity, SavedSearchMatchColumn, SavedSearchMatchEntity,
+    SearchIndexActiveModel, SearchIndexColumn, SearchIndexEntity,
 };
 
+use crate::geocoder::Geocoder;
+use crate::search_cache::SearchCache;
+use crate::{
+    FavoriteService, FraudService, NotificationService, OrderService, PriceHistoryService,
+    AlertQueue, RegionService, RevisionService,
+};
+
+#[derive(Clone)]
 pub struct HouseService {
-    num_pages_cache: Cache<String, u64>,
+    search_cache: SearchCache,
+    geocoder: Geocoder,
+    region_service: RegionService,
+    alert_queue: AlertQueue,
 }
 
 impl HouseService {
+    const MAX_MAP_ZOOM: u8 = 20;
+
     pub fn init() -> Self {
-        let num_pages_cache = Cache::builder()
-            .time_to_live(Duration::from_secs(30 * 60))
-            .build();
-        return HouseService { num_pages_cache };
+        let search_cache = SearchCache::new();
+        let geocoder = Geocoder::bundled();
+        HouseService {
+            search_cache,
+            geocoder,
+            region_service: RegionService::init(),
+            alert_queue: AlertQueue::default(),
+        }
+    }
+
+    pub fn search_cache(&self) -> &SearchCache {
+        &self.search_cache
+    }
+
+    /// Region tree houses are filed under, sharing its cache with clones.
+    pub fn region_service(&self) -> &RegionService {
+        &self.region_service
+    }
+
+    /// Fills the region tree from the gazetteer and, when that added regions,
+    /// files the houses not filed under one yet.
+    pub async fn seed_regions(&self, db: &DbConn) -> Result<(), MXFError> {
+        if !self.region_service.seed(db, &self.geocoder).await? {
+            return Ok(());
+        }
+        let unfiled: Vec<(u32, String, String)> = HouseListingEntity::find()
+            .select_only()
+            .column(HouseListingColumn::Hno)
+            .column(HouseListingColumn::Hdistrict)
+            .column(HouseListingColumn::Haddr)
+            .filter(HouseListingColumn::Hregion.is_null())
+            .into_tuple()
+            .all(db)
+            .await?;
+        for (hno, district, addr) in unfiled {
+            let Some(region) = self.region_service.resolve(db, &district, &addr).await? else {
+                continue;
+            };
+            HouseListingEntity::update_many()
+                .col_expr(HouseListingColumn::Hregion, Expr::value(region))
+                .filter(HouseListingColumn::Hno.eq(hno))
+                .exec(db)
+                .await?;
+        }
+        self.listings_changed();
+        Ok(())
+    }
+
+    /// Houses to match against the saved searches once written.
+    pub fn alert_queue(&self) -> &AlertQueue {
+        &self.alert_queue
+    }
+
+    /// To be called after any write to `house_listings`, so that searches
+    /// see it.
+    pub fn listings_changed(&self) {
+        self.search_cache.invalidate();
     }
 
     pub async fn get_house_by_hno(
@@ -32,6 +105,23 @@ impl HouseService {
             )))
     }
 
+    /// House `hno` if `uno` may look at it: listed houses are public, others
+    /// only shown to their landlord and staff, and deleted ones to staff.
+    pub async fn get_visible_house(
+        &self,
+        db: &DbConn,
+        hno: u32,
+        uno: Option<u32>,
+        staff: bool,
+    ) -> Result<HouseListingModel, MXFError> {
+        let house = self.get_house_by_hno(db, hno).await?;
+        if house.is_visible_to(uno, staff) {
+            Ok(house)
+        } else {
+            Err(MXFError::HouseUnavailable(hno))
+        }
+    }
+
     pub async fn get_houses_by_landlore(
         &self,
         db: &DbConn,
@@ -52,11 +142,35 @@ impl HouseService {
             .order_by_desc(HouseListingColumn::Hno)
             .one(db)
             .await?
-            .ok_or(MXFError::UnknownError(format!("No houses found")))?
+            .ok_or(MXFError::UnknownError("No houses found".to_string()))?
             .hno;
         Ok(hno + 1)
     }
 
+    /// All houses matching the filter, most relevant first.
+    pub fn search_select(house_filter: HouseFilter<'_>, listed_only: bool) -> Select<HouseListingEntity> {
+        let mut select = HouseListingEntity::find().filter(
+            Condition::from(house_filter)
+                .add_option(listed_only.then_some(HouseListingColumn::Hunlisted.eq(ListStatus::Listed))),
+        );
+        if let Some((key, order)) = house_filter.sort_key() {
+            select = select.order_by(key, order);
+        }
+        select.order_by_asc(HouseListingColumn::Hno)
+    }
+
+    /// A landlord's whole portfolio; deleted houses only on request.
+    pub fn portfolio_select(hlandlore: u32, include_deleted: bool) -> Select<HouseListingEntity> {
+        HouseListingEntity::find()
+            .filter(HouseListingColumn::Hlandlore.eq(hlandlore))
+            .filter(
+                Condition::all().add_option(
+                    (!include_deleted).then_some(HouseListingColumn::Hunlisted.ne(ListStatus::Deleted)),
+                ),
+            )
+            .order_by_asc(HouseListingColumn::Hno)
+    }
+
     /// If ok, returns (post models, num pages).
     pub async fn get_houses_in_page(
         &self,
@@ -65,31 +179,104 @@ impl HouseService {
         posts_per_page: u8,
         listed_only: bool,
     ) -> Result<(Vec<HouseListingModel>, u64), MXFError> {
-        let filter_string = house_filter.to_string();
         let posts_per_page = posts_per_page as u64;
-        println!(
-            "filter {}: {:?} {:?}",
-            filter_string,
+        println!("filter {}: {:?}", house_filter, Condition::from(house_filter));
+        let cache = &self.search_cache;
+        let page = house_filter.page - 1;
+        let paginator = Self::search_select(house_filter, listed_only).paginate(db, posts_per_page);
+        // The first page is what nearly every search shows, so it is kept too
+        if page == 0 {
+            if let Some(cached) = cache.first_page(house_filter, posts_per_page, listed_only) {
+                return Ok(cached);
+            }
+        } else if let Some(num_pages) = cache.num_pages(house_filter, posts_per_page, listed_only) {
+            return Ok((paginator.fetch_page(page).await?, num_pages));
+        }
+
+        let generation = cache.generation();
+        let num_pages = paginator.num_pages().await?;
+        let houses = paginator.fetch_page(page).await?;
+        let first_page = (page == 0).then_some(houses.as_slice());
+        cache.insert_search(
+            generation,
             house_filter,
-            Condition::from(house_filter)
-        );
-        let paginator = HouseListingEntity::find()
-            .filter(
-                Condition::from(house_filter)
-                    .add_option(listed_only.then_some(HouseListingColumn::Hunlisted.eq(ListStatus::Listed)))
-            )
-            .paginate(db, posts_per_page);
-        let do_insert = !self.num_pages_cache.contains_key::<String>(&filter_string);
-        if do_insert {
-            self.num_pages_cache
-                .insert(filter_string.clone(), paginator.num_pages().await?);
-        }
-        let num_pages = self.num_pages_cache.get::<String>(&filter_string).unwrap();
-        // let num_pages = paginator.num_pages().await?;
-        Ok((
-            paginator.fetch_page(house_filter.page - 1).await?,
+            posts_per_page,
+            listed_only,
             num_pages,
-        ))
+            first_page,
+        );
+        Ok((houses, num_pages))
+    }
+
+    /// Up to `limit` houses after `cursor` in search order, with the cursor
+    /// continuing after them. Unlike numbered pages, houses listed meanwhile
+    /// do not shift the results.
+    pub async fn get_houses_after(
+        &self,
+        db: &DbConn,
+        house_filter: HouseFilter<'_>,
+        cursor: Option<Cursor>,
+        limit: u64,
+        listed_only: bool,
+    ) -> Result<CursorPage<HouseListingModel>, MXFError> {
+        let mut select = Self::search_select(house_filter, listed_only);
+        if let Some(cursor) = cursor {
+            select = select.filter(cursor.after(house_filter.sort_key(), HouseListingColumn::Hno));
+        }
+        let mut houses = select.limit(limit + 1).all(db).await?;
+        let mut next_cursor = None;
+        if houses.len() as u64 > limit {
+            houses.truncate(limit as usize);
+            if let Some(last) = houses.last() {
+                next_cursor = Some(self.cursor_of(db, house_filter, last).await?.to_string());
+            }
+        }
+        Ok(CursorPage { items: houses, next_cursor, prev_cursor: None })
+    }
+
+    /// Cursor continuing a search after `house`.
+    pub async fn cursor_of(
+        &self,
+        db: &DbConn,
+        house_filter: HouseFilter<'_>,
+        house: &HouseListingModel,
+    ) -> Result<Cursor, MXFError> {
+        let key = match house_filter.sort_key() {
+            Some((key, _)) => HouseListingEntity::find_by_id(house.hno)
+                .select_only()
+                .column_as(Expr::cust_with_expr("CAST($1 AS DOUBLE)", key), "sort_key")
+                .into_tuple::<Option<f64>>()
+                .one(db)
+                .await?
+                .flatten(),
+            None => None,
+        };
+        Ok(Cursor { key, id: house.hno })
+    }
+
+    /// Checks a posted listing form, districts against the region tree.
+    pub async fn validate_listing(
+        &self,
+        db: &DbConn,
+        input: ListingInput,
+    ) -> Result<HouseListingModel, MXFError> {
+        let districts = self.district_names(db).await?;
+        input.validate(&districts.iter().map(String::as_str).collect::<Vec<_>>())
+    }
+
+    /// Checks the house described for a rent estimate.
+    pub async fn validate_estimate(
+        &self,
+        db: &DbConn,
+        input: ListingInput,
+    ) -> Result<HouseListingModel, MXFError> {
+        let districts = self.district_names(db).await?;
+        input.validate_for_estimate(&districts.iter().map(String::as_str).collect::<Vec<_>>())
+    }
+
+    /// Known district names, for forms to offer.
+    pub async fn district_names(&self, db: &DbConn) -> Result<Vec<String>, MXFError> {
+        self.region_service.district_names(db).await
     }
 
     pub async fn new_house(
@@ -98,10 +285,40 @@ impl HouseService {
         house_listing: HouseListingModel,
         uno: u32,
     ) -> Result<u32, MXFError> {
+        let house_listing = house_listing.with_layout().with_unit_price();
+        let coordinates = self.geocoder.geocode(&house_listing.hdistrict, &house_listing.haddr);
+        let region =
+            self.region_service.resolve(db, &house_listing.hdistrict, &house_listing.haddr).await?;
+        // New houses go public only after review; landlords may keep a draft.
+        let status = match house_listing.hunlisted {
+            ListStatus::Draft => ListStatus::Draft,
+            _ => ListStatus::PendingReview,
+        };
+        let flags = if status == ListStatus::PendingReview {
+            let mut candidate = house_listing.clone();
+            candidate.hno = 0;
+            candidate.hlandlore = uno;
+            FraudService::detect(db, &candidate).await?
+        } else {
+            Vec::new()
+        };
         let mut house: HouseListingActiveModel = house_listing.into();
         house.hno = NotSet;
         house.hlandlore = Set(uno);
+        house.hunlisted = Set(status);
+        house.hreason = Set(None);
+        house.hlat = Set(coordinates.map(|c| c.0));
+        house.hlng = Set(coordinates.map(|c| c.1));
+        house.hregion = Set(region);
+        house.hlisted_at = Set(None);
+        house.hrefreshed_at = Set(Some(Local::now().naive_local()));
         let res = HouseListingEntity::insert(house).exec(db).await?;
+        self.listings_changed();
+        let house = self.get_house_by_hno(db, res.last_insert_id).await?;
+        self.index_house(db, &house).await?;
+        PriceHistoryService::record_price(db, house.hno, house.hprice).await?;
+        RevisionService::record(db, None, &house, Some(uno)).await?;
+        FraudService::save_flags(db, house.hno, flags).await?;
         Ok(res.last_insert_id)
     }
 
@@ -120,19 +337,573 @@ impl HouseService {
         house_listing: HouseListingModel,
         uno: u32,
     ) -> Result<u32, MXFError> {
-        self.verify_landlore(db, house_listing.hno, uno).await?;
+        let house_listing = house_listing.with_layout().with_unit_price();
+        let before = self.get_house_by_hno(db, house_listing.hno).await?;
+        if before.hlandlore != uno {
+            return Err(MXFError::NotLandlore(uno));
+        }
+        if before.hunlisted.is_archived() {
+            return Err(MXFError::HouseArchived(before.hno));
+        }
+        let coordinates = self.geocoder.geocode(&house_listing.hdistrict, &house_listing.haddr);
+        let region =
+            self.region_service.resolve(db, &house_listing.hdistrict, &house_listing.haddr).await?;
+        let mut status = Self::next_status(&before, &house_listing);
+        // Flagged houses leave the public listing until staff have looked at them.
+        let flags = if status == ListStatus::Listed || status == ListStatus::PendingReview {
+            let mut candidate = house_listing.clone();
+            candidate.hlandlore = uno;
+            FraudService::detect(db, &candidate).await?
+        } else {
+            Vec::new()
+        };
+        if !flags.is_empty() {
+            status = ListStatus::PendingReview;
+        }
         let mut house: HouseListingActiveModel = house_listing.into();
         house.reset(HouseListingColumn::Hdistrict);
         house.reset(HouseListingColumn::Haddr);
         house.reset(HouseListingColumn::Hlo);
+        house.reset(HouseListingColumn::Hbedrooms);
+        house.reset(HouseListingColumn::HlivingRooms);
+        house.reset(HouseListingColumn::Hbathrooms);
         house.reset(HouseListingColumn::Hflr);
         house.reset(HouseListingColumn::Harea);
         house.reset(HouseListingColumn::Hprice);
+        house.reset(HouseListingColumn::HunitPrice);
         house.reset(HouseListingColumn::Hsuite);
-        house.reset(HouseListingColumn::Hunlisted);
+        house.reset(HouseListingColumn::Hdesc);
+        house.hunlisted = Set(status);
+        // Reasons given for the previous status, by staff or by expiry
+        if status != before.hunlisted {
+            house.hreason = Set(None);
+        }
+        house.hlat = Set(coordinates.map(|c| c.0));
+        house.hlng = Set(coordinates.map(|c| c.1));
+        house.hregion = Set(region);
+        // Any edit by the landlord shows the listing is still looked after
+        house.hrefreshed_at = Set(Some(Local::now().naive_local()));
+        Self::stamp_listed(before.hunlisted, status, &mut house);
         println!("To Modify: {}, {:?}", uno, house);
         let house = HouseListingEntity::update(house).exec(db).await?;
+        self.listings_changed();
         println!("Modify house by {}: {:?}", uno, house);
+        self.index_house(db, &house).await?;
+        if before.hprice != house.hprice {
+            PriceHistoryService::record_price(db, house.hno, house.hprice).await?;
+        }
+        RevisionService::record(db, Some(&before), &house, Some(uno)).await?;
+        if status == ListStatus::Listed || status == ListStatus::PendingReview {
+            FraudService::save_flags(db, house.hno, flags).await?;
+        }
+        self.notify_changes(db, &before, &house).await?;
+        self.alert_queue.push(house.hno);
         Ok(house.hno)
     }
+
+    /// Starts a new lifetime for a house going public, so that it does not
+    /// expire for the time it spent offline.
+    pub(crate) fn stamp_listed(
+        before: ListStatus,
+        after: ListStatus,
+        house: &mut HouseListingActiveModel,
+    ) {
+        if after == ListStatus::Listed && before != ListStatus::Listed {
+            let now = Local::now().naive_local();
+            house.hlisted_at = Set(Some(now));
+            house.hrefreshed_at = Set(Some(now));
+        }
+    }
+
+    /// Status a landlord's edit leads to. Houses that were never approved can
+    /// only be kept as a draft or submitted for review. Approved houses may be
+    /// listed or unlisted freely, but go back to review when their price,
+    /// address or layout change.
+    fn next_status(before: &HouseListingModel, after: &HouseListingModel) -> ListStatus {
+        let reviewed_fields_changed = before.hprice != after.hprice
+            || before.hdistrict != after.hdistrict
+            || before.haddr != after.haddr
+            || before.hlo != after.hlo;
+        if !before.hunlisted.is_approved() {
+            match after.hunlisted {
+                ListStatus::Draft => ListStatus::Draft,
+                _ => ListStatus::PendingReview,
+            }
+        } else if reviewed_fields_changed {
+            ListStatus::PendingReview
+        } else {
+            match after.hunlisted {
+                ListStatus::Unlisted => ListStatus::Unlisted,
+                _ => ListStatus::Listed,
+            }
+        }
+    }
+
+    pub async fn get_houses_pending_review(
+        &self,
+        db: &DbConn,
+    ) -> Result<Vec<HouseListingModel>, MXFError> {
+        HouseListingEntity::find()
+            .filter(HouseListingColumn::Hunlisted.eq(ListStatus::PendingReview))
+            .order_by_asc(HouseListingColumn::Hno)
+            .all(db)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    /// Approves or rejects a house waiting for review and tells the landlord.
+    pub async fn review_house(
+        &self,
+        db: &DbConn,
+        hno: u32,
+        approve: bool,
+        reason: Option<String>,
+        uno: u32,
+    ) -> Result<(), MXFError> {
+        let house = self.get_house_by_hno(db, hno).await?;
+        if house.hunlisted != ListStatus::PendingReview {
+            return Err(MXFError::NotPendingReview(hno));
+        }
+        let reason = reason.filter(|r| !r.trim().is_empty());
+        let content = if approve {
+            "房源已通过审核并上架".to_string()
+        } else {
+            format!("房源未通过审核：{}", reason.as_deref().unwrap_or("未说明原因"))
+        };
+        let mut reviewed: HouseListingActiveModel = house.clone().into();
+        let status = if approve {
+            ListStatus::Listed
+        } else {
+            ListStatus::Rejected
+        };
+        reviewed.hunlisted = Set(status);
+        Self::stamp_listed(house.hunlisted, status, &mut reviewed);
+        reviewed.hreason = Set(if approve { None } else { reason });
+        let reviewed = reviewed.update(db).await?;
+        self.listings_changed();
+        println!("Review house {}: {:?}", hno, reviewed.hunlisted);
+        RevisionService::record(db, Some(&house), &reviewed, Some(uno)).await?;
+        FraudService::resolve_flags(db, hno).await?;
+
+        NotificationService::notify_all(
+            db,
+            &[reviewed.hlandlore],
+            hno,
+            NotificationType::ReviewResult,
+            &content,
+        )
+        .await?;
+        if approve {
+            self.alert_queue.push(hno);
+        }
+        Ok(())
+    }
+
+    /// Refuses while the house has an active lease or a pending request.
+    async fn verify_not_in_use(&self, db: &impl ConnectionTrait, hno: u32) -> Result<(), MXFError> {
+        let orders = OrderEntity::find()
+            .filter(OrderColumn::Hno.eq(hno))
+            .all(db)
+            .await?;
+        if OrderService::is_occupied(&orders, Local::now().naive_local()) {
+            Err(MXFError::HouseInUse(hno))
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn set_status(
+        &self,
+        db: &impl ConnectionTrait,
+        house: HouseListingModel,
+        status: ListStatus,
+        editor: u32,
+    ) -> Result<HouseListingModel, MXFError> {
+        let mut updated: HouseListingActiveModel = house.clone().into();
+        updated.hunlisted = Set(status);
+        let updated = updated.update(db).await?;
+        RevisionService::record(db, Some(&house), &updated, Some(editor)).await?;
+        Ok(updated)
+    }
+
+    /// The house, locked until `txn` ends so that no order slips in while
+    /// its status changes.
+    async fn lock_house(
+        &self,
+        txn: &DatabaseTransaction,
+        hno: u32,
+    ) -> Result<HouseListingModel, MXFError> {
+        HouseListingEntity::find_by_id(hno)
+            .lock_exclusive()
+            .one(txn)
+            .await?
+            .ok_or(MXFError::UnknownError(format!("House with hno {} not found", hno)))
+    }
+
+    /// Archives a house of landlord `uno`, or soft-deletes it when `delete`
+    /// is set. Either way the row stays so that its orders remain readable.
+    pub async fn archive_house(
+        &self,
+        db: &DbConn,
+        hno: u32,
+        uno: u32,
+        delete: bool,
+    ) -> Result<(), MXFError> {
+        let txn = db.begin().await?;
+        let house = self.lock_house(&txn, hno).await?;
+        if house.hlandlore != uno {
+            return Err(MXFError::NotLandlore(uno));
+        }
+        self.verify_not_in_use(&txn, hno).await?;
+        let was_listed = house.hunlisted == ListStatus::Listed;
+        let status = if delete {
+            ListStatus::Deleted
+        } else {
+            ListStatus::Archived
+        };
+        self.set_status(&txn, house, status, uno).await?;
+        txn.commit().await?;
+        self.listings_changed();
+        println!("Archive house {} by {}: {:?}", hno, uno, status);
+        if was_listed {
+            FavoriteService::notify_watchers(db, hno, NotificationType::Unlisted, "房源已下架").await?;
+        }
+        Ok(())
+    }
+
+    /// Brings an archived or deleted house back for staff to review before it
+    /// is listed again.
+    pub async fn restore_house(&self, db: &DbConn, hno: u32, uno: u32) -> Result<(), MXFError> {
+        let txn = db.begin().await?;
+        let house = self.lock_house(&txn, hno).await?;
+        if !house.hunlisted.is_archived() {
+            return Ok(());
+        }
+        self.set_status(&txn, house, ListStatus::PendingReview, uno).await?;
+        txn.commit().await?;
+        self.listings_changed();
+        Ok(())
+    }
+
+    /// Puts the house back as it was after revision `rno`, recording the
+    /// revert as a new revision by `uno`. The status follows the same rules as
+    /// a landlord's edit. Archived houses must be restored first.
+    pub async fn revert_house(&self, db: &DbConn, rno: u32, uno: u32) -> Result<u32, MXFError> {
+        // Snapshots older than the room count columns get them from `hlo`
+        let snapshot = RevisionService::get_snapshot(db, rno).await?.with_layout();
+        let before = self.get_house_by_hno(db, snapshot.hno).await?;
+        if before.hunlisted.is_archived() {
+            return Err(MXFError::HouseArchived(before.hno));
+        }
+        let coordinates = self.geocoder.geocode(&snapshot.hdistrict, &snapshot.haddr);
+        let region = self.region_service.resolve(db, &snapshot.hdistrict, &snapshot.haddr).await?;
+        let mut status = Self::reverted_status(&before, &snapshot);
+        let flags = if status == ListStatus::Listed || status == ListStatus::PendingReview {
+            FraudService::detect(db, &snapshot).await?
+        } else {
+            Vec::new()
+        };
+        if !flags.is_empty() {
+            status = ListStatus::PendingReview;
+        }
+        let mut house: HouseListingActiveModel = before.clone().into();
+        house.hunit_price = Set(snapshot.unit_price());
+        house.hdistrict = Set(snapshot.hdistrict);
+        house.haddr = Set(snapshot.haddr);
+        house.hlo = Set(snapshot.hlo);
+        house.hbedrooms = Set(snapshot.hbedrooms);
+        house.hliving_rooms = Set(snapshot.hliving_rooms);
+        house.hbathrooms = Set(snapshot.hbathrooms);
+        house.hflr = Set(snapshot.hflr);
+        house.harea = Set(snapshot.harea);
+        house.hprice = Set(snapshot.hprice);
+        house.hsuite = Set(snapshot.hsuite);
+        house.hdesc = Set(snapshot.hdesc);
+        house.hunlisted = Set(status);
+        Self::stamp_listed(before.hunlisted, status, &mut house);
+        if status != before.hunlisted {
+            house.hreason = Set(None);
+        }
+        house.hlat = Set(coordinates.map(|c| c.0));
+        house.hlng = Set(coordinates.map(|c| c.1));
+        house.hregion = Set(region);
+        let house = house.update(db).await?;
+        self.listings_changed();
+        println!("Revert house {} to revision {} by {}", house.hno, rno, uno);
+        self.index_house(db, &house).await?;
+        if before.hprice != house.hprice {
+            PriceHistoryService::record_price(db, house.hno, house.hprice).await?;
+        }
+        RevisionService::record(db, Some(&before), &house, Some(uno)).await?;
+        if status == ListStatus::Listed || status == ListStatus::PendingReview {
+            FraudService::save_flags(db, house.hno, flags).await?;
+        }
+        self.notify_changes(db, &before, &house).await?;
+        self.alert_queue.push(house.hno);
+        Ok(house.hno)
+    }
+
+    /// Status a revert to `snapshot` leads to. The revision's fields come
+    /// back as if the landlord had typed them, but the current status is
+    /// kept, so that reviews and archiving cannot be undone this way.
+    fn reverted_status(before: &HouseListingModel, snapshot: &HouseListingModel) -> ListStatus {
+        let reverted = HouseListingModel {
+            hunlisted: before.hunlisted,
+            ..snapshot.clone()
+        };
+        Self::next_status(before, &reverted)
+    }
+
+    /// Removes an archived or deleted house and everything pointing at it,
+    /// its orders and history included.
+    pub async fn purge_house(&self, db: &DbConn, hno: u32) -> Result<(), MXFError> {
+        let house = self.get_house_by_hno(db, hno).await?;
+        if !house.hunlisted.is_archived() {
+            return Err(MXFError::UnknownError(format!(
+                "House with hno {} must be archived before purging",
+                hno
+            )));
+        }
+        let txn = db.begin().await?;
+        SearchIndexEntity::delete_by_id(hno).exec(&txn).await?;
+        FavoriteEntity::delete_many()
+            .filter(FavoriteColumn::Hno.eq(hno))
+            .exec(&txn)
+            .await?;
+        SavedSearchMatchEntity::delete_many()
+            .filter(SavedSearchMatchColumn::Hno.eq(hno))
+            .exec(&txn)
+            .await?;
+        SavedSearchAlertEntity::delete_many()
+            .filter(SavedSearchAlertColumn::Hno.eq(hno))
+            .exec(&txn)
+            .await?;
+        NotificationEntity::delete_many()
+            .filter(NotificationColumn::Hno.eq(hno))
+            .exec(&txn)
+            .await?;
+        OrderEntity::delete_many()
+            .filter(OrderColumn::Hno.eq(hno))
+            .exec(&txn)
+            .await?;
+        HouseRevisionEntity::delete_many()
+            .filter(HouseRevisionColumn::Hno.eq(hno))
+            .exec(&txn)
+            .await?;
+        HouseStatsEntity::delete_many()
+            .filter(HouseStatsColumn::Hno.eq(hno))
+            .exec(&txn)
+            .await?;
+        PriceHistoryEntity::delete_many()
+            .filter(PriceHistoryColumn::Hno.eq(hno))
+            .exec(&txn)
+            .await?;
+        HouseFlagEntity::delete_many()
+            .filter(HouseFlagColumn::Hno.eq(hno))
+            .exec(&txn)
+            .await?;
+        HouseListingEntity::delete_by_id(hno).exec(&txn).await?;
+        txn.commit().await?;
+        self.listings_changed();
+        println!("Purge house {}", hno);
+        Ok(())
+    }
+
+    pub async fn get_archived_houses(&self, db: &DbConn) -> Result<Vec<HouseListingModel>, MXFError> {
+        HouseListingEntity::find()
+            .filter(HouseListingColumn::Hunlisted.is_in([ListStatus::Archived, ListStatus::Deleted]))
+            .order_by_asc(HouseListingColumn::Hno)
+            .all(db)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    /// Tells the users who favorited a house about changes they care about.
+    /// Houses in review or taken off the site are not announced.
+    async fn notify_changes(
+        &self,
+        db: &DbConn,
+        before: &HouseListingModel,
+        after: &HouseListingModel,
+    ) -> Result<(), MXFError> {
+        if !after.hunlisted.is_approved() {
+            return Ok(());
+        }
+        if before.hprice != after.hprice {
+            let mut content = format!("租赁价格由 {} 元/月 调整为 {} 元/月", before.hprice, after.hprice);
+            if after.hprice < before.hprice {
+                let percent = (before.hprice - after.hprice) * 100 / before.hprice.max(1);
+                content.push_str(&format!("，降价 {}%", percent));
+                if PriceHistoryService::lowest_price(db, after.hno).await? == Some(after.hprice) {
+                    content.push_str("，为历史最低价");
+                }
+            }
+            FavoriteService::notify_watchers(db, after.hno, NotificationType::PriceChanged, &content)
+                .await?;
+        }
+        match (before.hunlisted, after.hunlisted) {
+            (ListStatus::Listed, ListStatus::Unlisted) => {
+                FavoriteService::notify_watchers(db, after.hno, NotificationType::Unlisted, "房源已下架")
+                    .await?
+            }
+            (ListStatus::Unlisted, ListStatus::Listed) => {
+                FavoriteService::notify_watchers(
+                    db,
+                    after.hno,
+                    NotificationType::AvailableAgain,
+                    "房源已重新上架",
+                )
+                .await?
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Groups the houses matching `house_filter` into grid cells sized for the
+    /// given map zoom level, one marker per non-empty cell.
+    pub async fn get_map_markers(
+        &self,
+        db: &DbConn,
+        house_filter: HouseFilter<'_>,
+        zoom: u8,
+        listed_only: bool,
+    ) -> Result<Vec<MapMarker>, MXFError> {
+        let points: Vec<(u32, Option<f64>, Option<f64>)> = HouseListingEntity::find()
+            .select_only()
+            .column(HouseListingColumn::Hno)
+            .column(HouseListingColumn::Hlat)
+            .column(HouseListingColumn::Hlng)
+            .filter(
+                Condition::from(house_filter)
+                    .add(HouseListingColumn::Hlat.is_not_null())
+                    .add(HouseListingColumn::Hlng.is_not_null())
+                    .add_option(listed_only.then_some(HouseListingColumn::Hunlisted.eq(ListStatus::Listed)))
+            )
+            .into_tuple()
+            .all(db)
+            .await?;
+
+        // A cell is a quarter of a 256px map tile at this zoom level.
+        let cell = 90.0 / f64::powi(2.0, zoom.min(Self::MAX_MAP_ZOOM) as i32);
+        let mut cells: HashMap<(i64, i64), (f64, f64, u32, u32)> = HashMap::new();
+        for (hno, lat, lng) in points {
+            let (Some(lat), Some(lng)) = (lat, lng) else {
+                continue;
+            };
+            let key = ((lat / cell).floor() as i64, (lng / cell).floor() as i64);
+            let entry = cells.entry(key).or_insert((0.0, 0.0, 0, hno));
+            entry.0 += lat;
+            entry.1 += lng;
+            entry.2 += 1;
+        }
+        Ok(cells
+            .into_values()
+            .map(|(lat_sum, lng_sum, count, hno)| MapMarker {
+                lat: lat_sum / count as f64,
+                lng: lng_sum / count as f64,
+                count,
+                hno: (count == 1).then_some(hno),
+            })
+            .collect())
+    }
+
+    /// Geocodes houses stored before coordinates were tracked. Returns the
+    /// number of houses updated.
+    pub async fn geocode_missing(&self, db: &DbConn) -> Result<u64, MXFError> {
+        let houses = HouseListingEntity::find()
+            .filter(HouseListingColumn::Hlat.is_null())
+            .all(db)
+            .await?;
+        let mut updated = 0;
+        for house in houses {
+            if let Some((lat, lng)) = self.geocoder.geocode(&house.hdistrict, &house.haddr) {
+                let mut house: HouseListingActiveModel = house.into();
+                house.hlat = Set(Some(lat));
+                house.hlng = Set(Some(lng));
+                house.update(db).await?;
+                updated += 1;
+            }
+        }
+        if updated > 0 {
+            self.listings_changed();
+        }
+        Ok(updated)
+    }
+
+    /// Writes the segmented searchable text of a house to the search index.
+    async fn index_house(&self, db: &DbConn, house: &HouseListingModel) -> Result<(), MXFError> {
+        let content = segment(&format!(
+            "{} {} {} {} {}",
+            house.hdistrict, house.haddr, house.hlo, house.hsuite, house.hdesc
+        ));
+        let entry = SearchIndexActiveModel {
+            hno: Set(house.hno),
+            content: Set(content),
+        };
+        SearchIndexEntity::insert(entry)
+            .on_conflict(
+                OnConflict::column(SearchIndexColumn::Hno)
+                    .update_column(SearchIndexColumn::Content)
+                    .to_owned(),
+            )
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+
+    /// Re-indexes every house. Returns the number of houses indexed.
+    pub async fn rebuild_search_index(&self, db: &DbConn) -> Result<u64, MXFError> {
+        let houses = HouseListingEntity::find().all(db).await?;
+        let mut indexed = 0;
+        for house in houses {
+            self.index_house(db, &house).await?;
+            indexed += 1;
+        }
+        Ok(indexed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mxf_entity::ListingInput;
+
+    fn house(hprice: u32, hunlisted: ListStatus) -> HouseListingModel {
+        let house = ListingInput {
+            hno: 1,
+            hdistrict: "海淀区".to_string(),
+            haddr: "中关村大街 1 号".to_string(),
+            hlo: "2室1厅".to_string(),
+            hflr: Some(5.0),
+            harea: Some(50.0),
+            hprice: Some(hprice as f64),
+            ..Default::default()
+        }
+        .validate(&["海淀区"])
+        .unwrap();
+        HouseListingModel { hunlisted, ..house }
+    }
+
+    #[test]
+    fn reverted_status_keeps_current_status() {
+        let reverted = |before, snapshot| HouseService::reverted_status(&before, &snapshot);
+        // Only the status differs: the revert does not change it
+        let listed = house(5000, ListStatus::Listed);
+        assert_eq!(reverted(listed.clone(), house(5000, ListStatus::Unlisted)), ListStatus::Listed);
+        let unlisted = house(5000, ListStatus::Unlisted);
+        assert_eq!(reverted(unlisted, house(5000, ListStatus::Listed)), ListStatus::Unlisted);
+        // An older price needs another review
+        assert_eq!(reverted(listed, house(4000, ListStatus::Listed)), ListStatus::PendingReview);
+        // Houses never approved cannot be listed by going back to a listed revision
+        for status in [ListStatus::PendingReview, ListStatus::Rejected] {
+            let before = house(5000, status);
+            assert_eq!(
+                reverted(before, house(5000, ListStatus::Listed)),
+                ListStatus::PendingReview
+            );
+        }
+        let draft = house(5000, ListStatus::Draft);
+        assert_eq!(reverted(draft, house(5000, ListStatus::Listed)), ListStatus::Draft);
+    }
 }
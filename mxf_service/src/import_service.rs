@@ -0,0 +1,391 @@
+use calamine::{open_workbook_from_rs, Reader, Xlsx, XlsxError};
+use sea_orm::*;
+use std::collections::{HashMap, HashSet};
+use std::io::Cursor;
+use std::sync::{Arc, Mutex};
+
+use mxf_entity::{
+    HouseListingColumn, HouseListingEntity, ImportAction, ImportFormat, ImportJob, ImportRow,
+    ImportRowReport, ImportStatus, ListStatus, ListingInput, MXFError,
+};
+
+use crate::HouseService;
+
+/// A data row as read from the file: its line number and either the mapped
+/// row or every validation error found in it.
+pub type ParsedRow = (usize, Result<ImportRow, Vec<String>>);
+
+#[derive(Clone, Copy, PartialEq)]
+enum Field {
+    Extref,
+    District,
+    Addr,
+    Layout,
+    Floor,
+    Area,
+    Price,
+    Suite,
+    Desc,
+}
+
+/// Keeps the progress and reports of import jobs in memory, so they can be
+/// polled while the import runs in the background.
+#[derive(Clone)]
+pub struct ImportService {
+    jobs: Arc<Mutex<HashMap<u32, ImportJob>>>,
+}
+
+impl ImportService {
+    /// Finished jobs kept for polling; the oldest are dropped beyond this.
+    const MAX_FINISHED_JOBS: usize = 100;
+    const MAX_EXTREF_LEN: usize = 64;
+    const REQUIRED: [Field; 6] = [
+        Field::District,
+        Field::Addr,
+        Field::Layout,
+        Field::Floor,
+        Field::Area,
+        Field::Price,
+    ];
+
+    pub fn init() -> Self {
+        Self {
+            jobs: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Headers may use the column names or the labels of the listing form.
+    fn field_of(header: &str) -> Option<Field> {
+        match header.trim().to_lowercase().as_str() {
+            "hextref" | "extref" | "external_reference" | "外部编号" => Some(Field::Extref),
+            "hdistrict" | "district" | "区域" => Some(Field::District),
+            "haddr" | "address" | "地址" | "房源地址" => Some(Field::Addr),
+            "hlo" | "layout" | "房型" => Some(Field::Layout),
+            "hflr" | "floor" | "楼层" => Some(Field::Floor),
+            "harea" | "area" | "面积" => Some(Field::Area),
+            "hprice" | "price" | "价格" | "租金" => Some(Field::Price),
+            "hsuite" | "amenities" | "主要设施" => Some(Field::Suite),
+            "hdesc" | "description" | "描述" | "房源描述" => Some(Field::Desc),
+            _ => None,
+        }
+    }
+
+    fn read_table(format: ImportFormat, bytes: &[u8]) -> Result<Vec<Vec<String>>, MXFError> {
+        match format {
+            ImportFormat::Csv => {
+                let mut reader = csv::ReaderBuilder::new()
+                    .has_headers(false)
+                    .flexible(true)
+                    .from_reader(bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes));
+                reader
+                    .records()
+                    .map(|r| {
+                        r.map(|r| r.iter().map(|c| c.to_string()).collect())
+                            .map_err(|e| MXFError::InvalidImport(e.to_string()))
+                    })
+                    .collect()
+            }
+            ImportFormat::Xlsx => {
+                let mut workbook: Xlsx<_> = open_workbook_from_rs(Cursor::new(bytes))
+                    .map_err(|e: XlsxError| MXFError::InvalidImport(e.to_string()))?;
+                let range = workbook
+                    .worksheet_range_at(0)
+                    .ok_or(MXFError::InvalidImport("workbook has no sheet".to_string()))?
+                    .map_err(|e| MXFError::InvalidImport(e.to_string()))?;
+                Ok(range
+                    .rows()
+                    .map(|row| row.iter().map(|c| c.to_string()).collect())
+                    .collect())
+            }
+        }
+    }
+
+    /// Spreadsheets store numbers as floats, so "60.0" is accepted as 60.
+    fn parse_number(value: &str) -> Option<u32> {
+        let value = value.trim();
+        value.parse::<u32>().ok().or_else(|| {
+            value
+                .parse::<f64>()
+                .ok()
+                .filter(|v| v.fract() == 0.0 && *v >= 0.0 && *v <= u32::MAX as f64)
+                .map(|v| v as u32)
+        })
+    }
+
+    fn parse_row(columns: &[(usize, Field)], cells: &[String]) -> Result<ImportRow, Vec<String>> {
+        let mut row = ImportRow::default();
+        let mut errors = Vec::new();
+        for &(i, field) in columns {
+            let value = cells.get(i).map(|c| c.trim()).unwrap_or("");
+            if value.is_empty() {
+                if Self::REQUIRED.contains(&field) {
+                    errors.push(format!("第 {} 列不能为空", i + 1));
+                }
+                continue;
+            }
+            let mut number = |name: &str, positive: bool| match Self::parse_number(value) {
+                Some(n) if n > 0 || !positive => n,
+                _ => {
+                    errors.push(format!("{}无效：{}", name, value));
+                    0
+                }
+            };
+            match field {
+                Field::Extref => {
+                    if value.chars().count() > Self::MAX_EXTREF_LEN {
+                        errors.push(format!("外部编号超过 {} 个字符", Self::MAX_EXTREF_LEN));
+                    }
+                    row.extref = Some(value.to_string());
+                }
+                Field::District => row.hdistrict = value.to_string(),
+                Field::Addr => row.haddr = value.to_string(),
+                Field::Layout => row.hlo = value.to_string(),
+                Field::Floor => row.hflr = number("楼层", false),
+                Field::Area => row.harea = number("面积", true),
+                Field::Price => row.hprice = number("价格", true),
+                Field::Suite => row.hsuite = value.to_string(),
+                Field::Desc => row.hdesc = value.to_string(),
+            }
+        }
+        if errors.is_empty() {
+            Ok(row)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Reads and validates every row of the file. Fails as a whole only when
+    /// the file is unreadable or lacks required columns.
+    pub fn parse(format: ImportFormat, bytes: &[u8]) -> Result<Vec<ParsedRow>, MXFError> {
+        let table = Self::read_table(format, bytes)?;
+        let mut lines = table.into_iter().enumerate();
+        let header = lines
+            .next()
+            .ok_or(MXFError::InvalidImport("file is empty".to_string()))?
+            .1;
+        let columns: Vec<(usize, Field)> = header
+            .iter()
+            .enumerate()
+            .filter_map(|(i, h)| Self::field_of(h).map(|f| (i, f)))
+            .collect();
+        if Self::REQUIRED
+            .iter()
+            .any(|f| !columns.iter().any(|(_, c)| c == f))
+        {
+            return Err(MXFError::InvalidImport(
+                "missing columns: need 区域, 地址, 房型, 楼层, 面积, 价格".to_string(),
+            ));
+        }
+
+        let mut seen = HashSet::new();
+        Ok(lines
+            .filter(|(_, cells)| cells.iter().any(|c| !c.trim().is_empty()))
+            .map(|(i, cells)| {
+                let parsed = Self::parse_row(&columns, &cells).and_then(|row| match &row.extref {
+                    Some(extref) if !seen.insert(extref.clone()) => {
+                        Err(vec![format!("外部编号在文件中重复：{}", extref)])
+                    }
+                    _ => Ok(row),
+                });
+                (i + 1, parsed)
+            })
+            .collect())
+    }
+
+    /// Registers a job for `uno` and returns its id.
+    pub fn create_job(&self, uno: u32, dry_run: bool, upsert: bool, total: usize) -> u32 {
+        let mut jobs = self.jobs.lock().unwrap();
+        let mut finished: Vec<u32> = jobs
+            .values()
+            .filter(|j| j.status == ImportStatus::Finished)
+            .map(|j| j.id)
+            .collect();
+        finished.sort();
+        let excess = finished.len().saturating_sub(Self::MAX_FINISHED_JOBS);
+        for id in finished.into_iter().take(excess) {
+            jobs.remove(&id);
+        }
+        let id = jobs.keys().max().map_or(1, |id| id + 1);
+        jobs.insert(
+            id,
+            ImportJob {
+                id,
+                uno,
+                dry_run,
+                upsert,
+                status: ImportStatus::Running,
+                total,
+                processed: 0,
+                failed: 0,
+                rows: Vec::new(),
+            },
+        );
+        id
+    }
+
+    /// Returns the job, if it belongs to `uno`. Jobs of other users are not
+    /// found, so that job ids cannot be probed.
+    pub fn get_job(&self, id: u32, uno: u32) -> Result<ImportJob, MXFError> {
+        let jobs = self.jobs.lock().unwrap();
+        jobs.get(&id)
+            .filter(|job| job.uno == uno)
+            .cloned()
+            .ok_or(MXFError::ImportNotFound(id))
+    }
+
+    fn record(&self, id: u32, report: ImportRowReport) {
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(&id) {
+            job.processed += 1;
+            if !report.errors.is_empty() {
+                job.failed += 1;
+            }
+            job.rows.push(report);
+        }
+    }
+
+    /// Imports the rows of job `id`, recording a report per row. Rows are
+    /// matched to the landlord's houses by external reference; matches are
+    /// updated when `upsert` is set and rejected otherwise. A dry run only
+    /// reports what would be done.
+    pub async fn run(
+        &self,
+        db: &DbConn,
+        house_service: &HouseService,
+        id: u32,
+        rows: Vec<ParsedRow>,
+    ) {
+        let Some(job) = self.jobs.lock().unwrap().get(&id).cloned() else {
+            return;
+        };
+        for (line, parsed) in rows {
+            let report = match parsed {
+                Ok(row) => self.import_row(db, house_service, &job, line, row).await,
+                Err(errors) => ImportRowReport {
+                    row: line,
+                    extref: None,
+                    action: None,
+                    hno: None,
+                    errors,
+                },
+            };
+            self.record(id, report);
+        }
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(&id) {
+            job.status = ImportStatus::Finished;
+        }
+        println!("Import job {} by {} finished", id, job.uno);
+    }
+
+    async fn import_row(
+        &self,
+        db: &DbConn,
+        house_service: &HouseService,
+        job: &ImportJob,
+        line: usize,
+        row: ImportRow,
+    ) -> ImportRowReport {
+        let mut report = ImportRowReport {
+            row: line,
+            extref: row.extref.clone(),
+            action: None,
+            hno: None,
+            errors: Vec::new(),
+        };
+        let existing = match &row.extref {
+            Some(extref) => HouseListingEntity::find()
+                .filter(HouseListingColumn::Hlandlore.eq(job.uno))
+                .filter(HouseListingColumn::Hextref.eq(extref.as_str()))
+                .one(db)
+                .await
+                .map_err(MXFError::from),
+            None => Ok(None),
+        };
+        // The same checks as the listing form, run on dry runs too
+        let extref = row.extref.clone();
+        let mut house = match house_service.validate_listing(db, Self::to_input(row)).await {
+            Ok(house) => house,
+            Err(MXFError::InvalidListing(errors)) => {
+                report.errors = errors.iter().map(|e| e.message.clone()).collect();
+                return report;
+            }
+            Err(e) => {
+                report.errors.push(e.to_string());
+                return report;
+            }
+        };
+        house.hlandlore = job.uno;
+        let result = match existing {
+            Err(e) => Err(e),
+            Ok(Some(existing)) if !job.upsert => Err(MXFError::InvalidImport(format!(
+                "外部编号已存在：房源 {}",
+                existing.hno
+            ))),
+            Ok(Some(existing)) if existing.hunlisted.is_archived() => {
+                Err(MXFError::HouseArchived(existing.hno))
+            }
+            Ok(Some(existing)) => {
+                report.action = Some(ImportAction::Update);
+                report.hno = Some(existing.hno);
+                if job.dry_run {
+                    Ok(existing.hno)
+                } else {
+                    house.hno = existing.hno;
+                    house.hunlisted = existing.hunlisted;
+                    house.hextref = existing.hextref;
+                    house_service.modify_house(db, house, job.uno).await
+                }
+            }
+            Ok(None) => {
+                report.action = Some(ImportAction::Create);
+                if job.dry_run {
+                    Ok(0)
+                } else {
+                    house.hunlisted = ListStatus::PendingReview;
+                    house.hextref = extref;
+                    house_service.new_house(db, house, job.uno).await
+                }
+            }
+        };
+        match result {
+            Ok(hno) if hno > 0 => report.hno = Some(hno),
+            Ok(_) => (),
+            Err(e) => {
+                report.action = None;
+                report.errors.push(e.to_string());
+            }
+        }
+        report
+    }
+
+    /// The row as the listing form would post it.
+    fn to_input(row: ImportRow) -> ListingInput {
+        ListingInput {
+            hno: 0,
+            hdistrict: row.hdistrict,
+            haddr: row.haddr,
+            hlo: row.hlo,
+            hflr: Some(row.hflr as f64),
+            harea: Some(row.harea as f64),
+            hprice: Some(row.hprice as f64),
+            hsuite: row.hsuite,
+            hdesc: row.hdesc,
+            hunlisted: String::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_job_hides_jobs_of_other_users() {
+        let import_service = ImportService::init();
+        let id = import_service.create_job(1, true, false, 3);
+        assert_eq!(import_service.get_job(id, 1).unwrap().uno, 1);
+        for (id, uno) in [(id, 2), (id + 1, 1)] {
+            let job = import_service.get_job(id, uno);
+            assert!(matches!(job, Err(MXFError::ImportNotFound(i)) if i == id));
+        }
+    }
+}
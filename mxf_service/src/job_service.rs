@@ -0,0 +1,38 @@
+use chrono::NaiveDateTime;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::*;
+
+use mxf_entity::{JobRunActiveModel, JobRunColumn, JobRunEntity, MXFError};
+
+/// Remembers how far each periodic job has run, across restarts.
+pub struct JobService;
+
+impl JobService {
+    /// Where `job` stopped last time, or `first` if it never ran.
+    pub async fn last_run(
+        db: &DbConn,
+        job: &str,
+        first: NaiveDateTime,
+    ) -> Result<NaiveDateTime, MXFError> {
+        Ok(JobRunEntity::find_by_id(job)
+            .one(db)
+            .await?
+            .map_or(first, |run| run.jlast))
+    }
+
+    /// Records that `job` has handled everything up to `until`.
+    pub async fn finish(db: &DbConn, job: &str, until: NaiveDateTime) -> Result<(), MXFError> {
+        JobRunEntity::insert(JobRunActiveModel {
+            jname: Set(job.to_string()),
+            jlast: Set(until),
+        })
+        .on_conflict(
+            OnConflict::column(JobRunColumn::Jname)
+                .update_column(JobRunColumn::Jlast)
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+        Ok(())
+    }
+}
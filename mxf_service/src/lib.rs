@@ -1,8 +1,45 @@
+pub mod analytics_service;
+pub mod bucket_service;
+pub mod calendar_service;
+pub mod expiry_service;
+pub mod export_service;
+pub mod facet_service;
+pub mod favorite_service;
+pub mod fraud_service;
+pub mod geocoder;
 pub mod house_service;
+pub mod import_service;
+pub mod job_service;
+pub mod notification_service;
 pub mod order_service;
 pub mod pool;
+pub mod price_history_service;
+pub mod recommendation_service;
+pub mod region_service;
+pub mod rent_estimate_service;
+pub mod revision_service;
+pub mod saved_search_service;
+pub mod search_cache;
 pub mod user_service;
 
+pub use analytics_service::AnalyticsService;
+pub use bucket_service::BucketService;
+pub use calendar_service::CalendarService;
+pub use expiry_service::{ExpiryPolicy, ExpiryService};
+pub use export_service::ExportService;
+pub use facet_service::FacetService;
+pub use favorite_service::FavoriteService;
+pub use fraud_service::FraudService;
 pub use house_service::HouseService;
+pub use import_service::ImportService;
+pub use job_service::JobService;
+pub use notification_service::NotificationService;
 pub use order_service::OrderService;
+pub use price_history_service::PriceHistoryService;
+pub use recommendation_service::RecommendationService;
+pub use region_service::RegionService;
+pub use rent_estimate_service::RentEstimateService;
+pub use revision_service::RevisionService;
+pub use saved_search_service::{AlertQueue, SavedSearchService};
+pub use search_cache::SearchCache;
 pub use user_service::UserService;
@@ -0,0 +1,64 @@
+use chrono::Local;
+use sea_orm::*;
+
+use mxf_entity::{
+    MXFError, NotificationActiveModel, NotificationColumn, NotificationEntity, NotificationModel,
+    NotificationType,
+};
+
+pub struct NotificationService;
+
+impl NotificationService {
+    pub fn init() -> Self {
+        Self {}
+    }
+
+    /// Sends the same notification about house `hno` to every user in `unos`.
+    pub async fn notify_all(
+        db: &DbConn,
+        unos: &[u32],
+        hno: u32,
+        ntype: NotificationType,
+        content: &str,
+    ) -> Result<(), MXFError> {
+        if unos.is_empty() {
+            return Ok(());
+        }
+        let now = Local::now().naive_local();
+        let notifications = unos.iter().map(|&uno| NotificationActiveModel {
+            nno: NotSet,
+            uno: Set(uno),
+            hno: Set(hno),
+            ntype: Set(ntype),
+            ncontent: Set(content.to_string()),
+            ndate: Set(now),
+            nread: Set(false),
+        });
+        println!("Notify {:?} about {}: {}", unos, hno, content);
+        NotificationEntity::insert_many(notifications).exec(db).await?;
+        Ok(())
+    }
+
+    pub async fn get_notifications_by_uno(
+        &self,
+        db: &DbConn,
+        uno: u32,
+    ) -> Result<Vec<NotificationModel>, MXFError> {
+        NotificationEntity::find()
+            .filter(NotificationColumn::Uno.eq(uno))
+            .order_by_desc(NotificationColumn::Ndate)
+            .all(db)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    /// Marks all notifications of user `uno` as read.
+    pub async fn mark_all_read(&self, db: &DbConn, uno: u32) -> Result<(), MXFError> {
+        NotificationEntity::update_many()
+            .col_expr(NotificationColumn::Nread, true.into())
+            .filter(NotificationColumn::Uno.eq(uno))
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+}
@@ -1,8 +1,12 @@
-use chrono::{Local, Months};
+use chrono::{Local, Months, NaiveDateTime};
+use sea_orm::sea_query::Expr;
 use sea_orm::*;
 use std::collections::HashMap;
 
-use mxf_entity::{MXFError, OrderActiveModel, OrderColumn, OrderEntity, OrderModel, OrderType};
+use mxf_entity::{
+    Cursor, CursorPage, MXFError, OrderActiveModel, OrderColumn, OrderEntity, OrderModel,
+    OrderType, PeriodKind,
+};
 
 pub struct OrderService;
 
@@ -26,12 +30,7 @@ impl OrderService {
     pub fn filter_latest(orders: &Vec<OrderModel>) -> Vec<OrderModel> {
         let mut orders_type: HashMap<u32, OrderType> = HashMap::new();
         for order in orders {
-            if orders_type
-                .entry(order.ostatus)
-                .or_insert(order.otype)
-                .clone()
-                < order.otype
-            {
+            if *orders_type.entry(order.ostatus).or_insert(order.otype) < order.otype {
                 orders_type.insert(order.ostatus, order.otype);
             }
         }
@@ -42,6 +41,26 @@ impl OrderService {
             .collect()
     }
 
+    /// How the latest order of a chain holds the house over its dates. A
+    /// lease stays booked until the landlord agrees to cancel it.
+    pub fn hold(order: &OrderModel) -> Option<PeriodKind> {
+        match order.otype {
+            OrderType::LeaseRequest => Some(PeriodKind::Pending),
+            OrderType::LeaseConfirm | OrderType::CancelRequest => Some(PeriodKind::Booked),
+            OrderType::Deleted | OrderType::CancelConfirm => None,
+        }
+    }
+
+    /// Whether the order chains of a house hold it at `now`: a pending
+    /// request, or a lease that has not ended yet.
+    pub fn is_occupied(orders: &Vec<OrderModel>, now: NaiveDateTime) -> bool {
+        Self::filter_latest(orders).iter().any(|o| match Self::hold(o) {
+            Some(PeriodKind::Booked) => o.oend > now,
+            Some(_) => true,
+            None => false,
+        })
+    }
+
     pub async fn get_orders_by_htenant(
         &self,
         db: &DbConn,
@@ -66,6 +85,84 @@ impl OrderService {
             .map_err(|e| e.into())
     }
 
+    /// Latest order of each chain where `party` is `uno`, oldest chain
+    /// first, `limit` at a time after `cursor`, or right before it when
+    /// `before` is set. The same as `filter_latest` applied to all orders,
+    /// without loading them.
+    async fn get_latest_orders(
+        db: &DbConn,
+        party: OrderColumn,
+        uno: u32,
+        cursor: Option<Cursor>,
+        before: bool,
+        limit: u64,
+    ) -> Result<CursorPage<OrderModel>, MXFError> {
+        let position = cursor.map(|c| {
+            if before {
+                OrderColumn::Ono.lt(c.id)
+            } else {
+                OrderColumn::Ono.gt(c.id)
+            }
+        });
+        let mut orders = OrderEntity::find()
+            .filter(party.eq(uno))
+            .filter(Expr::cust_with_values(
+                format!(
+                    "(`ostatus`, `otype`) IN \
+                     (SELECT `ostatus`, MAX(`otype`) FROM `orders` WHERE `{}` = ? \
+                     GROUP BY `ostatus`)",
+                    party.as_str()
+                ),
+                [uno],
+            ))
+            .filter(Condition::all().add_option(position))
+            .order_by(OrderColumn::Ono, if before { Order::Desc } else { Order::Asc })
+            .limit(limit + 1)
+            .all(db)
+            .await?;
+        let more = orders.len() as u64 > limit;
+        orders.truncate(limit as usize);
+        if before {
+            orders.reverse();
+        }
+        let cursor_of = |order: Option<&OrderModel>| {
+            order.map(|o| Cursor { key: None, id: o.ono }.to_string())
+        };
+        // Paging back starts from a later page, paging on from an earlier one
+        let (has_prev, has_next) = if before {
+            (more, true)
+        } else {
+            (cursor.is_some(), more)
+        };
+        Ok(CursorPage {
+            next_cursor: cursor_of(orders.last().filter(|_| has_next)),
+            prev_cursor: cursor_of(orders.first().filter(|_| has_prev)),
+            items: orders,
+        })
+    }
+
+    pub async fn get_orders_by_htenant_after(
+        &self,
+        db: &DbConn,
+        htenant: u32,
+        cursor: Option<Cursor>,
+        before: bool,
+        limit: u64,
+    ) -> Result<CursorPage<OrderModel>, MXFError> {
+        Self::get_latest_orders(db, OrderColumn::Htenant, htenant, cursor, before, limit).await
+    }
+
+    pub async fn get_orders_by_hlandlore_after(
+        &self,
+        db: &DbConn,
+        hlandlore: u32,
+        cursor: Option<Cursor>,
+        before: bool,
+        limit: u64,
+    ) -> Result<CursorPage<OrderModel>, MXFError> {
+        Self::get_latest_orders(db, OrderColumn::Hlandlore, hlandlore, cursor, before, limit).await
+    }
+
     pub async fn get_orders(&self, db: &DbConn) -> Result<Vec<OrderModel>, MXFError> {
         OrderEntity::find().all(db).await.map_err(|e| e.into())
     }
@@ -80,7 +177,7 @@ impl OrderService {
             .into_tuple()
             .all(db)
             .await
-            .map_err(|e| MXFError::DatabaseError(e))?;
+            .map_err(MXFError::DatabaseError)?;
 
         println!("{:?}", orders);
         for order in orders {
@@ -159,3 +256,50 @@ impl OrderService {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn day(d: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 9, d).unwrap().and_hms_opt(0, 0, 0).unwrap()
+    }
+
+    fn order(ono: u32, otype: OrderType) -> OrderModel {
+        OrderModel {
+            ono,
+            hno: 1,
+            hlandlore: 2,
+            htenant: 3,
+            odate: day(1),
+            otype,
+            ostart: day(5),
+            oend: day(20),
+            ostatus: 1,
+        }
+    }
+
+    #[test]
+    fn cancel_request_still_occupies_until_lease_ends() {
+        let chain = |types: &[OrderType]| -> Vec<OrderModel> {
+            types.iter().enumerate().map(|(i, t)| order(i as u32 + 1, *t)).collect()
+        };
+        let requested = chain(&[OrderType::LeaseRequest]);
+        assert!(OrderService::is_occupied(&requested, day(25)));
+        let leased = chain(&[OrderType::LeaseRequest, OrderType::LeaseConfirm]);
+        assert!(OrderService::is_occupied(&leased, day(10)));
+        assert!(!OrderService::is_occupied(&leased, day(20)));
+        let cancelling =
+            chain(&[OrderType::LeaseRequest, OrderType::LeaseConfirm, OrderType::CancelRequest]);
+        assert!(OrderService::is_occupied(&cancelling, day(10)));
+        assert!(!OrderService::is_occupied(&cancelling, day(20)));
+        let cancelled = chain(&[
+            OrderType::LeaseRequest,
+            OrderType::LeaseConfirm,
+            OrderType::CancelRequest,
+            OrderType::CancelConfirm,
+        ]);
+        assert!(!OrderService::is_occupied(&cancelled, day(10)));
+    }
+}
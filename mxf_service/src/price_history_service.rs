@@ -0,0 +1,313 @@
+use chrono::{Duration, Local, Months, NaiveDateTime};
+use mini_moka::sync::Cache;
+use sea_orm::sea_query::Query;
+use sea_orm::*;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use mxf_entity::price_history;
+use mxf_entity::{
+    DistrictPriceTrend, HouseFilter, HouseListingColumn, HouseListingEntity, HouseListingModel,
+    ListStatus, MXFError, PriceHistoryActiveModel, PriceHistoryColumn, PriceHistoryEntity,
+    PriceHistoryModel, RegionLevel, RegionModel,
+};
+
+use crate::RegionService;
+
+/// Unit price medians by district region.
+struct DistrictMedians {
+    /// District of every region at or below district level.
+    district_of: HashMap<u32, u32>,
+    medians: HashMap<u32, f64>,
+}
+
+pub struct PriceHistoryService {
+    medians: Cache<(), Arc<DistrictMedians>>,
+}
+
+impl PriceHistoryService {
+    /// Fewer listed houses than this in a district give no usable median.
+    const MIN_MEDIAN_SAMPLES: usize = 3;
+    /// How long unit price medians are kept before being computed again.
+    const MEDIANS_TIME_TO_LIVE: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+
+    pub fn init() -> Self {
+        Self {
+            medians: Cache::builder()
+                .max_capacity(1)
+                .time_to_live(Self::MEDIANS_TIME_TO_LIVE)
+                .build(),
+        }
+    }
+
+    pub async fn record_price(db: &DbConn, hno: u32, price: u32) -> Result<(), MXFError> {
+        let record = PriceHistoryActiveModel {
+            pno: NotSet,
+            hno: Set(hno),
+            pprice: Set(price),
+            pdate: Set(Local::now().naive_local()),
+        };
+        PriceHistoryEntity::insert(record).exec(db).await?;
+        Ok(())
+    }
+
+    /// Lowest price the house was ever listed at.
+    pub async fn lowest_price(db: &DbConn, hno: u32) -> Result<Option<u32>, MXFError> {
+        PriceHistoryEntity::find()
+            .select_only()
+            .column_as(PriceHistoryColumn::Pprice.min(), "lowest")
+            .filter(PriceHistoryColumn::Hno.eq(hno))
+            .into_tuple::<Option<u32>>()
+            .one(db)
+            .await
+            .map(|lowest| lowest.flatten())
+            .map_err(|e| e.into())
+    }
+
+    pub async fn get_history(
+        &self,
+        db: &DbConn,
+        hno: u32,
+    ) -> Result<Vec<PriceHistoryModel>, MXFError> {
+        PriceHistoryEntity::find()
+            .filter(PriceHistoryColumn::Hno.eq(hno))
+            .order_by_asc(PriceHistoryColumn::Pdate)
+            .order_by_asc(PriceHistoryColumn::Pno)
+            .all(db)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    /// Highest price of a history in date order since `since`, counting the
+    /// price in effect then.
+    fn highest_since(history: &[(NaiveDateTime, u32)], since: NaiveDateTime) -> Option<u32> {
+        let start = history.partition_point(|(date, _)| *date < since);
+        history[start.saturating_sub(1)..].iter().map(|(_, price)| *price).max()
+    }
+
+    /// For each house, the higher price it had within
+    /// `HouseFilter::PRICE_DROP_DAYS`, if its price has dropped since; the
+    /// same rule as the `d` filter.
+    pub async fn get_price_drops(
+        &self,
+        db: &DbConn,
+        houses: &[HouseListingModel],
+    ) -> Result<Vec<Option<u32>>, MXFError> {
+        let since = Local::now().naive_local() - Duration::days(HouseFilter::PRICE_DROP_DAYS);
+        let mut histories: HashMap<u32, Vec<(NaiveDateTime, u32)>> = HashMap::new();
+        for (hno, pdate, pprice) in PriceHistoryEntity::find()
+            .select_only()
+            .column(PriceHistoryColumn::Hno)
+            .column(PriceHistoryColumn::Pdate)
+            .column(PriceHistoryColumn::Pprice)
+            .filter(PriceHistoryColumn::Hno.is_in(houses.iter().map(|h| h.hno)))
+            .order_by_asc(PriceHistoryColumn::Pdate)
+            .order_by_asc(PriceHistoryColumn::Pno)
+            .into_tuple::<(u32, NaiveDateTime, u32)>()
+            .all(db)
+            .await?
+        {
+            histories.entry(hno).or_default().push((pdate, pprice));
+        }
+        Ok(houses
+            .iter()
+            .map(|h| {
+                histories
+                    .get(&h.hno)
+                    .and_then(|history| Self::highest_since(history, since))
+                    .filter(|&p| p > h.hprice)
+            })
+            .collect())
+    }
+
+    /// Middle value of `values`, the mean of the two middle ones for an even
+    /// count.
+    fn median(values: &mut [f64]) -> Option<f64> {
+        values.sort_by(|a, b| a.total_cmp(b));
+        let mid = values.len() / 2;
+        match values.len() {
+            0 => None,
+            len if len % 2 == 0 => Some((values[mid - 1] + values[mid]) / 2.0),
+            _ => Some(values[mid]),
+        }
+    }
+
+    /// Median unit price of the listed houses of every district region with
+    /// enough of them, and the district each region lies in.
+    async fn load_district_medians(
+        db: &DbConn,
+        region_service: &RegionService,
+    ) -> Result<DistrictMedians, MXFError> {
+        let regions = region_service.regions(db).await?;
+        let by_rno: HashMap<u32, &RegionModel> = regions.iter().map(|r| (r.rno, r)).collect();
+        let district_of: HashMap<u32, u32> = regions
+            .iter()
+            .filter_map(|r| {
+                let mut region = r;
+                while region.rlevel != RegionLevel::District {
+                    region = by_rno.get(&region.rparent?)?;
+                }
+                Some((r.rno, region.rno))
+            })
+            .collect();
+        let mut unit_prices: HashMap<u32, Vec<f64>> = HashMap::new();
+        for (region, unit_price) in HouseListingEntity::find()
+            .select_only()
+            .column(HouseListingColumn::Hregion)
+            .column(HouseListingColumn::HunitPrice)
+            .filter(HouseListingColumn::Hunlisted.eq(ListStatus::Listed))
+            .filter(HouseListingColumn::Hregion.is_not_null())
+            .filter(HouseListingColumn::HunitPrice.is_not_null())
+            .into_tuple::<(u32, f64)>()
+            .all(db)
+            .await?
+        {
+            if let Some(district) = district_of.get(&region) {
+                unit_prices.entry(*district).or_default().push(unit_price);
+            }
+        }
+        let medians = unit_prices
+            .into_iter()
+            .filter(|(_, prices)| prices.len() >= Self::MIN_MEDIAN_SAMPLES)
+            .filter_map(|(district, mut prices)| {
+                Some((district, (Self::median(&mut prices)? * 100.0).round() / 100.0))
+            })
+            .collect();
+        Ok(DistrictMedians { district_of, medians })
+    }
+
+    /// For each house, the median monthly rent per m² of the listed houses in
+    /// its district, `None` where there are too few to compare with. Medians
+    /// are kept for a while, being the same for every page.
+    pub async fn get_unit_price_medians(
+        &self,
+        db: &DbConn,
+        region_service: &RegionService,
+        houses: &[HouseListingModel],
+    ) -> Result<Vec<Option<f64>>, MXFError> {
+        let medians = match self.medians.get(&()) {
+            Some(medians) => medians,
+            None => {
+                let medians = Arc::new(Self::load_district_medians(db, region_service).await?);
+                self.medians.insert((), medians.clone());
+                medians
+            }
+        };
+        Ok(houses
+            .iter()
+            .map(|h| {
+                let district = medians.district_of.get(&h.hregion?)?;
+                medians.medians.get(district).copied()
+            })
+            .collect())
+    }
+
+    /// Monthly average asking price and number of price drops per district
+    /// over the last `months` months. Drafts, rejected and deleted houses
+    /// never had a trustworthy public price and are left out.
+    pub async fn get_district_trends(
+        &self,
+        db: &DbConn,
+        months: u32,
+    ) -> Result<Vec<DistrictPriceTrend>, MXFError> {
+        let since = Local::now()
+            .naive_local()
+            .checked_sub_months(Months::new(months))
+            .ok_or(MXFError::InvalidQuery(format!("{} months is too far back", months)))?;
+        // Rows in the window, and the last row before it of each house so
+        // that the first price in the window can be compared with it
+        let records: Vec<(u32, u32, chrono::NaiveDateTime, String)> = PriceHistoryEntity::find()
+            .select_only()
+            .column(PriceHistoryColumn::Hno)
+            .column(PriceHistoryColumn::Pprice)
+            .column(PriceHistoryColumn::Pdate)
+            .column(HouseListingColumn::Hdistrict)
+            .join(JoinType::InnerJoin, price_history::Relation::HouseListing.def())
+            .filter(HouseListingColumn::Hunlisted.is_not_in([
+                ListStatus::Draft,
+                ListStatus::Rejected,
+                ListStatus::Deleted,
+            ]))
+            .filter(
+                Condition::any().add(PriceHistoryColumn::Pdate.gte(since)).add(
+                    PriceHistoryColumn::Pno.in_subquery(
+                        Query::select()
+                            .expr(PriceHistoryColumn::Pno.max())
+                            .from(PriceHistoryEntity)
+                            .and_where(PriceHistoryColumn::Pdate.lt(since))
+                            .group_by_col(PriceHistoryColumn::Hno)
+                            .to_owned(),
+                    ),
+                ),
+            )
+            .order_by_asc(PriceHistoryColumn::Hno)
+            .order_by_asc(PriceHistoryColumn::Pdate)
+            .order_by_asc(PriceHistoryColumn::Pno)
+            .into_tuple()
+            .all(db)
+            .await?;
+
+        // (district, month) -> (price sum, records, drops)
+        let mut trends: BTreeMap<(String, String), (u64, u32, u32)> = BTreeMap::new();
+        let mut previous: Option<(u32, u32)> = None;
+        for (hno, price, date, district) in records {
+            let dropped = matches!(previous, Some((h, p)) if h == hno && price < p);
+            previous = Some((hno, price));
+            if date < since {
+                continue;
+            }
+            let month = date.format("%Y-%m").to_string();
+            let entry = trends.entry((district, month)).or_insert((0, 0, 0));
+            entry.0 += price as u64;
+            entry.1 += 1;
+            entry.2 += dropped as u32;
+        }
+        Ok(trends
+            .into_iter()
+            .map(|((district, month), (sum, records, drops))| DistrictPriceTrend {
+                district,
+                month,
+                average_price: (sum / records as u64) as u32,
+                records,
+                drops,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn day(d: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 9, d).unwrap().and_hms_opt(12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn highest_since_counts_price_at_start() {
+        // Cut on the 20th from a price set before the window
+        let history = [(day(1), 5000), (day(20), 4500)];
+        assert_eq!(PriceHistoryService::highest_since(&history, day(10)), Some(5000));
+        // Only the latest price before the window counts
+        let history = [(day(1), 6000), (day(5), 5000), (day(20), 4500)];
+        assert_eq!(PriceHistoryService::highest_since(&history, day(10)), Some(5000));
+        // Raised and cut inside the window
+        let history = [(day(1), 4000), (day(12), 5200), (day(20), 4800)];
+        assert_eq!(PriceHistoryService::highest_since(&history, day(10)), Some(5200));
+        // Unchanged since long ago
+        let history = [(day(1), 4000)];
+        assert_eq!(PriceHistoryService::highest_since(&history, day(10)), Some(4000));
+        // The price replaced right at the start still counts
+        let history = [(day(1), 6000), (day(10), 5000)];
+        assert_eq!(PriceHistoryService::highest_since(&history, day(10)), Some(6000));
+        assert_eq!(PriceHistoryService::highest_since(&[], day(10)), None);
+    }
+
+    #[test]
+    fn median_of_odd_and_even_counts() {
+        assert_eq!(PriceHistoryService::median(&mut [80.0, 60.0, 100.0]), Some(80.0));
+        assert_eq!(PriceHistoryService::median(&mut [100.0, 60.0, 90.0, 70.0]), Some(80.0));
+        assert_eq!(PriceHistoryService::median(&mut []), None);
+    }
+}
@@ -0,0 +1,119 @@
+use chrono::Local;
+use sea_orm::*;
+use std::collections::{HashMap, HashSet};
+
+use mxf_entity::{
+    district_base, district_spellings, HouseListingColumn, HouseListingEntity, HouseListingModel,
+    ListStatus, MXFError, OrderColumn, OrderEntity, OrderModel,
+};
+
+use crate::OrderService;
+
+/// Ranks available houses by how much they resemble a given one.
+pub struct RecommendationService;
+
+impl RecommendationService {
+    /// How many similar houses to show when the caller does not ask for a count.
+    pub const DEFAULT_RECOMMENDATIONS: usize = 5;
+    pub const MAX_RECOMMENDATIONS: usize = 20;
+    /// Houses priced within this factor of the target are considered even in
+    /// other districts.
+    const PRICE_BAND: f64 = 1.5;
+
+    const DISTRICT_WEIGHT: f64 = 3.0;
+    const LAYOUT_WEIGHT: f64 = 2.0;
+    const AREA_WEIGHT: f64 = 2.0;
+    const PRICE_WEIGHT: f64 = 2.0;
+    const SUITE_WEIGHT: f64 = 1.5;
+
+    pub fn init() -> Self {
+        Self {}
+    }
+
+    /// 1 for equal values, falling linearly to 0 as one doubles the other.
+    pub(crate) fn closeness(a: u32, b: u32) -> f64 {
+        let (a, b) = (a as f64, b as f64);
+        if a.max(b) == 0.0 {
+            return 1.0;
+        }
+        (1.0 - (a - b).abs() / a.max(b)).max(0.0)
+    }
+
+    pub(crate) fn amenities(suite: &str) -> HashSet<&str> {
+        suite
+            .split(|c: char| c.is_whitespace() || ",，、;；/".contains(c))
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
+
+    fn score(target: &HouseListingModel, other: &HouseListingModel) -> f64 {
+        let district =
+            district_base(&target.hdistrict) == district_base(&other.hdistrict);
+        // "3室2厅" vs "3室1厅" still shares the bedroom count.
+        let layout = if target.hlo == other.hlo {
+            1.0
+        } else if target.hbedrooms.is_some() && target.hbedrooms == other.hbedrooms {
+            0.5
+        } else {
+            0.0
+        };
+        let (ours, theirs) = (Self::amenities(&target.hsuite), Self::amenities(&other.hsuite));
+        let union = ours.union(&theirs).count();
+        let suite = if union == 0 {
+            0.0
+        } else {
+            ours.intersection(&theirs).count() as f64 / union as f64
+        };
+        Self::DISTRICT_WEIGHT * district as u8 as f64
+            + Self::LAYOUT_WEIGHT * layout
+            + Self::AREA_WEIGHT * Self::closeness(target.harea, other.harea)
+            + Self::PRICE_WEIGHT * Self::closeness(target.hprice, other.hprice)
+            + Self::SUITE_WEIGHT * suite
+    }
+
+    /// Up to `n` listed houses without an active lease or pending request,
+    /// most similar to `target` first.
+    pub async fn get_similar_houses(
+        &self,
+        db: &DbConn,
+        target: &HouseListingModel,
+        n: usize,
+    ) -> Result<Vec<HouseListingModel>, MXFError> {
+        let n = n.min(Self::MAX_RECOMMENDATIONS);
+        let price = target.hprice as f64;
+        let candidates = HouseListingEntity::find()
+            .filter(HouseListingColumn::Hunlisted.eq(ListStatus::Listed))
+            .filter(HouseListingColumn::Hno.ne(target.hno))
+            .filter(
+                Condition::any()
+                    .add(HouseListingColumn::Hdistrict.is_in(district_spellings(&target.hdistrict)))
+                    .add(HouseListingColumn::Hprice.between(
+                        (price / Self::PRICE_BAND) as u32,
+                        (price * Self::PRICE_BAND) as u32,
+                    )),
+            )
+            .all(db)
+            .await?;
+
+        let mut orders: HashMap<u32, Vec<OrderModel>> = HashMap::new();
+        for order in OrderEntity::find()
+            .filter(OrderColumn::Hno.is_in(candidates.iter().map(|h| h.hno)))
+            .all(db)
+            .await?
+        {
+            orders.entry(order.hno).or_default().push(order);
+        }
+        let now = Local::now().naive_local();
+        let mut ranked: Vec<(f64, HouseListingModel)> = candidates
+            .into_iter()
+            .filter(|h| {
+                orders
+                    .get(&h.hno)
+                    .map_or(true, |o| !OrderService::is_occupied(o, now))
+            })
+            .map(|h| (Self::score(target, &h), h))
+            .collect();
+        ranked.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.hno.cmp(&b.1.hno)));
+        Ok(ranked.into_iter().take(n).map(|(_, h)| h).collect())
+    }
+}
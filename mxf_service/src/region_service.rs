@@ -0,0 +1,200 @@
+use mini_moka::sync::Cache;
+use sea_orm::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use mxf_entity::{
+    district_base, HouseFilter, HouseListingColumn, HouseListingEntity, ListStatus, MXFError,
+    RegionActiveModel, RegionColumn, RegionEntity, RegionLevel, RegionModel, RegionOption,
+};
+
+use crate::geocoder::Geocoder;
+
+/// Files houses under the region tree and counts search results per region.
+/// Clones share the cached tree.
+#[derive(Clone)]
+pub struct RegionService {
+    regions: Cache<(), Arc<Vec<RegionModel>>>,
+}
+
+impl RegionService {
+    /// How long the region tree is kept before being read again.
+    const REGIONS_TIME_TO_LIVE: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+
+    pub fn init() -> Self {
+        Self {
+            regions: Cache::builder()
+                .max_capacity(1)
+                .time_to_live(Self::REGIONS_TIME_TO_LIVE)
+                .build(),
+        }
+    }
+
+    /// All regions in `rno` order.
+    pub async fn regions(&self, db: &DbConn) -> Result<Arc<Vec<RegionModel>>, MXFError> {
+        if let Some(regions) = self.regions.get(&()) {
+            return Ok(regions);
+        }
+        let regions =
+            Arc::new(RegionEntity::find().order_by_asc(RegionColumn::Rno).all(db).await?);
+        self.regions.insert((), regions.clone());
+        Ok(regions)
+    }
+
+    /// Adds the city, districts and streets of the gazetteer missing from the
+    /// region tree, streets as neighborhoods. Returns whether any was added.
+    pub async fn seed(&self, db: &DbConn, geocoder: &Geocoder) -> Result<bool, MXFError> {
+        let mut regions = RegionEntity::find().all(db).await?;
+        let known = regions.len();
+        let city = match geocoder.city() {
+            Some(city) => {
+                Some(Self::find_or_insert(db, &mut regions, None, city, RegionLevel::City).await?)
+            }
+            None => None,
+        };
+        let mut districts = HashMap::new();
+        for district in geocoder.districts() {
+            let rno =
+                Self::find_or_insert(db, &mut regions, city, district, RegionLevel::District)
+                    .await?;
+            districts.insert(district, rno);
+        }
+        for (district, street) in geocoder.streets() {
+            let Some(&district) = districts.get(district) else {
+                println!("regions: street {:?} of unknown district {:?}", street, district);
+                continue;
+            };
+            let level = RegionLevel::Neighborhood;
+            Self::find_or_insert(db, &mut regions, Some(district), street, level).await?;
+        }
+        self.regions.invalidate(&());
+        Ok(regions.len() > known)
+    }
+
+    async fn find_or_insert(
+        db: &DbConn,
+        regions: &mut Vec<RegionModel>,
+        rparent: Option<u32>,
+        rname: &str,
+        rlevel: RegionLevel,
+    ) -> Result<u32, MXFError> {
+        if let Some(region) = regions.iter().find(|r| r.rparent == rparent && r.rname == rname) {
+            return Ok(region.rno);
+        }
+        let region = RegionActiveModel {
+            rno: NotSet,
+            rparent: Set(rparent),
+            rname: Set(rname.to_string()),
+            rlevel: Set(rlevel),
+        }
+        .insert(db)
+        .await?;
+        let rno = region.rno;
+        regions.push(region);
+        Ok(rno)
+    }
+
+    /// Names of the districts in the region tree, as listings should spell them.
+    pub async fn district_names(&self, db: &DbConn) -> Result<Vec<String>, MXFError> {
+        Ok(self
+            .regions(db)
+            .await?
+            .iter()
+            .filter(|r| r.rlevel == RegionLevel::District)
+            .map(|r| r.rname.clone())
+            .collect())
+    }
+
+    /// Most specific region of a house: its district, or the neighborhood of
+    /// that district named in the address, the longest name winning.
+    pub async fn resolve(
+        &self,
+        db: &DbConn,
+        district: &str,
+        addr: &str,
+    ) -> Result<Option<u32>, MXFError> {
+        let base = district_base(district);
+        if base.is_empty() {
+            return Ok(None);
+        }
+        let regions = self.regions(db).await?;
+        let Some(district) = regions
+            .iter()
+            .find(|r| r.rlevel == RegionLevel::District && district_base(&r.rname) == base)
+        else {
+            return Ok(None);
+        };
+        let neighborhood = regions
+            .iter()
+            .filter(|r| r.rparent == Some(district.rno))
+            .filter(|r| addr.contains(r.rname.as_str()))
+            .max_by_key(|r| r.rname.chars().count());
+        Ok(Some(neighborhood.map_or(district.rno, |r| r.rno)))
+    }
+
+    /// Regions from the city down to `rno`.
+    pub async fn get_path(&self, db: &DbConn, rno: u32) -> Result<Vec<RegionModel>, MXFError> {
+        let regions = self.regions(db).await?;
+        let regions: HashMap<u32, &RegionModel> = regions.iter().map(|r| (r.rno, r)).collect();
+        let mut path = Vec::new();
+        let mut next = Some(rno);
+        while let Some(region) = next.and_then(|rno| regions.get(&rno)) {
+            path.push((*region).clone());
+            next = region.rparent;
+        }
+        path.reverse();
+        Ok(path)
+    }
+
+    /// Regions right below the one searched in, or the top level when the
+    /// search has no region, each with the number of listed houses it holds
+    /// for the rest of the search. A single city is skipped to its districts.
+    pub async fn get_region_options(
+        &self,
+        db: &DbConn,
+        house_filter: HouseFilter<'_>,
+    ) -> Result<Vec<RegionOption>, MXFError> {
+        let regions = self.regions(db).await?;
+        let children = |parent: Option<u32>| -> Vec<&RegionModel> {
+            regions.iter().filter(|r| r.rparent == parent).collect()
+        };
+        let mut options = children(house_filter.region());
+        if house_filter.region().is_none() && options.len() == 1 {
+            options = children(Some(options[0].rno));
+        }
+        if options.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let counts: Vec<(Option<u32>, i64)> = HouseListingEntity::find()
+            .select_only()
+            .column(HouseListingColumn::Hregion)
+            .column_as(HouseListingColumn::Hno.count(), "count")
+            .filter(Condition::from(house_filter.with_region(None)))
+            .filter(HouseListingColumn::Hunlisted.eq(ListStatus::Listed))
+            .group_by(HouseListingColumn::Hregion)
+            .into_tuple()
+            .all(db)
+            .await?;
+        // Add the houses of each region to every region above it
+        let parents: HashMap<u32, Option<u32>> =
+            regions.iter().map(|r| (r.rno, r.rparent)).collect();
+        let mut totals: HashMap<u32, u64> = HashMap::new();
+        for (region, count) in counts {
+            let mut next = region;
+            while let Some(rno) = next {
+                *totals.entry(rno).or_default() += count as u64;
+                next = parents.get(&rno).copied().flatten();
+            }
+        }
+
+        Ok(options
+            .into_iter()
+            .map(|r| RegionOption {
+                region: r.clone(),
+                count: totals.get(&r.rno).copied().unwrap_or(0),
+                query: house_filter.with_region(Some(r.rno)).to_query_string(),
+            })
+            .collect())
+    }
+}
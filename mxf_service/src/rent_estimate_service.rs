@@ -0,0 +1,293 @@
+use chrono::{Local, Months, NaiveDateTime};
+use sea_orm::sea_query::Query;
+use sea_orm::*;
+use std::collections::HashMap;
+
+use mxf_entity::{
+    district_spellings, Comparable, ComparableSource, HouseFlagColumn, HouseFlagEntity,
+    HouseListingColumn, HouseListingEntity, HouseListingModel, ListStatus, MXFError, OrderColumn,
+    OrderEntity, OrderType, PriceHistoryColumn, PriceHistoryEntity, RentEstimate,
+};
+
+use crate::RecommendationService;
+
+/// Suggests a rent for a house from the prices of similar houses in its
+/// district: listed now, listed before, and leased. Each house counts once,
+/// by its latest lease if it has one.
+pub struct RentEstimateService;
+
+impl RentEstimateService {
+    pub const MAX_COMPARABLES: usize = 12;
+    /// Fewer comparables than this give no estimate.
+    const MIN_COMPARABLES: usize = 3;
+    /// Houses more than this factor larger or smaller are not comparable.
+    const AREA_BAND: f64 = 1.6;
+    /// Past asking prices and leases older than this are left out.
+    const HISTORY_MONTHS: u32 = 24;
+
+    /// How much a price counts by where it comes from; a confirmed lease
+    /// shows what tenants actually pay.
+    const LISTED_WEIGHT: f64 = 1.0;
+    const PAST_WEIGHT: f64 = 0.6;
+    const LEASED_WEIGHT: f64 = 1.5;
+
+    const AREA_WEIGHT: f64 = 3.0;
+    const LAYOUT_WEIGHT: f64 = 2.0;
+    const FLOOR_WEIGHT: f64 = 1.0;
+    const SUITE_WEIGHT: f64 = 1.0;
+
+    /// Suggested rents are rounded to this many yuan.
+    const ROUNDING: f64 = 50.0;
+
+    pub fn init() -> Self {
+        Self {}
+    }
+
+    /// From 0 to 1; the price is left out, being what is estimated.
+    fn similarity(target: &HouseListingModel, other: &HouseListingModel) -> f64 {
+        let layout = if target.hlo.is_empty() || target.hlo == other.hlo {
+            1.0
+        } else if target.hbedrooms.is_some() && target.hbedrooms == other.hbedrooms {
+            0.5
+        } else {
+            0.0
+        };
+        let ours = RecommendationService::amenities(&target.hsuite);
+        let theirs = RecommendationService::amenities(&other.hsuite);
+        let union = ours.union(&theirs).count();
+        let suite = if union == 0 {
+            1.0
+        } else {
+            ours.intersection(&theirs).count() as f64 / union as f64
+        };
+        let score = Self::AREA_WEIGHT * RecommendationService::closeness(target.harea, other.harea)
+            + Self::LAYOUT_WEIGHT * layout
+            + Self::FLOOR_WEIGHT * RecommendationService::closeness(target.hflr, other.hflr)
+            + Self::SUITE_WEIGHT * suite;
+        score / (Self::AREA_WEIGHT + Self::LAYOUT_WEIGHT + Self::FLOOR_WEIGHT + Self::SUITE_WEIGHT)
+    }
+
+    fn comparable(
+        target: &HouseListingModel,
+        house: &HouseListingModel,
+        source: ComparableSource,
+        price: u32,
+        date: Option<NaiveDateTime>,
+    ) -> Comparable {
+        Comparable {
+            hno: house.hno,
+            source,
+            hdistrict: house.hdistrict.clone(),
+            hlo: house.hlo.clone(),
+            hflr: house.hflr,
+            harea: house.harea,
+            price,
+            unit_price: (price as f64 / house.harea as f64 * 100.0).round() / 100.0,
+            date,
+            similarity: (Self::similarity(target, house) * 100.0).round() / 100.0,
+        }
+    }
+
+    fn weight(comparable: &Comparable) -> f64 {
+        comparable.similarity
+            * match comparable.source {
+                ComparableSource::Listed => Self::LISTED_WEIGHT,
+                ComparableSource::Past => Self::PAST_WEIGHT,
+                ComparableSource::Leased => Self::LEASED_WEIGHT,
+            }
+    }
+
+    /// Value below which `q` of the total weight lies; `values` sorted.
+    fn weighted_quantile(values: &[(f64, f64)], q: f64) -> f64 {
+        let total: f64 = values.iter().map(|(_, w)| w).sum();
+        let mut seen = 0.0;
+        for (value, weight) in values {
+            seen += weight;
+            if seen >= q * total {
+                return *value;
+            }
+        }
+        values.last().map_or(0.0, |(value, _)| *value)
+    }
+
+    /// Estimates the rent of `target` from the comparables most like it.
+    /// `target.hno` is left out of its own comparables.
+    pub async fn estimate(
+        &self,
+        db: &DbConn,
+        target: &HouseListingModel,
+    ) -> Result<RentEstimate, MXFError> {
+        let since = Local::now().naive_local() - Months::new(Self::HISTORY_MONTHS);
+        let area = target.harea as f64;
+        // Districts typed before they were checked may lack the "区"
+        let houses: HashMap<u32, HouseListingModel> = HouseListingEntity::find()
+            .filter(HouseListingColumn::Hdistrict.is_in(district_spellings(&target.hdistrict)))
+            .filter(HouseListingColumn::Harea.between(
+                (area / Self::AREA_BAND).floor().max(1.0) as u32,
+                (area * Self::AREA_BAND).ceil() as u32,
+            ))
+            .filter(HouseListingColumn::Hno.ne(target.hno))
+            // Drafts, rejected and pending houses never had a public price,
+            // and deleted ones may have been fake
+            .filter(HouseListingColumn::Hunlisted.is_in([
+                ListStatus::Listed,
+                ListStatus::Unlisted,
+                ListStatus::Archived,
+            ]))
+            .filter(
+                HouseListingColumn::Hno.not_in_subquery(
+                    Query::select()
+                        .column(HouseFlagColumn::Hno)
+                        .from(HouseFlagEntity)
+                        .and_where(HouseFlagColumn::Fresolved.eq(false))
+                        .to_owned(),
+                ),
+            )
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|h| (h.hno, h))
+            .collect();
+
+        // The latest confirmed lease of each house, at the price asked then
+        let mut leases: HashMap<u32, NaiveDateTime> = HashMap::new();
+        for (hno, odate) in OrderEntity::find()
+            .select_only()
+            .column(OrderColumn::Hno)
+            .column(OrderColumn::Odate)
+            .filter(OrderColumn::Otype.eq(OrderType::LeaseConfirm))
+            .filter(OrderColumn::Odate.gte(since))
+            .filter(OrderColumn::Hno.is_in(houses.keys().copied()))
+            .into_tuple::<(u32, NaiveDateTime)>()
+            .all(db)
+            .await?
+        {
+            let latest = leases.entry(hno).or_insert(odate);
+            *latest = (*latest).max(odate);
+        }
+        let mut prices: HashMap<u32, Vec<(NaiveDateTime, u32)>> = HashMap::new();
+        for (hno, pdate, pprice) in PriceHistoryEntity::find()
+            .select_only()
+            .column(PriceHistoryColumn::Hno)
+            .column(PriceHistoryColumn::Pdate)
+            .column(PriceHistoryColumn::Pprice)
+            .filter(PriceHistoryColumn::Hno.is_in(leases.keys().copied()))
+            .order_by_asc(PriceHistoryColumn::Pdate)
+            .order_by_asc(PriceHistoryColumn::Pno)
+            .into_tuple::<(u32, NaiveDateTime, u32)>()
+            .all(db)
+            .await?
+        {
+            prices.entry(hno).or_default().push((pdate, pprice));
+        }
+        let mut comparables: Vec<Comparable> = houses
+            .values()
+            .filter_map(|house| {
+                // Leases from before the price history began tell nothing
+                let leased = leases.get(&house.hno).and_then(|odate| {
+                    let (_, price) = prices
+                        .get(&house.hno)?
+                        .iter()
+                        .rev()
+                        .find(|(pdate, _)| pdate <= odate)?;
+                    Some((ComparableSource::Leased, *price, Some(*odate)))
+                });
+                let listed = || {
+                    if house.hunlisted == ListStatus::Listed {
+                        let date = house.hrefreshed_at.or(house.hlisted_at);
+                        Some((ComparableSource::Listed, house.hprice, date))
+                    } else {
+                        // Only houses that were public at some point
+                        house
+                            .hlisted_at
+                            .and(house.hrefreshed_at.or(house.hlisted_at))
+                            .filter(|date| *date >= since)
+                            .map(|date| (ComparableSource::Past, house.hprice, Some(date)))
+                    }
+                };
+                leased.or_else(listed).map(|(source, price, date)| {
+                    Self::comparable(target, house, source, price, date)
+                })
+            })
+            .collect();
+
+        comparables.sort_by(|a, b| {
+            Self::weight(b).total_cmp(&Self::weight(a)).then(a.hno.cmp(&b.hno))
+        });
+        comparables.truncate(Self::MAX_COMPARABLES);
+        if comparables.len() < Self::MIN_COMPARABLES {
+            return Ok(RentEstimate { low: None, median: None, high: None, comparables });
+        }
+
+        let mut rents: Vec<(f64, f64)> =
+            comparables.iter().map(|c| (c.unit_price * area, Self::weight(c))).collect();
+        rents.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let suggest = |q: f64| {
+            let rent = Self::weighted_quantile(&rents, q);
+            Some(((rent / Self::ROUNDING).round() * Self::ROUNDING) as u32)
+        };
+        Ok(RentEstimate {
+            low: suggest(0.25),
+            median: suggest(0.5),
+            high: suggest(0.75),
+            comparables,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mxf_entity::ListingInput;
+
+    fn house(hlo: &str, hflr: u32, harea: u32, hsuite: &str) -> HouseListingModel {
+        ListingInput {
+            hdistrict: "海淀区".to_string(),
+            hlo: hlo.to_string(),
+            hflr: Some(hflr as f64),
+            harea: Some(harea as f64),
+            hsuite: hsuite.to_string(),
+            ..Default::default()
+        }
+        .validate_for_estimate(&["海淀区"])
+        .unwrap()
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn similarity_weighs_each_feature() {
+        let target = house("2室1厅", 5, 50, "空调 冰箱");
+        let similarity = |other| RentEstimateService::similarity(&target, &other);
+        assert!(close(similarity(house("2室1厅", 5, 50, "冰箱，空调")), 1.0));
+        // Same bedrooms only counts half the layout
+        assert!(close(similarity(house("2室2厅", 5, 50, "空调 冰箱")), 6.0 / 7.0));
+        // Half the area and floor, nothing else in common
+        assert!(close(similarity(house("3室1厅", 10, 100, "洗衣机")), 2.0 / 7.0));
+        // One amenity of three shared
+        assert!(close(similarity(house("2室1厅", 5, 50, "空调 洗衣机")), 6.0 / 7.0 + 1.0 / 21.0));
+
+        // A layout left out of the estimate form matches any, amenities do not
+        let target = house("", 5, 50, "");
+        let similarity = |other| RentEstimateService::similarity(&target, &other);
+        assert!(close(similarity(house("3室1厅", 5, 50, "")), 1.0));
+        assert!(close(similarity(house("3室1厅", 5, 50, "空调")), 6.0 / 7.0));
+    }
+
+    #[test]
+    fn weighted_quantile_follows_weights() {
+        let values = [(1.0, 1.0), (2.0, 1.0), (3.0, 2.0)];
+        let quantile = |q| RentEstimateService::weighted_quantile(&values, q);
+        assert_eq!(quantile(0.0), 1.0);
+        assert_eq!(quantile(0.25), 1.0);
+        assert_eq!(quantile(0.5), 2.0);
+        assert_eq!(quantile(0.6), 3.0);
+        assert_eq!(quantile(1.0), 3.0);
+        // Weightless values are never reached before a weighted one
+        let values = [(1.0, 0.0), (2.0, 1.0)];
+        assert_eq!(RentEstimateService::weighted_quantile(&values, 0.5), 2.0);
+        assert_eq!(RentEstimateService::weighted_quantile(&[], 0.5), 0.0);
+    }
+}
@@ -0,0 +1,131 @@
+use chrono::Local;
+use sea_orm::*;
+
+use mxf_entity::{
+    FieldChange, HouseListingModel, HouseRevisionActiveModel, HouseRevisionColumn,
+    HouseRevisionEntity, HouseRevisionModel, MXFError, RevisionDiff,
+};
+
+pub struct RevisionService;
+
+impl RevisionService {
+    pub fn init() -> Self {
+        Self {}
+    }
+
+    /// Columns compared between revisions, with their labels on the listing form.
+    fn tracked_fields(house: &HouseListingModel) -> [(&'static str, &'static str, String); 11] {
+        [
+            ("hdistrict", "区域", house.hdistrict.clone()),
+            ("haddr", "地址", house.haddr.clone()),
+            ("hlo", "房型", house.hlo.clone()),
+            ("hflr", "楼层", house.hflr.to_string()),
+            ("harea", "面积", house.harea.to_string()),
+            ("hprice", "价格", house.hprice.to_string()),
+            ("hsuite", "主要设施", house.hsuite.clone()),
+            ("hdesc", "房源描述", house.hdesc.clone()),
+            ("hunlisted", "状态", format!("{:?}", house.hunlisted)),
+            ("hreason", "状态说明", house.hreason.clone().unwrap_or_default()),
+            ("hextref", "外部编号", house.hextref.clone().unwrap_or_default()),
+        ]
+    }
+
+    fn diff(before: Option<&HouseListingModel>, after: &HouseListingModel) -> Vec<FieldChange> {
+        let before = before.map(Self::tracked_fields);
+        Self::tracked_fields(after)
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, (field, label, value))| {
+                let old = before.as_ref().map(|b| b[i].2.clone()).unwrap_or_default();
+                (before.is_none() || old != value).then(|| FieldChange {
+                    field: field.to_string(),
+                    label: label.to_string(),
+                    before: old,
+                    after: value,
+                })
+            })
+            .collect()
+    }
+
+    fn snapshot(revision: &HouseRevisionModel) -> Result<HouseListingModel, MXFError> {
+        serde_json::from_str(&revision.rsnapshot).map_err(|e| {
+            MXFError::UnknownError(format!("Revision {} is unreadable: {}", revision.rno, e))
+        })
+    }
+
+    /// Appends a revision of `after`, made by `editor`, or by the scheduler
+    /// when `None`. `before` is `None` for new houses. Edits that change no
+    /// tracked field are not recorded.
+    pub async fn record(
+        db: &impl ConnectionTrait,
+        before: Option<&HouseListingModel>,
+        after: &HouseListingModel,
+        editor: Option<u32>,
+    ) -> Result<(), MXFError> {
+        let fields = match before {
+            Some(_) => {
+                let changes = Self::diff(before, after);
+                if changes.is_empty() {
+                    return Ok(());
+                }
+                changes
+                    .into_iter()
+                    .map(|c| c.field)
+                    .collect::<Vec<String>>()
+                    .join(",")
+            }
+            None => String::new(),
+        };
+        let revision = HouseRevisionActiveModel {
+            rno: NotSet,
+            hno: Set(after.hno),
+            reditor: Set(editor),
+            rdate: Set(Local::now().naive_local()),
+            rfields: Set(fields),
+            rsnapshot: Set(serde_json::to_string(after)
+                .map_err(|e| MXFError::UnknownError(e.to_string()))?),
+        };
+        HouseRevisionEntity::insert(revision).exec(db).await?;
+        Ok(())
+    }
+
+    /// The listing as it was right after revision `rno`.
+    pub async fn get_snapshot(
+        db: &DbConn,
+        rno: u32,
+    ) -> Result<HouseListingModel, MXFError> {
+        let revision = HouseRevisionEntity::find_by_id(rno)
+            .one(db)
+            .await?
+            .ok_or(MXFError::RevisionNotFound(rno))?;
+        Self::snapshot(&revision)
+    }
+
+    /// Revisions of the house, newest first, each compared with the one before.
+    pub async fn get_revision_diffs(
+        &self,
+        db: &DbConn,
+        hno: u32,
+    ) -> Result<Vec<RevisionDiff>, MXFError> {
+        let revisions = HouseRevisionEntity::find()
+            .filter(HouseRevisionColumn::Hno.eq(hno))
+            .order_by_asc(HouseRevisionColumn::Rno)
+            .all(db)
+            .await?;
+        let mut previous: Option<HouseListingModel> = None;
+        let mut diffs = Vec::with_capacity(revisions.len());
+        for revision in revisions {
+            let snapshot = Self::snapshot(&revision)?;
+            diffs.push(RevisionDiff {
+                rno: revision.rno,
+                hno: revision.hno,
+                reditor: revision.reditor,
+                rdate: revision.rdate,
+                changes: Self::diff(previous.as_ref(), &snapshot),
+            });
+            previous = Some(snapshot);
+        }
+        diffs.reverse();
+        Ok(diffs)
+    }
+}
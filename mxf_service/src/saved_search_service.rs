@@ -0,0 +1,302 @@
+use chrono::{Duration, Local, NaiveDateTime};
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use futures::StreamExt;
+use sea_orm::sea_query::{OnConflict, Query};
+use sea_orm::*;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use mxf_entity::{
+    AlertFrequency, BucketKind, FieldError, HouseFilter, HouseListingColumn, HouseListingEntity,
+    ListStatus, MXFError, NotificationType, SavedSearchActiveModel, SavedSearchAlertActiveModel,
+    SavedSearchAlertColumn, SavedSearchAlertEntity, SavedSearchColumn, SavedSearchData,
+    SavedSearchEntity, SavedSearchMatchActiveModel, SavedSearchMatchColumn,
+    SavedSearchMatchEntity, SavedSearchModel, SearchBuckets,
+};
+
+use crate::{BucketService, JobService, NotificationService};
+
+/// Houses written since they were last matched against the saved searches.
+/// Writers only push the house; `SavedSearchService::run_alerts` does the
+/// matching once the write is done, off the request.
+#[derive(Clone)]
+pub struct AlertQueue {
+    sender: UnboundedSender<u32>,
+    receiver: Arc<Mutex<Option<UnboundedReceiver<u32>>>>,
+}
+
+impl Default for AlertQueue {
+    fn default() -> Self {
+        let (sender, receiver) = unbounded();
+        AlertQueue { sender, receiver: Arc::new(Mutex::new(Some(receiver))) }
+    }
+}
+
+impl AlertQueue {
+    pub fn push(&self, hno: u32) {
+        if self.sender.unbounded_send(hno).is_err() {
+            println!("saved search alerts stopped, house {} not matched", hno);
+        }
+    }
+
+    /// The receiving end, for the one task running the alerts.
+    pub fn take_receiver(&self) -> Option<UnboundedReceiver<u32>> {
+        self.receiver.lock().unwrap().take()
+    }
+}
+
+pub struct SavedSearchService;
+
+impl SavedSearchService {
+    /// Houses named in one digest, to stay within the notification length.
+    const DIGEST_MAX_LISTED: usize = 20;
+    /// Longest name `saved_searches.sname` holds, in characters.
+    const MAX_NAME_LEN: usize = 64;
+    /// Name of searches saved without a filter and without a name.
+    const UNFILTERED_NAME: &'static str = "全部房源";
+    const DIGESTS_JOB: &'static str = "saved_search_digests";
+
+    pub fn init() -> Self {
+        Self {}
+    }
+
+    /// Saves the search in canonical form, buckets written out as bounds so
+    /// that later bucket edits leave it alone. Searches saved without a name
+    /// are named after their text query. If ok, returns its sno.
+    pub async fn save_search(
+        &self,
+        db: &DbConn,
+        uno: u32,
+        data: &SavedSearchData,
+        buckets: &SearchBuckets,
+    ) -> Result<u32, MXFError> {
+        let name = data.name.trim();
+        if name.chars().count() > Self::MAX_NAME_LEN {
+            return Err(MXFError::InvalidSavedSearch(vec![FieldError::new(
+                "name",
+                format!("名称不能超过 {} 个字符", Self::MAX_NAME_LEN),
+            )]));
+        }
+        let fields = HouseFilter::decode_query(data.query.trim_start_matches('?'));
+        let filter = HouseFilter::from_fields(&fields, buckets)?
+            .with_bucket_bounds(BucketKind::Floor)
+            .with_bucket_bounds(BucketKind::Area)
+            .with_bucket_bounds(BucketKind::Price);
+        let name = match name {
+            "" => match filter.to_query_text() {
+                text if text.is_empty() => Self::UNFILTERED_NAME.to_string(),
+                text => text.chars().take(Self::MAX_NAME_LEN).collect(),
+            },
+            name => name.to_string(),
+        };
+        let search = SavedSearchActiveModel {
+            sno: NotSet,
+            uno: Set(uno),
+            sname: Set(name),
+            squery: Set(filter.to_query_string()),
+            sfrequency: Set(data.frequency),
+            sdate: Set(Local::now().naive_local()),
+        };
+        let res = SavedSearchEntity::insert(search).exec(db).await?;
+        Ok(res.last_insert_id)
+    }
+
+    /// Other users' searches are reported as not found, like missing ones.
+    pub async fn delete_search(&self, db: &DbConn, sno: u32, uno: u32) -> Result<(), MXFError> {
+        SavedSearchEntity::find_by_id(sno)
+            .filter(SavedSearchColumn::Uno.eq(uno))
+            .one(db)
+            .await?
+            .ok_or(MXFError::SavedSearchNotFound(sno))?;
+        let txn = db.begin().await?;
+        SavedSearchMatchEntity::delete_many()
+            .filter(SavedSearchMatchColumn::Sno.eq(sno))
+            .exec(&txn)
+            .await?;
+        SavedSearchAlertEntity::delete_many()
+            .filter(SavedSearchAlertColumn::Sno.eq(sno))
+            .exec(&txn)
+            .await?;
+        SavedSearchEntity::delete_by_id(sno).exec(&txn).await?;
+        txn.commit().await?;
+        Ok(())
+    }
+
+    pub async fn get_searches_by_uno(
+        &self,
+        db: &DbConn,
+        uno: u32,
+    ) -> Result<Vec<SavedSearchModel>, MXFError> {
+        SavedSearchEntity::find()
+            .filter(SavedSearchColumn::Uno.eq(uno))
+            .order_by_desc(SavedSearchColumn::Sdate)
+            .all(db)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    /// Whether `hno` is among the results of the saved search, using the same
+    /// `Condition` as `/zufang`.
+    async fn matches(
+        db: &DbConn,
+        buckets: &SearchBuckets,
+        search: &SavedSearchModel,
+        hno: u32,
+    ) -> Result<bool, MXFError> {
+        let fields = HouseFilter::decode_query(&search.squery);
+        let house_filter = HouseFilter::from_fields(&fields, buckets)?;
+        let count = HouseListingEntity::find()
+            .filter(
+                Condition::from(house_filter)
+                    .add(HouseListingColumn::Hno.eq(hno))
+                    .add(HouseListingColumn::Hunlisted.eq(ListStatus::Listed)),
+            )
+            .count(db)
+            .await?;
+        Ok(count > 0)
+    }
+
+    /// Matches the houses pushed to `queue` as they come, until it is dropped.
+    pub async fn run_alerts(
+        db: DbConn,
+        bucket_service: BucketService,
+        mut queue: UnboundedReceiver<u32>,
+    ) {
+        while let Some(hno) = queue.next().await {
+            let buckets = bucket_service.get_buckets();
+            if let Err(e) = Self::alert_matches(&db, &buckets, hno).await {
+                println!("saved search alerts for house {} failed: {}", hno, e);
+            }
+        }
+    }
+
+    /// Alerts the owners of saved searches matching a listed house, once per
+    /// search: immediately, or by queueing it for the daily digest.
+    async fn alert_matches(
+        db: &DbConn,
+        buckets: &SearchBuckets,
+        hno: u32,
+    ) -> Result<(), MXFError> {
+        let Some(house) = HouseListingEntity::find_by_id(hno)
+            .filter(HouseListingColumn::Hunlisted.eq(ListStatus::Listed))
+            .one(db)
+            .await?
+        else {
+            return Ok(());
+        };
+        let searches = SavedSearchEntity::find()
+            .filter(SavedSearchColumn::Uno.ne(house.hlandlore))
+            .filter(
+                SavedSearchColumn::Sno.not_in_subquery(
+                    Query::select()
+                        .column(SavedSearchAlertColumn::Sno)
+                        .from(SavedSearchAlertEntity)
+                        .and_where(SavedSearchAlertColumn::Hno.eq(hno))
+                        .to_owned(),
+                ),
+            )
+            .all(db)
+            .await?;
+        let now = Local::now().naive_local();
+        for search in searches {
+            match Self::matches(db, buckets, &search, house.hno).await {
+                Ok(true) => (),
+                Ok(false) => continue,
+                Err(e) => {
+                    println!("saved search {} skipped: {}", search.sno, e);
+                    continue;
+                }
+            }
+            let alerted = SavedSearchAlertActiveModel {
+                sno: Set(search.sno),
+                hno: Set(hno),
+                adate: Set(now),
+            };
+            SavedSearchAlertEntity::insert(alerted)
+                .on_conflict(
+                    OnConflict::columns([SavedSearchAlertColumn::Sno, SavedSearchAlertColumn::Hno])
+                        .do_nothing()
+                        .to_owned(),
+                )
+                .exec_without_returning(db)
+                .await?;
+            match search.sfrequency {
+                AlertFrequency::Immediate => {
+                    NotificationService::notify_all(
+                        db,
+                        &[search.uno],
+                        house.hno,
+                        NotificationType::SearchMatch,
+                        &format!("您订阅的搜索「{}」有新的匹配房源", search.sname),
+                    )
+                    .await?
+                }
+                AlertFrequency::Daily => {
+                    let pending = SavedSearchMatchActiveModel {
+                        sno: Set(search.sno),
+                        hno: Set(house.hno),
+                        mdate: Set(now),
+                    };
+                    SavedSearchMatchEntity::insert(pending)
+                        .on_conflict(
+                            OnConflict::columns([
+                                SavedSearchMatchColumn::Sno,
+                                SavedSearchMatchColumn::Hno,
+                            ])
+                            .update_column(SavedSearchMatchColumn::Mdate)
+                            .to_owned(),
+                        )
+                        .exec(db)
+                        .await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends one notification per daily saved search with pending matches,
+    /// unless the digests already went out on the day of `until`.
+    pub async fn send_digests(db: &DbConn, until: NaiveDateTime) -> Result<(), MXFError> {
+        let first = until - Duration::days(1);
+        if JobService::last_run(db, Self::DIGESTS_JOB, first).await?.date() == until.date() {
+            return Ok(());
+        }
+        let pending = SavedSearchMatchEntity::find()
+            .order_by_asc(SavedSearchMatchColumn::Mdate)
+            .all(db)
+            .await?;
+        let mut by_search: BTreeMap<u32, Vec<u32>> = BTreeMap::new();
+        for m in pending.iter() {
+            by_search.entry(m.sno).or_default().push(m.hno);
+        }
+        for (sno, hnos) in by_search {
+            if let Some(search) = SavedSearchEntity::find_by_id(sno).one(db).await? {
+                let listed = hnos
+                    .iter()
+                    .take(Self::DIGEST_MAX_LISTED)
+                    .map(|h| h.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                NotificationService::notify_all(
+                    db,
+                    &[search.uno],
+                    *hnos.last().unwrap(),
+                    NotificationType::SearchMatch,
+                    &format!(
+                        "您订阅的搜索「{}」今日有 {} 套匹配房源：{}",
+                        search.sname,
+                        hnos.len(),
+                        listed
+                    ),
+                )
+                .await?;
+            }
+            SavedSearchMatchEntity::delete_many()
+                .filter(SavedSearchMatchColumn::Sno.eq(sno))
+                .filter(SavedSearchMatchColumn::Hno.is_in(hnos))
+                .exec(db)
+                .await?;
+        }
+        JobService::finish(db, Self::DIGESTS_JOB, until).await
+    }
+}
@@ -0,0 +1,199 @@
+use mini_moka::sync::Cache;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use mxf_entity::{FacetCounts, HouseFilter, HouseListingModel, SearchCacheStats};
+
+/// Page count and, once asked for, the first page of a search.
+#[derive(Clone)]
+struct CachedSearch {
+    num_pages: u64,
+    first_page: Option<Arc<Vec<HouseListingModel>>>,
+}
+
+/// Search results shared by all requests, emptied whenever a listing changes.
+/// Results also depend on orders and on the date, through the availability
+/// and price drop filters, so entries expire after a while regardless.
+#[derive(Clone)]
+pub struct SearchCache {
+    searches: Cache<String, CachedSearch>,
+    facets: Cache<String, FacetCounts>,
+    /// Bumped by every invalidation, so that results computed before one
+    /// are not stored after it.
+    generation: Arc<AtomicU64>,
+    page_hits: Arc<AtomicU64>,
+    page_misses: Arc<AtomicU64>,
+    facet_hits: Arc<AtomicU64>,
+    facet_misses: Arc<AtomicU64>,
+}
+
+impl Default for SearchCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SearchCache {
+    const TIME_TO_LIVE: Duration = Duration::from_secs(10 * 60);
+
+    pub fn new() -> Self {
+        SearchCache {
+            searches: Cache::builder().time_to_live(Self::TIME_TO_LIVE).build(),
+            facets: Cache::builder().time_to_live(Self::TIME_TO_LIVE).build(),
+            generation: Arc::new(AtomicU64::new(0)),
+            page_hits: Arc::new(AtomicU64::new(0)),
+            page_misses: Arc::new(AtomicU64::new(0)),
+            facet_hits: Arc::new(AtomicU64::new(0)),
+            facet_misses: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Canonical form of everything but the page that decides a result.
+    fn search_key(house_filter: HouseFilter<'_>, posts_per_page: u64, listed_only: bool) -> String {
+        format!(
+            "listed_only={}&per_page={}&{}",
+            listed_only,
+            posts_per_page,
+            house_filter.to_query_string()
+        )
+    }
+
+    fn record(hits: &AtomicU64, misses: &AtomicU64, hit: bool) {
+        let counter = if hit { hits } else { misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Current generation, to pass back when storing what is computed next.
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    pub fn num_pages(
+        &self,
+        house_filter: HouseFilter<'_>,
+        posts_per_page: u64,
+        listed_only: bool,
+    ) -> Option<u64> {
+        let key = Self::search_key(house_filter, posts_per_page, listed_only);
+        let num_pages = self.searches.get(&key).map(|s| s.num_pages);
+        Self::record(&self.page_hits, &self.page_misses, num_pages.is_some());
+        num_pages
+    }
+
+    /// (first page, num pages) of a search.
+    pub fn first_page(
+        &self,
+        house_filter: HouseFilter<'_>,
+        posts_per_page: u64,
+        listed_only: bool,
+    ) -> Option<(Vec<HouseListingModel>, u64)> {
+        let key = Self::search_key(house_filter, posts_per_page, listed_only);
+        let first_page = self
+            .searches
+            .get(&key)
+            .and_then(|s| s.first_page.map(|houses| (houses.as_ref().clone(), s.num_pages)));
+        Self::record(&self.page_hits, &self.page_misses, first_page.is_some());
+        first_page
+    }
+
+    /// Stores a search computed at `generation`, unless listings have changed
+    /// since.
+    pub fn insert_search(
+        &self,
+        generation: u64,
+        house_filter: HouseFilter<'_>,
+        posts_per_page: u64,
+        listed_only: bool,
+        num_pages: u64,
+        first_page: Option<&[HouseListingModel]>,
+    ) {
+        if generation != self.generation() {
+            return;
+        }
+        let key = Self::search_key(house_filter, posts_per_page, listed_only);
+        let first_page = first_page
+            .map(|houses| Arc::new(houses.to_vec()))
+            .or_else(|| self.searches.get(&key).and_then(|s| s.first_page));
+        self.searches.insert(key.clone(), CachedSearch { num_pages, first_page });
+        // An invalidation may have slipped in since the check above
+        if generation != self.generation() {
+            self.searches.invalidate(&key);
+        }
+    }
+
+    pub fn facets(&self, house_filter: HouseFilter<'_>) -> Option<FacetCounts> {
+        let facets = self.facets.get(&house_filter.to_query_string());
+        Self::record(&self.facet_hits, &self.facet_misses, facets.is_some());
+        facets
+    }
+
+    pub fn insert_facets(&self, generation: u64, house_filter: HouseFilter<'_>, facets: FacetCounts) {
+        if generation != self.generation() {
+            return;
+        }
+        let key = house_filter.to_query_string();
+        self.facets.insert(key.clone(), facets);
+        if generation != self.generation() {
+            self.facets.invalidate(&key);
+        }
+    }
+
+    /// Drops every cached result; called after any listing is written.
+    pub fn invalidate(&self) {
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        self.searches.invalidate_all();
+        self.facets.invalidate_all();
+    }
+
+    pub fn stats(&self) -> SearchCacheStats {
+        SearchCacheStats {
+            page_hits: self.page_hits.load(Ordering::Relaxed),
+            page_misses: self.page_misses.load(Ordering::Relaxed),
+            facet_hits: self.facet_hits.load(Ordering::Relaxed),
+            facet_misses: self.facet_misses.load(Ordering::Relaxed),
+            invalidations: self.generation(),
+            entries: self.searches.entry_count() + self.facets.entry_count(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mxf_entity::SearchBuckets;
+
+    #[test]
+    fn invalidation_drops_results_and_stale_inserts() {
+        let buckets = SearchBuckets::default();
+        let fields = HouseFilter::decode_query("district=海淀区");
+        let filter = HouseFilter::from_fields(&fields, &buckets).unwrap();
+        let cache = SearchCache::new();
+
+        assert_eq!(cache.num_pages(filter, 10, true), None);
+        cache.insert_search(cache.generation(), filter, 10, true, 3, None);
+        assert_eq!(cache.num_pages(filter, 10, true), Some(3));
+        // Only a stored first page is a hit for it
+        assert_eq!(cache.first_page(filter, 10, true), None);
+        // Other page sizes and listing scopes are other searches
+        assert_eq!(cache.num_pages(filter, 20, true), None);
+        assert_eq!(cache.num_pages(filter, 10, false), None);
+        cache.insert_facets(cache.generation(), filter, FacetCounts::default());
+        assert!(cache.facets(filter).is_some());
+
+        let generation = cache.generation();
+        cache.invalidate();
+        assert_eq!(cache.num_pages(filter, 10, true), None);
+        assert!(cache.facets(filter).is_none());
+        // Results computed before the invalidation are not stored
+        cache.insert_search(generation, filter, 10, true, 3, None);
+        cache.insert_facets(generation, filter, FacetCounts::default());
+        assert_eq!(cache.num_pages(filter, 10, true), None);
+        assert!(cache.facets(filter).is_none());
+
+        let stats = cache.stats();
+        assert_eq!((stats.page_hits, stats.page_misses), (1, 6));
+        assert_eq!((stats.facet_hits, stats.facet_misses), (1, 2));
+        assert_eq!(stats.invalidations, 1);
+    }
+}
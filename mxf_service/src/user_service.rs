@@ -73,10 +73,10 @@ impl UserService {
             .time_to_live(std::time::Duration::from_secs(24 * 60 * 60))
             .build();
 
-        return UserService {
+        UserService {
             uno_cache,
             // token: TokenPair::new(),
-        };
+        }
     }
 
     async fn get_user_by_name(
@@ -111,9 +111,8 @@ impl UserService {
     ) -> Result<UserModel, MXFError> {
         if !self.uno_cache.contains_key(&String::from(username_or_uno)) {
             let mut cond = Condition::any().add(UserColumn::Uname.eq(username_or_uno));
-            match username_or_uno.parse::<u32>() {
-                Ok(u) => cond = cond.add(UserColumn::Uno.eq(u)),
-                Err(_) => (),
+            if let Ok(u) = username_or_uno.parse::<u32>() {
+                cond = cond.add(UserColumn::Uno.eq(u));
             }
             let user = UserEntity::find()
                 .filter(cond)
@@ -153,7 +152,7 @@ impl UserService {
         register_data: &RegisterData<'_>,
     ) -> Result<UserModel, MXFError> {
         match self.get_user_by_name(db, register_data.username).await {
-            Ok(_) => return Err(MXFError::UserAlreadyExists(register_data.username.into())),
+            Ok(_) => Err(MXFError::UserAlreadyExists(register_data.username.into())),
             Err(MXFError::UserNotFound(_)) => {
                 let uno = UserEntity::find()
                     .column(UserColumn::Uno)
@@ -165,10 +164,10 @@ impl UserService {
                 println!("!!!!{:?}", uno);
                 match register_data.into_active_model(uno) {
                     Ok(am) => am.insert(db).await.map_err(|e| e.into()),
-                    Err(e) => return Err(e),
+                    Err(e) => Err(e),
                 }
             }
-            Err(e) => return Err(e),
+            Err(e) => Err(e),
         }
     }
 